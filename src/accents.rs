@@ -0,0 +1,88 @@
+use std::borrow::Cow;
+
+// Base letters of U+00C0..=U+017F, `-` where the character has no
+// canonical decomposition (`Æ`, `Ø`, `ß`, `Ł`, ...)
+const LATIN_BASES: &[u8; 192] = b"AAAAAA-CEEEEIIII-NOOOOO--UUUUY--aaaaaa-ceeeeiiii-nooooo--uuuuy-y\
+AaAaAaCcCcCcCcDd--EeEeEeEeEeGgGgGgGgHh--IiIiIiIiI---JjKk-LlLlLl----NnNnNn---OoOo\
+Oo--RrRrRrSsSsSsSsTtTt--UuUuUuUuUuUuWwYyYZzZzZz-";
+
+// The same for Latin Extended-B, U+0180..=U+024F (`ơ`, `ư`, `ǎ`, `ș`, ...).
+// `Ǣ` and the like, whose base isn't ASCII, are kept too
+const LATIN_EXTENDED_B_BASES: &[u8; 208] = b"--------------------------------Oo-------------Uu-----\
+-----------------------AaIiOoUuUuUuUuUu-AaAa----GgKkOoOo--j---Gg--NnAa----AaAaEe\
+EeIiIiOoOoRrRrUuUuSsTt--Hh------AaEeOoOoOoOoYy----------------------------";
+
+// The same for Latin Extended Additional, U+1E00..=U+1EFF, which has the
+// Vietnamese letters with two marks (`ệ`, `ở`, ...)
+const LATIN_ADDITIONAL_BASES: &[u8; 256] = b"AaBbBbBbCcDdDdDdDdDdEeEeEeEeEeFfGgHhHhHhHhHhIiIiKkKkKk\
+LlLlLlLlMmMmMmNnNnNnNnOoOoOoOoPpPpRrRrRrRrSsSsSsSsSsTtTtTtTtUuUuUuUuUuVvVvWwWwWw\
+WwWwXxXxYyZzZzZzhtwy------AaAaAaAaAaAaAaAaAaAaAaAaEeEeEeEeEeEeEeEeIiIiOoOoOoOoOo\
+OoOoOoOoOoOoOoUuUuUuUuUuUuUuYyYyYyYy------";
+
+/// `text` with accents removed, as NFD decomposition followed by dropping
+/// combining marks would: precomposed letters from Latin-1, Latin
+/// Extended-A and -B and Latin Extended Additional become their base letter
+/// and combining diacritics (U+0300..=U+036F) are dropped; other letters
+/// are kept. Alongside it, the byte offset in `text` of each byte of the
+/// result plus one for its end, so spans found in the result can be mapped
+/// back. Borrowed when there was nothing to remove.
+pub(crate) fn strip_accents(text: &str) -> (Cow<'_, str>, Option<Vec<usize>>) {
+    if text.is_ascii() || text.chars().all(|c| base_of(c) == Some(c)) {
+        return (Cow::Borrowed(text), None);
+    }
+
+    let mut stripped = String::with_capacity(text.len());
+    let mut offsets = Vec::with_capacity(text.len() + 1);
+    for (i, c) in text.char_indices() {
+        let Some(base) = base_of(c) else {
+            continue;
+        };
+        stripped.push(base);
+        offsets.extend(std::iter::repeat_n(i, base.len_utf8()));
+    }
+    offsets.push(text.len());
+
+    (Cow::Owned(stripped), Some(offsets))
+}
+
+/// The character with its accent removed, `None` for a combining mark.
+fn base_of(c: char) -> Option<char> {
+    match c as u32 {
+        0x0300..=0x036F => None,
+        code @ 0x00C0..=0x017F => Some(base_in(LATIN_BASES, code - 0x00C0, c)),
+        code @ 0x0180..=0x024F => Some(base_in(LATIN_EXTENDED_B_BASES, code - 0x0180, c)),
+        code @ 0x1E00..=0x1EFF => Some(base_in(LATIN_ADDITIONAL_BASES, code - 0x1E00, c)),
+        _ => Some(c),
+    }
+}
+
+fn base_in(bases: &[u8], index: u32, c: char) -> char {
+    match bases[index as usize] {
+        b'-' => c,
+        base => base as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_accents() {
+        assert_eq!(strip_accents("plain"), (Cow::Borrowed("plain"), None));
+        assert_eq!(strip_accents("Straße Ø"), (Cow::Borrowed("Straße Ø"), None));
+
+        let (stripped, offsets) = strip_accents("Café Ŝtr");
+        assert_eq!(stripped, "Cafe Str");
+        assert_eq!(offsets.unwrap(), vec![0, 1, 2, 3, 5, 6, 8, 9, 10]);
+
+        // Already decomposed: `e` followed by a combining acute accent
+        let (stripped, offsets) = strip_accents("cafe\u{301}!");
+        assert_eq!(stripped, "cafe!");
+        assert_eq!(offsets.unwrap(), vec![0, 1, 2, 3, 6, 7]);
+
+        // Vietnamese: Latin Extended-B and Extended Additional letters
+        let (stripped, _) = strip_accents("Tiếng Việt người Đà Nẵng");
+        assert_eq!(stripped, "Tieng Viet nguoi Đa Nang");
+    }
+}
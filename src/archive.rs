@@ -0,0 +1,377 @@
+use crate::binary::looks_binary;
+use crate::inflate::inflate;
+use crate::types::{FsReadError, FsWarning, WarningKind};
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+
+const ZIP_LOCAL_HEADER: u32 = 0x0403_4b50;
+const ZIP_CENTRAL_HEADER: u32 = 0x0201_4b50;
+const ZIP_END_OF_DIRECTORY: u32 = 0x0605_4b50;
+// Most a zip member, or the whole tar stream of a .tar.gz, may inflate to
+const MAX_INFLATED_SIZE: usize = 256 * 1024 * 1024;
+
+/// Whether `path` names an archive the search can look inside, going by its
+/// extension: `.zip`, `.tar.gz` or `.tgz`.
+pub fn is_archive(path: &Path) -> bool {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_ascii_lowercase());
+    name.is_some_and(|n| n.ends_with(".zip") || n.ends_with(".tar.gz") || n.ends_with(".tgz"))
+}
+
+/// Calls `visit` with the name and content of every regular file in the
+/// archive that looks like UTF-8 text. The archive is decompressed in memory;
+/// nothing is written to disk. Binary and non-UTF-8 members are skipped, as
+/// are zip members stored with a method other than store or deflate. Zip
+/// members inflating past `MAX_INFLATED_SIZE` are skipped too, and so is a
+/// whole .tar.gz whose tar stream does; each comes back as a warning.
+pub fn for_each_text_member(
+    path: &Path,
+    visit: impl FnMut(&str, &str),
+) -> Result<Vec<FsWarning>, FsReadError> {
+    text_members(path, MAX_INFLATED_SIZE, visit)
+}
+
+fn text_members(
+    path: &Path,
+    max_inflated: usize,
+    mut visit: impl FnMut(&str, &str),
+) -> Result<Vec<FsWarning>, FsReadError> {
+    let data = std::fs::read(path)?;
+    let mut visit_text = |name: &str, content: &[u8]| {
+        if looks_binary(content) {
+            return;
+        }
+        if let Ok(text) = std::str::from_utf8(content) {
+            visit(name, text);
+        }
+    };
+
+    let archive = path.to_string_lossy();
+    let oversized = if archive.to_ascii_lowercase().ends_with(".zip") {
+        zip_members(&data, max_inflated, &mut visit_text)?
+            .into_iter()
+            .map(|name| format!("{}!{}", archive, name))
+            .collect()
+    } else {
+        match gunzip(&data, max_inflated) {
+            Ok(tar) => {
+                tar_members(&tar, &mut visit_text)?;
+                Vec::new()
+            }
+            Err(e) if e.kind() == ErrorKind::FileTooLarge => vec![archive.to_string()],
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    Ok(oversized
+        .into_iter()
+        .map(|path| FsWarning {
+            message: format!("{} inflates to more than {} bytes; skipped", path, max_inflated),
+            path,
+            kind: WarningKind::Other,
+        })
+        .collect())
+}
+
+fn corrupt(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("corrupt archive: {}", message))
+}
+
+fn u16_at(data: &[u8], pos: usize) -> std::io::Result<u16> {
+    let bytes = data.get(pos..pos + 2).ok_or_else(|| corrupt("unexpected end"))?;
+    Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> std::io::Result<u32> {
+    let bytes = data.get(pos..pos + 4).ok_or_else(|| corrupt("unexpected end"))?;
+    Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// Strips the gzip header (RFC 1952) and inflates the first member. The
+/// trailing CRC isn't checked.
+fn gunzip(data: &[u8], max_output: usize) -> std::io::Result<Vec<u8>> {
+    const FHCRC: u8 = 0x02;
+    const FEXTRA: u8 = 0x04;
+    const FNAME: u8 = 0x08;
+    const FCOMMENT: u8 = 0x10;
+
+    if data.len() < 10 || data[..3] != [0x1f, 0x8b, 8] {
+        return Err(corrupt("not a gzip stream"));
+    }
+    let flags = data[3];
+    let mut pos = 10;
+
+    if flags & FEXTRA != 0 {
+        pos += 2 + u16_at(data, pos)? as usize;
+    }
+    for flag in [FNAME, FCOMMENT] {
+        if flags & flag != 0 {
+            let rest = data.get(pos..).ok_or_else(|| corrupt("unexpected end"))?;
+            pos += memchr::memchr(0, rest).ok_or_else(|| corrupt("unterminated header"))? + 1;
+        }
+    }
+    if flags & FHCRC != 0 {
+        pos += 2;
+    }
+
+    inflate(data.get(pos..).ok_or_else(|| corrupt("unexpected end"))?, max_output)
+}
+
+/// Regular files in a tar stream, with names from GNU long-name (`L`) and
+/// pax (`x`) headers when present.
+fn tar_members(data: &[u8], visit: &mut impl FnMut(&str, &[u8])) -> std::io::Result<()> {
+    let mut pos = 0;
+    let mut long_name = None;
+
+    while let Some(header) = data.get(pos..pos + 512) {
+        // The archive ends with zeroed blocks
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let size = parse_octal(&header[124..136])?;
+        let body_start = pos + 512;
+        let body = data
+            .get(body_start..body_start + size)
+            .ok_or_else(|| corrupt("unexpected end"))?;
+
+        let name = long_name.take().unwrap_or_else(|| {
+            let name = field_str(&header[..100]);
+            let prefix = field_str(&header[345..500]);
+            if &header[257..263] == b"ustar\0" && !prefix.is_empty() {
+                format!("{}/{}", prefix, name)
+            } else {
+                name
+            }
+        });
+
+        match header[156] {
+            b'0' | 0 => visit(&name, body),
+            b'L' => long_name = Some(field_str(body)),
+            b'x' => long_name = pax_path(body),
+            _ => {}
+        }
+
+        pos = body_start + size.div_ceil(512) * 512;
+    }
+
+    Ok(())
+}
+
+/// NUL-terminated header field.
+fn field_str(field: &[u8]) -> String {
+    let end = memchr::memchr(0, field).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn parse_octal(field: &[u8]) -> std::io::Result<usize> {
+    let digits = field_str(field);
+    usize::from_str_radix(digits.trim(), 8).map_err(|_| corrupt("invalid size field"))
+}
+
+/// `path` record of a pax extended header; records are `<len> <key>=<value>\n`.
+fn pax_path(body: &[u8]) -> Option<String> {
+    String::from_utf8_lossy(body)
+        .lines()
+        .find_map(|record| record.split_once(' ')?.1.strip_prefix("path=").map(String::from))
+}
+
+/// Members listed in the zip central directory. Zip64 archives aren't
+/// supported. Returns the names of members skipped for inflating past
+/// `max_inflated` bytes.
+fn zip_members(
+    data: &[u8],
+    max_inflated: usize,
+    visit: &mut impl FnMut(&str, &[u8]),
+) -> std::io::Result<Vec<String>> {
+    // The end record is at least 22 bytes and may be followed by a comment
+    let end = (0..data.len().saturating_sub(21))
+        .rev()
+        .find(|&i| u32_at(data, i).ok() == Some(ZIP_END_OF_DIRECTORY))
+        .ok_or_else(|| corrupt("missing end of central directory"))?;
+    let count = u16_at(data, end + 10)? as usize;
+    let mut pos = u32_at(data, end + 16)? as usize;
+    let mut oversized = Vec::new();
+
+    for _ in 0..count {
+        if u32_at(data, pos)? != ZIP_CENTRAL_HEADER {
+            return Err(corrupt("bad central directory entry"));
+        }
+        let flags = u16_at(data, pos + 8)?;
+        let method = u16_at(data, pos + 10)?;
+        let compressed_size = u32_at(data, pos + 20)? as usize;
+        let name_len = u16_at(data, pos + 28)? as usize;
+        let extra_len = u16_at(data, pos + 30)? as usize;
+        let comment_len = u16_at(data, pos + 32)? as usize;
+        let local = u32_at(data, pos + 42)? as usize;
+        let name = data
+            .get(pos + 46..pos + 46 + name_len)
+            .ok_or_else(|| corrupt("unexpected end"))?;
+        let name = String::from_utf8_lossy(name);
+        pos += 46 + name_len + extra_len + comment_len;
+
+        // Directories end in a slash; bit 0 marks encrypted members
+        if name.ends_with('/') || flags & 1 != 0 {
+            continue;
+        }
+
+        if u32_at(data, local)? != ZIP_LOCAL_HEADER {
+            return Err(corrupt("bad local header"));
+        }
+        let name_len = u16_at(data, local + 26)? as usize;
+        let extra_len = u16_at(data, local + 28)? as usize;
+        let start = local + 30 + name_len + extra_len;
+        let raw = data
+            .get(start..start + compressed_size)
+            .ok_or_else(|| corrupt("unexpected end"))?;
+
+        match method {
+            0 => visit(&name, raw),
+            8 => match inflate(raw, max_inflated) {
+                Ok(content) => visit(&name, &content),
+                Err(e) if e.kind() == ErrorKind::FileTooLarge => oversized.push(name.into_owned()),
+                Err(e) => return Err(e),
+            },
+            _ => {}
+        }
+    }
+
+    Ok(oversized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{search_pattern, SearchOptions};
+    use crate::test_util::TempDir;
+    use crate::types::FsReadResult;
+    use std::path::PathBuf;
+
+    /// A single stored DEFLATE block holding `content`.
+    fn stored_deflate(content: &[u8]) -> Vec<u8> {
+        let len = content.len() as u16;
+        let mut out = vec![1];
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend(content);
+        out
+    }
+
+    fn tar_entry(name: &str, content: &[u8]) -> Vec<u8> {
+        let mut header = [0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        let size = format!("{:011o}\0", content.len());
+        header[124..136].copy_from_slice(size.as_bytes());
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+
+        let mut entry = header.to_vec();
+        entry.extend(content);
+        entry.resize(512 + content.len().div_ceil(512) * 512, 0);
+        entry
+    }
+
+    fn members(path: &Path) -> Vec<(String, String)> {
+        let mut found = Vec::new();
+        for_each_text_member(path, |name, text| found.push((name.to_string(), text.to_string())))
+            .unwrap();
+        found
+    }
+
+    /// `data.tar.gz` in a fresh directory with a text and a binary member.
+    fn write_tar_gz() -> (TempDir, PathBuf) {
+        let mut tar = tar_entry("docs/a.txt", b"hello\nworld\n");
+        tar.extend(tar_entry("bin/tool", b"\x7fELF\0\0world"));
+        tar.extend([0u8; 1024]);
+
+        // gzip header with FNAME set, then the deflate data and a dummy trailer
+        let mut gz = vec![0x1f, 0x8b, 8, 0x08, 0, 0, 0, 0, 0, 0xff];
+        gz.extend(b"data.tar\0");
+        gz.extend(stored_deflate(&tar));
+        gz.extend([0u8; 8]);
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("data.tar.gz");
+        std::fs::write(&path, gz).unwrap();
+        (temp, path)
+    }
+
+    #[test]
+    fn test_tar_gz_members() {
+        let (_temp, path) = write_tar_gz();
+
+        assert!(is_archive(&path));
+        assert_eq!(
+            members(&path),
+            vec![("docs/a.txt".to_string(), "hello\nworld\n".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_zip_members() {
+        // zipfile.ZipFile(..., "w", zipfile.ZIP_DEFLATED) holding a.txt ("abc\n" * 20)
+        // and an empty dir/ entry
+        let zip: &[u8] = &[
+            80, 75, 3, 4, 20, 0, 0, 0, 8, 0, 0, 0, 33, 0, 243, 166, 167, 58, 9, 0, 0, 0, 80, 0,
+            0, 0, 5, 0, 0, 0, 97, 46, 116, 120, 116, 75, 76, 74, 230, 74, 164, 34, 6, 0, 80, 75,
+            3, 4, 20, 0, 0, 0, 0, 0, 0, 0, 33, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0,
+            100, 105, 114, 47, 80, 75, 1, 2, 20, 3, 20, 0, 0, 0, 8, 0, 0, 0, 33, 0, 243, 166,
+            167, 58, 9, 0, 0, 0, 80, 0, 0, 0, 5, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 1, 0, 0,
+            0, 0, 97, 46, 116, 120, 116, 80, 75, 1, 2, 20, 3, 20, 0, 0, 0, 0, 0, 0, 0, 33, 0, 0,
+            0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 128, 1, 44, 0,
+            0, 0, 100, 105, 114, 47, 80, 75, 5, 6, 0, 0, 0, 0, 2, 0, 2, 0, 101, 0, 0, 0, 78, 0,
+            0, 0, 0, 0,
+        ];
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("bundle.zip");
+        std::fs::write(&path, zip).unwrap();
+
+        assert_eq!(members(&path), vec![("a.txt".to_string(), "abc\n".repeat(20))]);
+
+        // a.txt inflates to 80 bytes
+        let mut visited = 0;
+        let warnings = text_members(&path, 79, |_, _| visited += 1).unwrap();
+        assert_eq!(visited, 0);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].path.ends_with("bundle.zip!a.txt"));
+    }
+
+    #[test]
+    fn test_tar_gz_too_large() {
+        let (_temp, path) = write_tar_gz();
+
+        let mut visited = 0;
+        let warnings = text_members(&path, 1024, |_, _| visited += 1).unwrap();
+        assert_eq!(visited, 0);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].path, path.to_string_lossy());
+    }
+
+    #[test]
+    fn test_search_archives() {
+        let (temp, _path) = write_tar_gz();
+        let options = SearchOptions {
+            search_archives: true,
+            ..Default::default()
+        };
+
+        if let FsReadResult::Search { matches, .. } =
+            search_pattern(temp.path(), "world", &options).unwrap()
+        {
+            assert_eq!(matches.len(), 1);
+            assert!(matches[0].file_path.ends_with("data.tar.gz!docs/a.txt"));
+            assert_eq!(matches[0].line_number, 2);
+        } else {
+            panic!("Expected Search result");
+        }
+
+        // Off by default: the archive fails to decode and is only warned about
+        if let FsReadResult::Search { matches, warnings, .. } =
+            search_pattern(temp.path(), "world", &SearchOptions::default()).unwrap()
+        {
+            assert!(matches.is_empty());
+            assert_eq!(warnings.len(), 1);
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use crate::types::{kind_check, FsReadError, FsReadResult, PathKind};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Content with a NUL byte in this many leading bytes is treated as binary.
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Reads raw bytes (no UTF-8 decoding), stopping at `max_bytes` if set.
+pub fn read_base64(path: &Path, max_bytes: Option<u64>) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let file = File::open(path)?;
+    let file_size = file.metadata()?.len();
+    let limit = max_bytes.unwrap_or(u64::MAX);
+
+    let mut bytes = Vec::with_capacity(file_size.min(limit) as usize);
+    file.take(limit).read_to_end(&mut bytes)?;
+
+    let bytes_read = bytes.len() as u64;
+
+    Ok(FsReadResult::Base64 {
+        content_b64: encode_base64(&bytes),
+        bytes_read,
+        truncated: bytes_read < file_size,
+    })
+}
+
+/// Whether `content` looks binary: a NUL byte in its first 8 KiB.
+pub(crate) fn looks_binary(content: &[u8]) -> bool {
+    memchr::memchr(0, &content[..content.len().min(BINARY_SNIFF_BYTES)]).is_some()
+}
+
+/// `looks_binary` for a file, reading no more than the sniffed prefix.
+pub(crate) fn is_binary_file(path: &Path) -> io::Result<bool> {
+    let mut head = Vec::with_capacity(BINARY_SNIFF_BYTES);
+    File::open(path)?
+        .take(BINARY_SNIFF_BYTES as u64)
+        .read_to_end(&mut head)?;
+    Ok(looks_binary(&head))
+}
+
+/// Standard (RFC 4648) base64 with padding.
+pub(crate) fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        out.push(BASE64_ALPHABET[(n >> 18) as usize & 63] as char);
+        out.push(BASE64_ALPHABET[(n >> 12) as usize & 63] as char);
+        if chunk.len() > 1 {
+            out.push(BASE64_ALPHABET[(n >> 6) as usize & 63] as char);
+        } else {
+            out.push('=');
+        }
+        if chunk.len() > 2 {
+            out.push(BASE64_ALPHABET[n as usize & 63] as char);
+        } else {
+            out.push('=');
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_encode_base64() {
+        assert_eq!(encode_base64(b""), "");
+        assert_eq!(encode_base64(b"f"), "Zg==");
+        assert_eq!(encode_base64(b"fo"), "Zm8=");
+        assert_eq!(encode_base64(b"foo"), "Zm9v");
+        assert_eq!(encode_base64(b"foobar"), "Zm9vYmFy");
+        assert_eq!(encode_base64(&[0xff, 0x00, 0xfe]), "/wD+");
+    }
+
+    #[test]
+    fn test_read_base64_truncates() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("icon.bin");
+        fs::write(&file_path, [0x89, b'P', b'N', b'G', 0x00, 0xff]).unwrap();
+
+        let result = read_base64(&file_path, Some(3)).unwrap();
+
+        if let FsReadResult::Base64 { content_b64, bytes_read, truncated } = result {
+            assert_eq!(content_b64, encode_base64(&[0x89, b'P', b'N']));
+            assert_eq!(bytes_read, 3);
+            assert!(truncated);
+        } else {
+            panic!("Expected Base64 result");
+        }
+    }
+}
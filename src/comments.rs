@@ -0,0 +1,403 @@
+use crate::language::{detect_language, Language};
+use crate::line::with_file_content;
+use crate::types::{kind_check, FsReadError, FsReadResult, PathKind, SyntaxScope};
+use std::path::Path;
+
+/// Lexical rules just deep enough to tell comments from string contents.
+struct Syntax {
+    line_comment: &'static str,
+    block_comment: Option<(&'static str, &'static str)>,
+    nested_blocks: bool,
+    /// Python `'''`/`"""` strings
+    triple_quotes: bool,
+    /// JS template literals
+    backtick_strings: bool,
+    /// Rust raw strings, char literals and lifetimes
+    rust_literals: bool,
+}
+
+const RUST: Syntax = Syntax {
+    line_comment: "//",
+    block_comment: Some(("/*", "*/")),
+    nested_blocks: true,
+    triple_quotes: false,
+    backtick_strings: false,
+    rust_literals: true,
+};
+
+const PYTHON: Syntax = Syntax {
+    line_comment: "#",
+    block_comment: None,
+    nested_blocks: false,
+    triple_quotes: true,
+    backtick_strings: false,
+    rust_literals: false,
+};
+
+// Regex literals aren't recognised, so a `//` or `/*` inside one is taken
+// for a comment
+const JS: Syntax = Syntax {
+    line_comment: "//",
+    block_comment: Some(("/*", "*/")),
+    nested_blocks: false,
+    triple_quotes: false,
+    backtick_strings: true,
+    rust_literals: false,
+};
+
+/// Returns the file with line and block comments removed. Lines left blank
+/// by the removal are dropped and counted; other languages come back
+/// unchanged.
+pub fn strip_comments(path: &Path) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let syntax = detect_language(path).and_then(syntax_for);
+
+    let (content, removed_comment_lines) = with_file_content(path, |c| {
+        Ok(match syntax {
+            Some(syntax) => strip(c, syntax),
+            None => (c.lines().collect::<Vec<_>>().join("\n"), 0),
+        })
+    })?;
+
+    Ok(FsReadResult::StripComments {
+        total_lines: content.lines().count(),
+        content,
+        removed_comment_lines,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+// Line comment starts common enough to guess with when the language has no
+// `Syntax` here
+const COMMENT_PREFIXES: &[&str] = &["//", "#", "--", ";", "%", "/*", "*", "<!--"];
+
+/// Counts the file's lines as comment, code or blank. A line with any code
+/// on it is code, even if it also holds a comment; string literals count as
+/// code. Languages without lexer rules are classified by whether the line
+/// starts with a common comment prefix, and flagged `heuristic`.
+pub fn comment_ratio(path: &Path) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+    let language = detect_language(path);
+
+    with_file_content(path, |content| {
+        let masked = mask_outside(content, language, SyntaxScope::Comments);
+        let (mut comment_lines, mut code_lines, mut blank_lines) = (0, 0, 0);
+
+        let mut masked_lines = masked.as_deref().map(str::lines);
+
+        for line in content.lines() {
+            let is_comment = match masked_lines.as_mut().and_then(Iterator::next) {
+                // Nothing but whitespace where everything but comments was
+                // blanked out
+                Some(comments) => line
+                    .bytes()
+                    .zip(comments.bytes())
+                    .all(|(original, kept)| kept != b' ' || original.is_ascii_whitespace()),
+                None => COMMENT_PREFIXES.iter().any(|p| line.trim_start().starts_with(p)),
+            };
+            if line.trim().is_empty() {
+                blank_lines += 1;
+            } else if is_comment {
+                comment_lines += 1;
+            } else {
+                code_lines += 1;
+            }
+        }
+
+        Ok(FsReadResult::CommentRatio {
+            comment_lines,
+            code_lines,
+            blank_lines,
+            ratio: (code_lines > 0).then(|| comment_lines as f64 / code_lines as f64),
+            heuristic: masked.is_none(),
+        })
+    })
+}
+
+fn syntax_for(language: Language) -> Option<&'static Syntax> {
+    match language {
+        Language::Rust => Some(&RUST),
+        Language::Python => Some(&PYTHON),
+        Language::JavaScript | Language::TypeScript => Some(&JS),
+        _ => None,
+    }
+}
+
+/// `source` with everything outside `scope` replaced by spaces, byte for
+/// byte, and line breaks kept, so lines and offsets match the original.
+/// `None` for languages `strip_comments` doesn't understand either.
+pub(crate) fn mask_outside(
+    source: &str,
+    language: Option<Language>,
+    scope: SyntaxScope,
+) -> Option<String> {
+    let syntax = syntax_for(language?)?;
+    let mut out = String::with_capacity(source.len());
+    let mut i = 0;
+
+    while i < source.len() {
+        let rest = &source[i..];
+        let (end, kind) = if rest.starts_with(syntax.line_comment) {
+            (i + rest.find('\n').unwrap_or(rest.len()), SyntaxScope::Comments)
+        } else if let Some((open, close)) =
+            syntax.block_comment.filter(|(open, _)| rest.starts_with(open))
+        {
+            let end = block_comment_end(source, i, open, close, syntax.nested_blocks);
+            (end, SyntaxScope::Comments)
+        } else if let Some(end) = literal_end(source, i, syntax) {
+            (end, SyntaxScope::Strings)
+        } else {
+            (i + char_len(source, i), SyntaxScope::Code)
+        };
+
+        if kind == scope {
+            out.push_str(&source[i..end]);
+        } else {
+            for c in source[i..end].chars() {
+                match c {
+                    '\n' | '\r' => out.push(c),
+                    _ => out.extend(std::iter::repeat_n(' ', c.len_utf8())),
+                }
+            }
+        }
+        i = end;
+    }
+
+    Some(out)
+}
+
+fn strip(source: &str, syntax: &Syntax) -> (String, usize) {
+    let bytes = source.as_bytes();
+    let mut out = String::with_capacity(source.len());
+    // Whether each output line had comment text removed from it
+    let mut commented = vec![false];
+    let mut i = 0;
+
+    // Every delimiter is ASCII, so slicing at them keeps `out` valid UTF-8
+    while i < bytes.len() {
+        let rest = &source[i..];
+
+        if rest.starts_with(syntax.line_comment) {
+            *commented.last_mut().unwrap() = true;
+            i += rest.find('\n').unwrap_or(rest.len());
+        } else if let Some((open, close)) =
+            syntax.block_comment.filter(|(open, _)| rest.starts_with(open))
+        {
+            *commented.last_mut().unwrap() = true;
+            let end = block_comment_end(source, i, open, close, syntax.nested_blocks);
+            let newlines = source[i..end].matches('\n').count();
+            out.extend(std::iter::repeat_n('\n', newlines));
+            commented.extend(std::iter::repeat_n(true, newlines));
+            // Keep `a/* x */b` from fusing into `ab`
+            let joins_tokens = newlines == 0
+                && out.chars().last().is_some_and(|c| !c.is_whitespace())
+                && source[end..].chars().next().is_some_and(|c| !c.is_whitespace());
+            if joins_tokens {
+                out.push(' ');
+            }
+            i = end;
+        } else {
+            let end = literal_end(source, i, syntax).unwrap_or(i + char_len(source, i));
+            let literal = &source[i..end];
+            commented.extend(literal.matches('\n').map(|_| false));
+            out.push_str(literal);
+            i = end;
+        }
+    }
+
+    let mut kept = Vec::new();
+    let mut removed = 0;
+
+    for (line, had_comment) in out.lines().zip(commented) {
+        if !had_comment {
+            kept.push(line);
+        } else if line.trim().is_empty() {
+            removed += 1;
+        } else {
+            kept.push(line.trim_end());
+        }
+    }
+
+    (kept.join("\n"), removed)
+}
+
+fn block_comment_end(source: &str, start: usize, open: &str, close: &str, nested: bool) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+
+    while i < source.len() {
+        let rest = &source[i..];
+        if rest.starts_with(close) {
+            depth -= 1;
+            i += close.len();
+            if depth == 0 {
+                return i;
+            }
+        } else if rest.starts_with(open) && (nested || depth == 0) {
+            depth += 1;
+            i += open.len();
+        } else {
+            i += char_len(source, i);
+        }
+    }
+
+    // Unterminated comments run to the end of the file
+    source.len()
+}
+
+/// End of the string or char literal starting at `start`, if one does.
+fn literal_end(source: &str, start: usize, syntax: &Syntax) -> Option<usize> {
+    let rest = &source[start..];
+    let bytes = rest.as_bytes();
+
+    if syntax.triple_quotes && (rest.starts_with("\"\"\"") || rest.starts_with("'''")) {
+        return Some(quoted_end(source, start + 3, &rest[..3], true));
+    }
+
+    if syntax.rust_literals {
+        if let Some(end) = rust_raw_string_end(source, start) {
+            return Some(end);
+        }
+        if bytes[0] == b'\'' {
+            return rust_char_end(source, start);
+        }
+    }
+
+    match bytes[0] {
+        b'"' => Some(quoted_end(source, start + 1, "\"", true)),
+        b'\'' => Some(quoted_end(source, start + 1, "'", true)),
+        b'`' if syntax.backtick_strings => Some(quoted_end(source, start + 1, "`", true)),
+        _ => None,
+    }
+}
+
+/// Scans from `i` for `close`, honouring backslash escapes when `escapes`.
+fn quoted_end(source: &str, mut i: usize, close: &str, escapes: bool) -> usize {
+    while i < source.len() {
+        let rest = &source[i..];
+        if escapes && rest.starts_with('\\') {
+            i += 1 + source[i + 1..].chars().next().map_or(0, char::len_utf8);
+        } else if rest.starts_with(close) {
+            return i + close.len();
+        } else {
+            i += char_len(source, i);
+        }
+    }
+    source.len()
+}
+
+/// `r"..."`, `r#"..."#` and their `b`-prefixed forms.
+fn rust_raw_string_end(source: &str, start: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let is_ident = |b: u8| b.is_ascii_alphanumeric() || b == b'_';
+
+    let r = match bytes[start] {
+        b'b' if bytes.get(start + 1) == Some(&b'r') => start + 1,
+        b'r' => start,
+        _ => return None,
+    };
+    if start > 0 && is_ident(bytes[start - 1]) {
+        return None;
+    }
+
+    let hashes = bytes[r + 1..].iter().take_while(|&&b| b == b'#').count();
+    if bytes.get(r + 1 + hashes) != Some(&b'"') {
+        return None;
+    }
+
+    let close = format!("\"{}", "#".repeat(hashes));
+    Some(quoted_end(source, r + 2 + hashes, &close, false))
+}
+
+/// Char literal at `start`, or `None` for a lifetime like `'a`.
+fn rust_char_end(source: &str, start: usize) -> Option<usize> {
+    let mut chars = source[start + 1..].chars();
+    match chars.next()? {
+        '\\' => Some(quoted_end(source, start + 1, "'", true)),
+        c => {
+            let after = start + 1 + c.len_utf8();
+            (source[after..].starts_with('\'')).then_some(after + 1)
+        }
+    }
+}
+
+fn char_len(source: &str, i: usize) -> usize {
+    source[i..].chars().next().map_or(1, char::len_utf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_rust_comments() {
+        let source = "/// Doc\nfn f<'a>(x: &'a str) -> char { // trailing\n    /* outer /* inner */ still */\n    let s = \"// not a comment\";\n    let r = r#\"/* raw */\"#;\n    let c = '\"';\n    a/* x */b\n}\n";
+        let (content, removed) = strip(source, &RUST);
+
+        assert_eq!(
+            content,
+            "fn f<'a>(x: &'a str) -> char {\n    let s = \"// not a comment\";\n    let r = r#\"/* raw */\"#;\n    let c = '\"';\n    a b\n}"
+        );
+        assert_eq!(removed, 2);
+    }
+
+    #[test]
+    fn test_strip_python_and_js_comments() {
+        let python = "# header\nx = '# kept'  # gone\ns = \"\"\"\n# docstring line\n\"\"\"\n";
+        let (content, removed) = strip(python, &PYTHON);
+        assert_eq!(content, "x = '# kept'\ns = \"\"\"\n# docstring line\n\"\"\"");
+        assert_eq!(removed, 1);
+
+        let js = "/**\n * Block\n */\nconst u = `http://${host}`; // url\n";
+        let (content, removed) = strip(js, &JS);
+        assert_eq!(content, "const u = `http://${host}`;");
+        assert_eq!(removed, 3);
+    }
+
+    #[test]
+    fn test_mask_outside() {
+        let source = "let s = \"TODO é\"; // TODO: fix\n/* TODO */ todo();\n";
+        let mask = |scope| {
+            let masked = mask_outside(source, Some(Language::Rust), scope).unwrap();
+            assert_eq!(masked.len(), source.len());
+            let words: Vec<String> = masked.split_whitespace().map(String::from).collect();
+            words.join(" ")
+        };
+
+        assert_eq!(mask(SyntaxScope::Comments), "// TODO: fix /* TODO */");
+        assert_eq!(mask(SyntaxScope::Strings), "\"TODO é\"");
+        assert_eq!(mask(SyntaxScope::Code), "let s = ; todo();");
+        assert!(mask_outside(source, Some(Language::Go), SyntaxScope::Code).is_none());
+    }
+
+    #[test]
+    fn test_comment_ratio() {
+        let temp = crate::test_util::TempDir::new().unwrap();
+        let counts = |name: &str, source: &str| {
+            let path = temp.path().join(name);
+            std::fs::write(&path, source).unwrap();
+            match comment_ratio(&path).unwrap() {
+                FsReadResult::CommentRatio {
+                    comment_lines,
+                    code_lines,
+                    blank_lines,
+                    ratio,
+                    heuristic,
+                } => (comment_lines, code_lines, blank_lines, ratio, heuristic),
+                _ => panic!("Expected CommentRatio result"),
+            }
+        };
+
+        let rust = "/// Doc\nfn f() { // trailing\n    /*\n     * block\n     */\n\n    let s = \"// no\";\n}\n";
+        assert_eq!(counts("a.rs", rust), (4, 3, 1, Some(4.0 / 3.0), false));
+
+        let sql = "-- users\nSELECT 1;\n\n";
+        assert_eq!(counts("q.sql", sql), (1, 1, 1, Some(1.0), true));
+        assert_eq!(counts("only.py", "# just a comment\n").3, None);
+        // Lines starting with a quote are data, not comments
+        let quoted = "\"name\",\"age\"\n'single'\n";
+        assert_eq!(counts("data.txt", quoted), (0, 2, 0, Some(0.0), true));
+    }
+}
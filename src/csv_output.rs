@@ -0,0 +1,128 @@
+use crate::types::{FsReadError, FsReadResult};
+use std::path::Path;
+
+const DIRECTORY_HEADER: &[&str] = &["path", "is_dir", "size", "modified", "depth"];
+const SEARCH_HEADER: &[&str] = &["file_path", "line_number", "line_content"];
+
+/// Serializes Directory and Search results as CSV (RFC 4180 quoting,
+/// `\n` record terminator). Other result types are rejected.
+pub fn to_csv(result: &FsReadResult) -> Result<String, FsReadError> {
+    let mut out = String::new();
+
+    match result {
+        FsReadResult::Directory { entries, .. } => {
+            write_record(&mut out, DIRECTORY_HEADER.iter().copied());
+            for entry in entries {
+                let depth = Path::new(&entry.path).components().count().to_string();
+                let modified = entry.modified.map(|m| m.to_string()).unwrap_or_default();
+                write_record(
+                    &mut out,
+                    [
+                        entry.path.as_str(),
+                        if entry.is_dir { "true" } else { "false" },
+                        &entry.size.to_string(),
+                        &modified,
+                        &depth,
+                    ],
+                );
+            }
+        }
+        FsReadResult::Search { matches, .. } => {
+            write_record(&mut out, SEARCH_HEADER.iter().copied());
+            for m in matches {
+                write_record(
+                    &mut out,
+                    [
+                        m.file_path.as_str(),
+                        &m.line_number.to_string(),
+                        &m.line_content,
+                    ],
+                );
+            }
+        }
+        _ => {
+            return Err(FsReadError::UnsupportedFormat(
+                "CSV is only available for Directory and Search results".to_string(),
+            ))
+        }
+    }
+
+    Ok(out)
+}
+
+fn write_record<'a>(out: &mut String, fields: impl IntoIterator<Item = &'a str>) {
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        if field.contains([',', '"', '\n', '\r']) {
+            out.push('"');
+            out.push_str(&field.replace('"', "\"\""));
+            out.push('"');
+        } else {
+            out.push_str(field);
+        }
+    }
+    out.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FileEntry, SearchMatch};
+
+    #[test]
+    fn test_directory_csv_quotes_paths() {
+        let result = FsReadResult::Directory {
+            entries: vec![FileEntry {
+                path: "src/a,b \"c\".rs".to_string(),
+                is_dir: false,
+                size: 12,
+                modified: None,
+                change: None,
+                git_status: None,
+            }],
+            total_count: 1,
+            truncated: false,
+            omitted_count: 0,
+            skipped: Vec::new(),
+        };
+
+        assert_eq!(
+            to_csv(&result).unwrap(),
+            "path,is_dir,size,modified,depth\n\"src/a,b \"\"c\"\".rs\",false,12,,2\n"
+        );
+    }
+
+    #[test]
+    fn test_search_csv() {
+        let result = FsReadResult::Search {
+            matches: vec![SearchMatch {
+                file_path: "main.rs".to_string(),
+                line_number: 3,
+                line_content: "let x = 1;".to_string(),
+                context_before: Default::default(),
+                context_after: Default::default(),
+                match_count: 1,
+                column: None,
+                match_id: String::new(),
+                block: None,
+                score: None,
+                score_factors: None,
+            }],
+            total_matches: 1,
+            truncated_lines: 0,
+            summary: None,
+            truncated: false,
+            omitted_count: 0,
+            warnings: Vec::new(),
+            capped_files: Vec::new(),
+            skipped: Vec::new(),
+        };
+
+        assert_eq!(
+            to_csv(&result).unwrap(),
+            "file_path,line_number,line_content\nmain.rs,3,let x = 1;\n"
+        );
+    }
+}
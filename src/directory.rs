@@ -1,7 +1,29 @@
-use crate::types::{FileEntry, FsReadError, FsReadResult};
-use std::path::Path;
+use crate::binary::is_binary_file;
+use crate::generated;
+use crate::git;
+use crate::gitignore::Gitignore;
+use crate::hash::{hash_file, ContentHasher};
+use crate::language::{detect_language, Language};
+use crate::glob::{compile_glob, normalize_separators, to_slash_path};
+use crate::types::{
+    kind_check, ChangeKind, DirNode, EntryKind, FileEntry, FileGroup, FsReadError, FsReadResult,
+    GroupKey, HashAlgorithm, LanguageStat, PathKind, SkipReason, SkippedEntry, TreeNode,
+};
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BinaryHeap, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::fs::Metadata;
+use std::io::{self, ErrorKind};
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::LazyLock;
+use std::time::{SystemTime, UNIX_EPOCH};
 use walkdir::WalkDir;
 
+/// Entry names skipped by every walk. These, `CONTEXTINATOR_IGNORE` and
+/// `extra_ignores` all follow one rule: a name matches an entry whose own
+/// name (its last path component) is exactly equal, at any depth.
 const DEFAULT_IGNORE: &[&str] = &[
     ".git",
     "node_modules",
@@ -13,60 +35,843 @@ const DEFAULT_IGNORE: &[&str] = &[
     "build",
 ];
 
-pub fn list_directory(path: &Path, depth: u32) -> Result<FsReadResult, FsReadError> {
-    if !path.exists() {
-        return Err(FsReadError::PathNotFound(path.to_path_buf()));
+/// Comma-separated names appended to `DEFAULT_IGNORE`, read once per process
+pub const IGNORE_ENV_VAR: &str = "CONTEXTINATOR_IGNORE";
+
+static ENV_IGNORE: LazyLock<Vec<String>> =
+    LazyLock::new(|| parse_ignore_list(&std::env::var(IGNORE_ENV_VAR).unwrap_or_default()));
+
+#[derive(Debug, Clone)]
+pub struct DirectoryOptions {
+    pub depth: u32,
+    pub resolve_root: bool,
+    pub git_status: bool,
+    pub include_hidden: bool,
+    /// Names ignored on top of the defaults and `CONTEXTINATOR_IGNORE`
+    pub extra_ignores: Vec<String>,
+    pub prune_empty_dirs: bool,
+    /// Gitignore-syntax file layered over the other ignore rules
+    pub ignore_file: Option<PathBuf>,
+    /// Gitignore-syntax patterns evaluated after `ignore_file`'s rules, so a
+    /// `!pattern` here can re-include what the file excluded
+    pub exclude_globs: Vec<String>,
+    /// Apply the `.gitignore` files of the root and its parents, up to the
+    /// repository top level or `gitignore_boundary`
+    pub parent_gitignores: bool,
+    pub gitignore_boundary: Option<PathBuf>,
+    pub skip_generated: bool,
+    pub entries_kind: EntryKind,
+    /// Display-only: `/` separators in returned paths on every platform
+    pub normalize_separators: bool,
+    /// Drop files with a NUL byte in their first 8 KiB. Every file gets
+    /// opened and read from, so this costs far more than a plain listing
+    pub text_only: bool,
+    /// List at most this many entries. The walk stops at the cap, so
+    /// `omitted_count` is only known when `prune_empty_dirs` makes it finish
+    pub max_entries: Option<usize>,
+}
+
+impl Default for DirectoryOptions {
+    fn default() -> Self {
+        Self {
+            depth: 0,
+            resolve_root: true,
+            git_status: false,
+            include_hidden: false,
+            extra_ignores: Vec::new(),
+            prune_empty_dirs: false,
+            ignore_file: None,
+            exclude_globs: Vec::new(),
+            parent_gitignores: false,
+            gitignore_boundary: None,
+            skip_generated: false,
+            entries_kind: EntryKind::All,
+            normalize_separators: true,
+            text_only: false,
+            max_entries: None,
+        }
+    }
+}
+
+/// Traversal settings shared by every mode that walks a tree.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct WalkOptions {
+    /// 0 lists immediate children only
+    pub depth: u32,
+    /// Keep dot-prefixed entries; `DEFAULT_IGNORE` still applies
+    pub include_hidden: bool,
+    pub extra_ignores: Vec<String>,
+    /// Matched against root-relative paths
+    pub ignore: Option<Gitignore>,
+    /// Reported as skipped with the `generated` reason instead of visited
+    pub skip_generated: bool,
+    /// Don't visit anything but directories, or stat the files inside them
+    pub dirs_only: bool,
+    /// Visit siblings in name order rather than the filesystem's order
+    pub sorted: bool,
+}
+
+pub fn list_directory(
+    path: &Path,
+    options: &DirectoryOptions,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    // Walk the real path so a symlinked root still yields clean relative paths
+    let root = if options.resolve_root {
+        path.canonicalize()?
+    } else {
+        path.to_path_buf()
+    };
+
+    let mut entries = Vec::new();
+
+    let walk = WalkOptions {
+        depth: options.depth,
+        include_hidden: options.include_hidden,
+        extra_ignores: options.extra_ignores.clone(),
+        ignore: ignore_rules(&root, options)?,
+        skip_generated: options.skip_generated,
+        // Pruning needs the files to know which directories are empty
+        dirs_only: options.entries_kind == EntryKind::DirsOnly && !options.prune_empty_dirs,
+        // A capped walk should stop at the same entries every time
+        sorted: options.max_entries.is_some(),
+    };
+    // Directories only count towards the cap if they'll be listed
+    let skip_dirs = options.entries_kind == EntryKind::FilesOnly && !options.prune_empty_dirs;
+    // Pruning can drop entries already collected, so the cap then waits
+    // until the listing is final
+    let walk_cap = options.max_entries.filter(|_| !options.prune_empty_dirs);
+
+    let mut unreadable = Vec::new();
+    let mut truncated = false;
+    let mut skipped = walk_entries_until(&root, &walk, |relative_path, metadata| {
+        if skip_dirs && metadata.is_dir() {
+            return ControlFlow::Continue(());
+        }
+        if options.text_only && metadata.is_file() {
+            match is_binary_file(&root.join(&relative_path)) {
+                Ok(false) => {}
+                Ok(true) => return ControlFlow::Continue(()),
+                Err(e) => {
+                    unreadable.push(skipped_entry(relative_path, Some(e.kind())));
+                    return ControlFlow::Continue(());
+                }
+            }
+        }
+        if walk_cap.is_some_and(|max| entries.len() >= max) {
+            truncated = true;
+            return ControlFlow::Break(());
+        }
+        entries.push(file_entry(relative_path, metadata));
+        ControlFlow::Continue(())
+    })?;
+    skipped.extend(unreadable);
+
+    if options.normalize_separators {
+        let paths = entries.iter_mut().map(|e| &mut e.path);
+        for path in paths.chain(skipped.iter_mut().map(|s| &mut s.path)) {
+            normalize_separators(path);
+        }
+    }
+
+    // Walk order depends on the platform and filesystem; callers cache on it
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    if options.prune_empty_dirs {
+        prune_empty_dirs(&root, &mut entries, options.depth.max(1) as usize);
+    }
+
+    match options.entries_kind {
+        EntryKind::All => {}
+        EntryKind::FilesOnly => entries.retain(|e| !e.is_dir),
+        EntryKind::DirsOnly => entries.retain(|e| e.is_dir),
     }
 
-    if !path.is_dir() {
-        return Err(FsReadError::InvalidPath(format!(
-            "{} is not a directory",
-            path.display()
-        )));
+    let mut omitted_count = 0;
+    if let Some(max) = options.max_entries.filter(|&max| entries.len() > max) {
+        omitted_count = entries.len() - max;
+        truncated = true;
+        entries.truncate(max);
     }
 
+    if options.git_status {
+        if let Some(statuses) = git::RepoStatus::load(&root) {
+            for entry in &mut entries {
+                entry.git_status = Some(statuses.status_of(Path::new(&entry.path), entry.is_dir));
+            }
+        }
+    }
+
+    Ok(FsReadResult::Directory {
+        total_count: entries.len(),
+        entries,
+        truncated,
+        omitted_count,
+        skipped,
+    })
+}
+
+/// Entries modified after `since` (unix seconds). An entry whose creation
+/// time is also after `since` is reported as added, otherwise as modified.
+/// Deleted files are never reported since a stat walk cannot observe them.
+pub fn changed_since(path: &Path, since: u64, depth: u32) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let mut entries = Vec::new();
+
+    let walk = WalkOptions {
+        depth,
+        ..Default::default()
+    };
+
+    let skipped = walk_entries(path, &walk, |relative_path, metadata| {
+        let mut entry = file_entry(relative_path, metadata);
+        if entry.modified.is_some_and(|m| m > since) {
+            let created = metadata.created().ok().and_then(unix_secs);
+            entry.change = Some(match created {
+                Some(c) if c > since => ChangeKind::Added,
+                _ => ChangeKind::Modified,
+            });
+            entries.push(entry);
+        }
+    })?;
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(FsReadResult::Directory {
+        total_count: entries.len(),
+        entries,
+        truncated: false,
+        omitted_count: 0,
+        skipped,
+    })
+}
+
+/// Entries whose root-relative path matches `glob`; `**` crosses directory
+/// levels while `*` stays within one. `depth` of `None` walks everything.
+pub fn glob_find(
+    path: &Path,
+    glob: &str,
+    depth: Option<u32>,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let regex = compile_glob(glob).map_err(|e| FsReadError::InvalidPattern(e.to_string()))?;
     let mut entries = Vec::new();
-    let max_depth = if depth == 0 { 1 } else { depth as usize };
 
-    let walker = WalkDir::new(path)
-        .max_depth(max_depth)
+    let walk = WalkOptions {
+        depth: depth.unwrap_or(u32::MAX),
+        ..Default::default()
+    };
+
+    let skipped = walk_entries(path, &walk, |relative_path, metadata| {
+        if regex.is_match(&to_slash_path(Path::new(&relative_path))) {
+            entries.push(file_entry(relative_path, metadata));
+        }
+    })?;
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(FsReadResult::Directory {
+        total_count: entries.len(),
+        entries,
+        truncated: false,
+        omitted_count: 0,
+        skipped,
+    })
+}
+
+/// Files under `path` modified after `reference` was, by path, for
+/// deciding what to rebuild. A missing reference means nothing has been
+/// built yet, so every file counts as newer. `depth` of `None` walks
+/// everything.
+pub fn newer_than(
+    path: &Path,
+    reference: &Path,
+    depth: Option<u32>,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let cutoff = match std::fs::metadata(reference) {
+        Ok(metadata) => Some(metadata.modified()?),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let walk = WalkOptions {
+        depth: depth.unwrap_or(u32::MAX),
+        ..Default::default()
+    };
+    let mut entries = Vec::new();
+    walk_entries(path, &walk, |relative_path, metadata| {
+        let is_newer = match (cutoff, metadata.modified()) {
+            (None, _) => true,
+            (Some(cutoff), Ok(modified)) => modified > cutoff,
+            (Some(_), Err(_)) => false,
+        };
+        if metadata.is_file() && is_newer {
+            entries.push(file_entry(relative_path, metadata));
+        }
+    })?;
+
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(FsReadResult::NewerThan {
+        entries,
+        reference_missing: cutoff.is_none(),
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// Files under `path` clustered by `key`, groups ordered by key. Uses the
+/// same ignore rules as `list_directory`; `None` depth walks everything.
+pub fn group_by(
+    path: &Path,
+    key: GroupKey,
+    depth: Option<u32>,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let mut groups: BTreeMap<String, FileGroup> = BTreeMap::new();
+    let walk = WalkOptions {
+        depth: depth.unwrap_or(u32::MAX),
+        ..Default::default()
+    };
+
+    walk_entries(path, &walk, |relative_path, metadata| {
+        if !metadata.is_file() {
+            return;
+        }
+        let relative = Path::new(&relative_path);
+        let group_key = match key {
+            GroupKey::Extension => relative.extension(),
+            GroupKey::FilenameStem => relative.file_stem(),
+            GroupKey::Directory => match relative.parent().map(Path::as_os_str) {
+                Some(parent) if !parent.is_empty() => Some(parent),
+                _ => Some(OsStr::new(".")),
+            },
+        };
+        let group_key = group_key.map(|k| k.to_string_lossy().to_string()).unwrap_or_default();
+
+        let group = groups.entry(group_key.clone()).or_insert_with(|| FileGroup {
+            key: group_key,
+            files: Vec::new(),
+            total_bytes: 0,
+        });
+        group.total_bytes += metadata.len();
+        group.files.push(relative_path);
+    })?;
+
+    let mut groups: Vec<FileGroup> = groups.into_values().collect();
+    for group in &mut groups {
+        group.files.sort();
+    }
+
+    Ok(FsReadResult::Groups {
+        groups,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// Files and bytes per detected language, for telling what kind of project
+/// a tree is. Files without a detected language aren't counted.
+pub fn language_breakdown(
+    path: &Path,
+    depth: Option<u32>,
+    skip_generated: bool,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let mut tally: HashMap<Language, (usize, u64)> = HashMap::new();
+    let walk = WalkOptions {
+        depth: depth.unwrap_or(u32::MAX),
+        skip_generated,
+        ..Default::default()
+    };
+    walk_entries(path, &walk, |relative_path, metadata| {
+        if !metadata.is_file() {
+            return;
+        }
+        if let Some(language) = detect_language(&path.join(relative_path)) {
+            let (files, bytes) = tally.entry(language).or_default();
+            *files += 1;
+            *bytes += metadata.len();
+        }
+    })?;
+
+    let total_bytes: u64 = tally.values().map(|&(_, bytes)| bytes).sum();
+    let mut languages: Vec<LanguageStat> = tally
         .into_iter()
-        .filter_entry(|e| should_include(e));
+        .map(|(language, (files, bytes))| LanguageStat {
+            language,
+            files,
+            bytes,
+            percentage: if total_bytes == 0 {
+                0.0
+            } else {
+                bytes as f64 * 100.0 / total_bytes as f64
+            },
+        })
+        .collect();
+    // Ties fall back to file count, then language, for a stable order
+    languages.sort_by_key(|stat| (Reverse(stat.bytes), Reverse(stat.files), stat.language));
+    let primary = languages
+        .iter()
+        .map(|stat| stat.language)
+        .find(|language| !language.is_markup_or_data());
 
-    for entry in walker {
-        let entry = entry.map_err(|e| FsReadError::IoError(e.into()))?;
-        
-        if entry.path() == path {
+    Ok(FsReadResult::LanguageBreakdown {
+        languages,
+        primary,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// Direct and recursive file counts and recursive bytes for every
+/// directory, root (`.`) first and then by path. Each file is added to all
+/// of its ancestors as it is walked, so only directories within `depth`
+/// levels of the root are kept but their totals cover the whole tree.
+pub fn dir_treemap(path: &Path, depth: Option<u32>) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let max_level = depth.map_or(usize::MAX, |depth| depth as usize);
+    let level = |dir: &Path| dir.components().count();
+    // Keyed by relative path, the root being empty so it sorts first
+    let mut totals: BTreeMap<PathBuf, DirNode> = BTreeMap::new();
+    dir_node(&mut totals, Path::new(""));
+
+    let walk = WalkOptions {
+        depth: u32::MAX,
+        ..Default::default()
+    };
+    walk_entries(path, &walk, |relative_path, metadata| {
+        let relative = Path::new(&relative_path);
+        if metadata.is_dir() {
+            if level(relative) <= max_level {
+                dir_node(&mut totals, relative);
+            }
+            return;
+        }
+        if !metadata.is_file() {
+            return;
+        }
+
+        let parent = relative.parent().unwrap_or(Path::new(""));
+        for (i, dir) in parent.ancestors().enumerate() {
+            if level(dir) > max_level {
+                continue;
+            }
+            let node = dir_node(&mut totals, dir);
+            if i == 0 {
+                node.direct_files += 1;
+            }
+            node.recursive_files += 1;
+            node.recursive_bytes += metadata.len();
+        }
+    })?;
+
+    Ok(FsReadResult::DirTreemap {
+        nodes: totals.into_values().collect(),
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+fn dir_node<'a>(totals: &'a mut BTreeMap<PathBuf, DirNode>, dir: &Path) -> &'a mut DirNode {
+    totals.entry(dir.to_path_buf()).or_insert_with(|| DirNode {
+        path: match dir.to_string_lossy() {
+            name if name.is_empty() => ".".to_string(),
+            name => name.into_owned(),
+        },
+        direct_files: 0,
+        recursive_files: 0,
+        recursive_bytes: 0,
+    })
+}
+
+/// One digest for a whole tree, walked with the default ignores. Entries
+/// are folded in path order as `d\0<path>\0` for directories and
+/// `f\0<path>\0<content digest>\0` for files, with `/`-separated relative
+/// paths, so the digest changes when any file's content or the tree's shape
+/// changes and is the same on every platform.
+pub fn tree_hash(path: &Path, algorithm: HashAlgorithm) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let mut entries = Vec::new();
+    let walk = WalkOptions {
+        depth: u32::MAX,
+        ..Default::default()
+    };
+    walk_entries(path, &walk, |relative_path, metadata| {
+        if !relative_path.is_empty() {
+            let relative = to_slash_path(Path::new(&relative_path));
+            entries.push((relative, relative_path, metadata.is_dir()));
+        }
+    })?;
+    entries.sort();
+
+    let mut tree = ContentHasher::new(algorithm);
+    let mut file_count = 0;
+    for (relative, relative_path, is_dir) in entries {
+        if is_dir {
+            tree.update(b"d\0");
+            tree.update(relative.as_bytes());
+            tree.update(b"\0");
             continue;
         }
 
-        let metadata = entry.metadata().map_err(|e| FsReadError::IoError(e.into()))?;
-        let relative_path = entry
-            .path()
-            .strip_prefix(path)
-            .unwrap_or(entry.path())
-            .to_string_lossy()
-            .to_string();
+        let content = hash_file(algorithm, &path.join(&relative_path))?;
+        tree.update(b"f\0");
+        tree.update(relative.as_bytes());
+        tree.update(b"\0");
+        tree.update(content.as_bytes());
+        tree.update(b"\0");
+        file_count += 1;
+    }
 
-        entries.push(FileEntry {
-            path: relative_path,
+    Ok(FsReadResult::TreeHash {
+        digest: tree.finish(),
+        file_count,
+    })
+}
+
+/// Digest of every file under `path`, hashed on as many threads as there
+/// are cores. Workers take the next unhashed file from a shared counter,
+/// so a few large files don't leave the other threads idle. Files that
+/// vanish or can't be read are reported as skipped, sorted by path.
+pub fn hash_manifest(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    ignore_file: Option<&Path>,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let mut files = Vec::new();
+    let walk = WalkOptions {
+        depth: u32::MAX,
+        ignore: ignore_file.map(Gitignore::from_file).transpose()?,
+        ..Default::default()
+    };
+    let mut skipped = walk_entries(path, &walk, |relative_path, metadata| {
+        if metadata.is_file() {
+            files.push(relative_path);
+        }
+    })?;
+
+    let threads = std::thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(files.len())
+        .max(1);
+    let next = AtomicUsize::new(0);
+    let digests: Vec<Vec<(usize, io::Result<String>)>> = std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(relative_path) = files.get(i) else {
+                            return done;
+                        };
+                        done.push((i, hash_file(algorithm, &path.join(relative_path))));
+                    }
+                })
+            })
+            .collect();
+        workers
+            .into_iter()
+            .map(|w| w.join().expect("hash manifest worker thread panicked"))
+            .collect()
+    });
+
+    let mut entries = Vec::with_capacity(files.len());
+    for (i, digest) in digests.into_iter().flatten() {
+        match digest {
+            Ok(digest) => entries.push((to_slash_path(Path::new(&files[i])), digest)),
+            Err(e) => skipped.push(skipped_entry(files[i].clone(), Some(e.kind()))),
+        }
+    }
+    // `/` separators on every platform, as in `entries`
+    for entry in &mut skipped {
+        entry.path = to_slash_path(Path::new(&entry.path));
+    }
+    entries.sort();
+    skipped.sort_by(|a, b| a.path.cmp(&b.path));
+
+    Ok(FsReadResult::HashManifest {
+        entries,
+        skipped,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// The `limit` largest files under `path`, largest first. Only `limit`
+/// candidates are held at a time, so memory stays flat however big the
+/// tree is.
+pub fn largest_files(
+    path: &Path,
+    limit: usize,
+    ignore_file: Option<&Path>,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    // Min-heap on (size, path descending): the top is the first to go.
+    // `limit` is caller-supplied, so it only bounds the initial capacity
+    let mut heap = BinaryHeap::with_capacity(limit.min(1024).saturating_add(1));
+    let walk = WalkOptions {
+        depth: u32::MAX,
+        ignore: ignore_file.map(Gitignore::from_file).transpose()?,
+        ..Default::default()
+    };
+    walk_entries(path, &walk, |relative_path, metadata| {
+        if limit == 0 || !metadata.is_file() {
+            return;
+        }
+        let key = (metadata.len(), Reverse(relative_path));
+        if heap.len() == limit {
+            match heap.peek() {
+                Some(Reverse((smallest, _))) if key > *smallest => {
+                    heap.pop();
+                }
+                _ => return,
+            }
+        }
+        let modified = metadata.modified().ok().and_then(unix_secs);
+        heap.push(Reverse((key, modified)));
+    })?;
+
+    let files = heap
+        .into_sorted_vec()
+        .into_iter()
+        .map(|Reverse(((size, Reverse(path)), modified))| FileEntry {
+            path,
+            is_dir: false,
+            size,
+            modified,
+            change: None,
+            git_status: None,
+        })
+        .collect();
+
+    Ok(FsReadResult::LargestFiles {
+        files,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// Parent `.gitignore` files, then `ignore_file`, then `exclude_globs` as
+/// one ordered rule list; later rules take precedence.
+fn ignore_rules(root: &Path, options: &DirectoryOptions) -> Result<Option<Gitignore>, FsReadError> {
+    let mut layers = Vec::new();
+    if options.parent_gitignores {
+        layers.push(Gitignore::from_parents(root, options.gitignore_boundary.as_deref())?);
+    }
+    if let Some(file) = &options.ignore_file {
+        layers.push(Gitignore::from_file(file)?);
+    }
+    if !options.exclude_globs.is_empty() {
+        layers.push(Gitignore::from_patterns(&options.exclude_globs)?);
+    }
+
+    Ok(layers.into_iter().reduce(|mut rules, layer| {
+        rules.extend(layer);
+        rules
+    }))
+}
+
+/// The same walk as `list_directory`, assembled into nested nodes with
+/// directories before files at every level.
+pub fn nested_tree(path: &Path, depth: u32) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let root_path = path.canonicalize()?;
+    let mut root = TreeNode {
+        name: root_path
+            .file_name()
+            .map_or_else(|| root_path.to_string_lossy(), |n| n.to_string_lossy())
+            .to_string(),
+        is_dir: true,
+        size: std::fs::metadata(&root_path)?.len(),
+        children: Vec::new(),
+    };
+
+    let walk = WalkOptions {
+        depth,
+        ..Default::default()
+    };
+
+    // Parents are always visited before their children
+    walk_entries(&root_path, &walk, |relative_path, metadata| {
+        let relative = Path::new(&relative_path);
+        let mut node = &mut root;
+        if let Some(parent) = relative.parent() {
+            for component in parent.components() {
+                let name = component.as_os_str().to_string_lossy();
+                match node.children.iter().position(|c| c.name == name) {
+                    Some(i) => node = &mut node.children[i],
+                    None => return,
+                }
+            }
+        }
+        node.children.push(TreeNode {
+            name: relative
+                .file_name()
+                .map(|n| n.to_string_lossy().to_string())
+                .unwrap_or(relative_path.clone()),
             is_dir: metadata.is_dir(),
             size: metadata.len(),
-            modified: metadata
-                .modified()
-                .ok()
-                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                .map(|d| d.as_secs()),
+            children: Vec::new(),
         });
+    })?;
+
+    sort_tree(&mut root);
+
+    Ok(FsReadResult::NestedTree { root })
+}
+
+fn sort_tree(node: &mut TreeNode) {
+    node.children
+        .sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    for child in &mut node.children {
+        sort_tree(child);
     }
+}
 
-    Ok(FsReadResult::Directory {
-        total_count: entries.len(),
-        entries,
+/// Walks `path` applying the ignore rules, calling `visit` with each entry's
+/// root-relative path. Entries that vanish or can't be read partway through
+/// are returned as skipped rather than failing the walk; an error on the
+/// root itself still does.
+pub(crate) fn walk_entries(
+    path: &Path,
+    options: &WalkOptions,
+    mut visit: impl FnMut(String, &Metadata),
+) -> Result<Vec<SkippedEntry>, FsReadError> {
+    walk_entries_until(path, options, |relative_path, metadata| {
+        visit(relative_path, metadata);
+        ControlFlow::Continue(())
     })
 }
 
-fn should_include(entry: &walkdir::DirEntry) -> bool {
+/// `walk_entries`, ending the walk as soon as `visit` breaks.
+pub(crate) fn walk_entries_until(
+    path: &Path,
+    options: &WalkOptions,
+    mut visit: impl FnMut(String, &Metadata) -> ControlFlow<()>,
+) -> Result<Vec<SkippedEntry>, FsReadError> {
+    let max_depth = if options.depth == 0 { 1 } else { options.depth as usize };
+    let relative = |p: &Path| p.strip_prefix(path).unwrap_or(p).to_string_lossy().to_string();
+    let mut skipped = Vec::new();
+    let mut generated = Vec::new();
+
+    let mut walker = WalkDir::new(path).max_depth(max_depth);
+    if options.sorted {
+        walker = walker.sort_by_file_name();
+    }
+    let walker = walker
+        .into_iter()
+        .filter_entry(|e| {
+            if options.dirs_only && e.depth() > 0 && !e.file_type().is_dir() {
+                return false;
+            }
+            if !should_include(e, path, options) {
+                return false;
+            }
+            let is_generated = options.skip_generated
+                && e.depth() > 0
+                && generated::is_generated(e.path(), e.file_type().is_dir());
+            if is_generated {
+                generated.push(SkippedEntry {
+                    path: relative(e.path()),
+                    reason: SkipReason::Generated,
+                });
+            }
+            !is_generated
+        });
+
+    for entry in walker {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => match e.path().filter(|_| e.depth() > 0) {
+                Some(p) => {
+                    skipped.push(skipped_entry(relative(p), e.io_error().map(|e| e.kind())));
+                    continue;
+                }
+                None => return Err(FsReadError::IoError(e.into())),
+            },
+        };
+
+        if entry.path() == path {
+            continue;
+        }
+
+        let relative_path = relative(entry.path());
+        match entry.metadata() {
+            Ok(metadata) => {
+                if visit(relative_path, &metadata).is_break() {
+                    break;
+                }
+            }
+            Err(e) => skipped.push(skipped_entry(relative_path, e.io_error().map(|e| e.kind()))),
+        }
+    }
+
+    skipped.extend(generated);
+    Ok(skipped)
+}
+
+fn skipped_entry(path: String, kind: Option<ErrorKind>) -> SkippedEntry {
+    let reason = match kind {
+        Some(ErrorKind::NotFound) => SkipReason::Vanished,
+        _ => SkipReason::Unreadable,
+    };
+    SkippedEntry { path, reason }
+}
+
+/// Drops directories with no file among the surviving entries beneath them.
+/// Directories at the depth limit weren't descended into, so they're kept
+/// unless they're empty on disk.
+fn prune_empty_dirs(root: &Path, entries: &mut Vec<FileEntry>, max_depth: usize) {
+    let is_occupied = |entry: &FileEntry| {
+        let path = Path::new(&entry.path);
+        !entry.is_dir
+            || (path.components().count() >= max_depth
+                && std::fs::read_dir(root.join(path)).is_ok_and(|mut d| d.next().is_some()))
+    };
+
+    let mut keep: HashSet<String> = HashSet::new();
+    for entry in entries.iter().filter(|e| is_occupied(e)) {
+        keep.extend(
+            Path::new(&entry.path)
+                .ancestors()
+                .map(|p| p.to_string_lossy().to_string()),
+        );
+    }
+
+    entries.retain(|entry| keep.contains(&entry.path));
+}
+
+fn file_entry(path: String, metadata: &Metadata) -> FileEntry {
+    FileEntry {
+        path,
+        is_dir: metadata.is_dir(),
+        size: metadata.len(),
+        modified: metadata.modified().ok().and_then(unix_secs),
+        change: None,
+        git_status: None,
+    }
+}
+
+fn unix_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+fn should_include(entry: &walkdir::DirEntry, root: &Path, options: &WalkOptions) -> bool {
     let name = entry.file_name().to_string_lossy();
 
     // Always include the root directory
@@ -75,25 +880,46 @@ fn should_include(entry: &walkdir::DirEntry) -> bool {
     }
 
     // Skip hidden files (but not the root)
-    if name.starts_with('.') {
+    if !options.include_hidden && name.starts_with('.') {
+        return false;
+    }
+
+    // Skip ignored names
+    if is_ignored_name(&name, &options.extra_ignores) {
         return false;
     }
 
-    // Skip ignored patterns
-    for pattern in DEFAULT_IGNORE {
-        if name.contains(pattern) {
-            return false;
+    match &options.ignore {
+        Some(ignore) => {
+            let relative = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            !ignore.is_ignored(relative, entry.file_type().is_dir())
         }
+        None => true,
     }
+}
 
-    true
+/// Whether `name` equals a default, environment or extra ignore name, as
+/// described on `DEFAULT_IGNORE`.
+fn is_ignored_name(name: &str, extra_ignores: &[String]) -> bool {
+    DEFAULT_IGNORE.contains(&name)
+        || ENV_IGNORE.iter().any(|n| n == name)
+        || extra_ignores.iter().any(|n| n == name)
+}
+
+fn parse_ignore_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .map(String::from)
+        .collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
-    use tempfile::TempDir;
+    use crate::test_util::TempDir;
 
     #[test]
     fn test_list_directory() {
@@ -104,9 +930,9 @@ mod tests {
         fs::write(temp_path.join("file2.txt"), "content").unwrap();
         fs::create_dir(temp_path.join("subdir")).unwrap();
 
-        let result = list_directory(temp_path, 0).unwrap();
+        let result = list_directory(temp_path, &DirectoryOptions::default()).unwrap();
 
-        if let FsReadResult::Directory { entries, total_count } = result {
+        if let FsReadResult::Directory { entries, total_count, .. } = result {
             println!("Got {} entries", total_count);
             for entry in &entries {
                 println!("  - {}", entry.path);
@@ -117,4 +943,693 @@ mod tests {
             panic!("Expected Directory result");
         }
     }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_list_directory_symlinked_root() {
+        let temp = TempDir::new().unwrap();
+        let real = temp.path().join("real");
+        fs::create_dir(&real).unwrap();
+        fs::write(real.join("file.txt"), "content").unwrap();
+        let link = temp.path().join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+
+        let result = list_directory(&link, &DirectoryOptions::default()).unwrap();
+
+        if let FsReadResult::Directory { entries, total_count, .. } = result {
+            assert_eq!(total_count, 1);
+            assert_eq!(entries[0].path, "file.txt");
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
+
+    #[test]
+    fn test_list_directory_include_hidden() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join(".github")).unwrap();
+        fs::create_dir(temp.path().join(".git")).unwrap();
+        fs::write(temp.path().join(".env.example"), "KEY=").unwrap();
+        fs::write(temp.path().join("distance.py"), "").unwrap();
+
+        let paths = |options: &DirectoryOptions| -> Vec<String> {
+            match list_directory(temp.path(), options).unwrap() {
+                FsReadResult::Directory { entries, .. } => {
+                    let mut paths: Vec<String> = entries.into_iter().map(|e| e.path).collect();
+                    paths.sort();
+                    paths
+                }
+                _ => panic!("Expected Directory result"),
+            }
+        };
+
+        assert_eq!(paths(&DirectoryOptions::default()), vec!["distance.py"]);
+
+        let options = DirectoryOptions {
+            include_hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(paths(&options), vec![".env.example", ".github", "distance.py"]);
+    }
+
+    #[test]
+    fn test_extra_ignores() {
+        assert_eq!(
+            parse_ignore_list(" .terraform,,coverage , .mypy_cache"),
+            vec![".terraform", "coverage", ".mypy_cache"]
+        );
+
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join("coverage")).unwrap();
+        fs::create_dir(temp.path().join("src")).unwrap();
+
+        let options = DirectoryOptions {
+            extra_ignores: vec!["coverage".into()],
+            ..Default::default()
+        };
+        let result = list_directory(temp.path(), &options).unwrap();
+
+        if let FsReadResult::Directory { entries, .. } = result {
+            let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+            assert_eq!(paths, vec!["src"]);
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
+
+    #[test]
+    fn test_prune_empty_dirs() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("empty/nested")).unwrap();
+        fs::create_dir_all(temp.path().join("full/sub")).unwrap();
+        fs::create_dir_all(temp.path().join("deep/a/b")).unwrap();
+        fs::write(temp.path().join("full/sub/file.txt"), "").unwrap();
+        fs::write(temp.path().join("deep/a/b/file.txt"), "").unwrap();
+
+        let options = DirectoryOptions {
+            depth: 3,
+            prune_empty_dirs: true,
+            ..Default::default()
+        };
+        let result = list_directory(temp.path(), &options).unwrap();
+
+        if let FsReadResult::Directory { entries, .. } = result {
+            let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+            assert_eq!(
+                paths,
+                vec!["deep", "deep/a", "deep/a/b", "full", "full/sub", "full/sub/file.txt"]
+            );
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
+
+    #[test]
+    fn test_list_directory_ignore_file() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+        fs::create_dir_all(root.join("gen/keep")).unwrap();
+        fs::create_dir_all(root.join("src/gen")).unwrap();
+        fs::write(root.join("a.snap"), "").unwrap();
+        fs::write(root.join("src/b.snap"), "").unwrap();
+        let ignore_file = temp.path().join(".contextignore");
+        fs::write(&ignore_file, "/gen/\n*.snap\n!src/*.snap\n").unwrap();
+
+        let options = DirectoryOptions {
+            depth: 3,
+            ignore_file: Some(ignore_file),
+            ..Default::default()
+        };
+        let result = list_directory(&root, &options).unwrap();
+
+        if let FsReadResult::Directory { entries, .. } = result {
+            let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+            assert_eq!(paths, vec!["src", "src/b.snap", "src/gen"]);
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
+
+    #[test]
+    fn test_list_directory_exclude_globs() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("js/keep")).unwrap();
+        fs::write(temp.path().join("js/app.min.js"), "").unwrap();
+        fs::write(temp.path().join("js/keep/this.min.js"), "").unwrap();
+        fs::write(temp.path().join("js/app.js"), "").unwrap();
+        let ignore_file = temp.path().join(".contextignore");
+        fs::write(&ignore_file, "*.js\n").unwrap();
+
+        let options = DirectoryOptions {
+            depth: 5,
+            ignore_file: Some(ignore_file),
+            exclude_globs: vec![
+                "!*.js".to_string(),
+                "**/*.min.js".to_string(),
+                "!keep/this.min.js".to_string(),
+                "!js/keep/this.min.js".to_string(),
+            ],
+            ..Default::default()
+        };
+        let result = list_directory(temp.path(), &options).unwrap();
+
+        if let FsReadResult::Directory { entries, .. } = result {
+            let files: Vec<&str> = entries
+                .iter()
+                .filter(|e| !e.is_dir)
+                .map(|e| e.path.as_str())
+                .collect();
+            // Anchored patterns are relative to the walk root, so only the
+            // last negation matches
+            assert_eq!(files, vec!["js/app.js", "js/keep/this.min.js"]);
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
+
+    #[test]
+    fn test_list_directory_skip_generated() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("vendor/lib")).unwrap();
+        fs::write(temp.path().join("vendor/lib/dep.go"), "package lib").unwrap();
+        fs::write(temp.path().join("Cargo.lock"), "").unwrap();
+        fs::write(temp.path().join("db.go"), "// Code generated. DO NOT EDIT.\n").unwrap();
+        fs::write(temp.path().join("main.go"), "package main").unwrap();
+
+        let options = DirectoryOptions {
+            depth: 5,
+            skip_generated: true,
+            ..Default::default()
+        };
+        let result = list_directory(temp.path(), &options).unwrap();
+
+        if let FsReadResult::Directory {
+            entries, skipped, ..
+        } = result
+        {
+            let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+            assert_eq!(paths, vec!["main.go"]);
+
+            let mut skipped: Vec<&str> = skipped
+                .iter()
+                .filter(|s| s.reason == SkipReason::Generated)
+                .map(|s| s.path.as_str())
+                .collect();
+            skipped.sort();
+            // A generated directory is reported once, not per file
+            assert_eq!(skipped, vec!["Cargo.lock", "db.go", "vendor"]);
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
+
+    #[test]
+    fn test_list_directory_entries_kind() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src/util")).unwrap();
+        fs::create_dir_all(temp.path().join("docs")).unwrap();
+        fs::write(temp.path().join("src/main.rs"), "").unwrap();
+        fs::write(temp.path().join("README.md"), "").unwrap();
+
+        let list = |entries_kind, prune_empty_dirs| {
+            let options = DirectoryOptions {
+                depth: 5,
+                entries_kind,
+                prune_empty_dirs,
+                ..Default::default()
+            };
+            match list_directory(temp.path(), &options).unwrap() {
+                FsReadResult::Directory { entries, .. } => {
+                    entries.into_iter().map(|e| e.path).collect::<Vec<_>>()
+                }
+                _ => panic!("Expected Directory result"),
+            }
+        };
+
+        assert_eq!(list(EntryKind::DirsOnly, false), vec!["docs", "src", "src/util"]);
+        assert_eq!(list(EntryKind::FilesOnly, false), vec!["README.md", "src/main.rs"]);
+        assert_eq!(list(EntryKind::All, false).len(), 5);
+        // Pruning still sees the files, so only directories holding one survive
+        assert_eq!(list(EntryKind::DirsOnly, true), vec!["src"]);
+    }
+
+    #[test]
+    fn test_list_directory_text_only() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join("assets")).unwrap();
+        fs::write(temp.path().join("assets/logo.png"), b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        fs::write(temp.path().join("notes.txt"), "plain text").unwrap();
+        fs::write(temp.path().join("empty.txt"), "").unwrap();
+
+        let options = DirectoryOptions {
+            depth: 2,
+            text_only: true,
+            ..Default::default()
+        };
+        let result = list_directory(temp.path(), &options).unwrap();
+        if let FsReadResult::Directory { entries, .. } = result {
+            let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+            // Directories stay even when everything in them is binary
+            assert_eq!(paths, vec!["assets", "empty.txt", "notes.txt"]);
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
+
+    #[test]
+    fn test_list_directory_max_entries() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join("sub")).unwrap();
+        for name in ["d.txt", "a.txt", "c.txt", "sub/b.txt"] {
+            fs::write(temp.path().join(name), "").unwrap();
+        }
+
+        let list = |max_entries, entries_kind| {
+            let options = DirectoryOptions {
+                depth: 2,
+                max_entries,
+                entries_kind,
+                ..Default::default()
+            };
+            match list_directory(temp.path(), &options).unwrap() {
+                FsReadResult::Directory {
+                    entries,
+                    total_count,
+                    truncated,
+                    ..
+                } => {
+                    assert_eq!(total_count, entries.len());
+                    let paths: Vec<String> = entries.into_iter().map(|e| e.path).collect();
+                    (paths, truncated)
+                }
+                _ => panic!("Expected Directory result"),
+            }
+        };
+
+        assert_eq!(list(Some(2), EntryKind::All), (vec!["a.txt".into(), "c.txt".into()], true));
+        assert!(!list(Some(5), EntryKind::All).1);
+        assert_eq!(list(None, EntryKind::All).0.len(), 5);
+        // Directories left out of the listing don't use up the cap
+        let (files, truncated) = list(Some(4), EntryKind::FilesOnly);
+        assert_eq!(files.len(), 4);
+        assert!(!truncated);
+
+        // With pruning the cap applies to the final listing
+        for name in ["x", "y", "z"] {
+            fs::create_dir(temp.path().join(name)).unwrap();
+            fs::write(temp.path().join(name).join("f.txt"), "").unwrap();
+        }
+        let options = DirectoryOptions {
+            depth: 2,
+            max_entries: Some(2),
+            entries_kind: EntryKind::DirsOnly,
+            prune_empty_dirs: true,
+            ..Default::default()
+        };
+        if let FsReadResult::Directory {
+            entries,
+            truncated,
+            omitted_count,
+            ..
+        } = list_directory(temp.path(), &options).unwrap()
+        {
+            let paths: Vec<String> = entries.into_iter().map(|e| e.path).collect();
+            assert_eq!(paths, vec!["sub", "x"]);
+            assert!(truncated);
+            assert_eq!(omitted_count, 2);
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
+
+    #[test]
+    fn test_list_directory_parent_gitignores() {
+        let temp = TempDir::new().unwrap();
+        let sub = temp.path().join("app");
+        fs::create_dir_all(temp.path().join(".git")).unwrap();
+        fs::create_dir_all(&sub).unwrap();
+        fs::write(temp.path().join(".gitignore"), "*.tmp\n").unwrap();
+        fs::write(sub.join("main.py"), "").unwrap();
+        fs::write(sub.join("scratch.tmp"), "").unwrap();
+
+        let paths = |parent_gitignores| {
+            let options = DirectoryOptions {
+                parent_gitignores,
+                ..Default::default()
+            };
+            match list_directory(&sub, &options).unwrap() {
+                FsReadResult::Directory { entries, .. } => {
+                    entries.into_iter().map(|e| e.path).collect::<Vec<_>>()
+                }
+                _ => panic!("Expected Directory result"),
+            }
+        };
+
+        assert_eq!(paths(false), vec!["main.py", "scratch.tmp"]);
+        assert_eq!(paths(true), vec!["main.py"]);
+    }
+
+    #[test]
+    fn test_glob_find() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src/a")).unwrap();
+        fs::create_dir(temp.path().join("node_modules")).unwrap();
+        for name in ["x.test.ts", "src/y.test.ts", "src/a/z.test.ts", "src/a/z.ts"] {
+            fs::write(temp.path().join(name), "").unwrap();
+        }
+        fs::write(temp.path().join("node_modules/n.test.ts"), "").unwrap();
+
+        let paths = |glob: &str| -> Vec<String> {
+            match glob_find(temp.path(), glob, None).unwrap() {
+                FsReadResult::Directory { entries, .. } => {
+                    entries.into_iter().map(|e| e.path).collect()
+                }
+                _ => panic!("Expected Directory result"),
+            }
+        };
+
+        assert_eq!(paths("**/*.test.ts"), vec!["src/a/z.test.ts", "src/y.test.ts", "x.test.ts"]);
+        assert_eq!(paths("*.test.ts"), vec!["x.test.ts"]);
+        assert_eq!(paths("src/*"), vec!["src/a", "src/y.test.ts"]);
+    }
+
+    #[test]
+    fn test_hash_manifest() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src/deep")).unwrap();
+        fs::create_dir(temp.path().join("node_modules")).unwrap();
+        let files = ["b.txt", "a.txt", "src/lib.rs", "src/deep/x.rs", "node_modules/n.js"];
+        for (i, name) in files.iter().enumerate() {
+            fs::write(temp.path().join(name), format!("content {}", i)).unwrap();
+        }
+
+        let entries = match hash_manifest(temp.path(), HashAlgorithm::Sha256, None).unwrap() {
+            FsReadResult::HashManifest { entries, skipped, .. } => {
+                assert!(skipped.is_empty());
+                entries
+            }
+            _ => panic!("Expected HashManifest result"),
+        };
+
+        let paths: Vec<&str> = entries.iter().map(|(path, _)| path.as_str()).collect();
+        assert_eq!(paths, vec!["a.txt", "b.txt", "src/deep/x.rs", "src/lib.rs"]);
+        for (path, digest) in &entries {
+            let expected = hash_file(HashAlgorithm::Sha256, &temp.path().join(path)).unwrap();
+            assert_eq!(digest, &expected);
+        }
+    }
+
+    #[test]
+    fn test_dir_treemap() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src/deep")).unwrap();
+        fs::create_dir(temp.path().join("empty")).unwrap();
+        fs::write(temp.path().join("README.md"), "12345").unwrap();
+        fs::write(temp.path().join("src/lib.rs"), "123").unwrap();
+        fs::write(temp.path().join("src/deep/a.rs"), "12").unwrap();
+        fs::write(temp.path().join("src/deep/b.rs"), "1").unwrap();
+
+        let nodes = |depth| match dir_treemap(temp.path(), depth).unwrap() {
+            FsReadResult::DirTreemap { nodes, .. } => nodes
+                .into_iter()
+                .map(|n| {
+                    let path = to_slash_path(Path::new(&n.path));
+                    (path, n.direct_files, n.recursive_files, n.recursive_bytes)
+                })
+                .collect::<Vec<_>>(),
+            _ => panic!("Expected DirTreemap result"),
+        };
+
+        let node = |path: &str, direct, recursive, bytes| {
+            (path.to_string(), direct, recursive, bytes)
+        };
+        assert_eq!(
+            nodes(None),
+            vec![
+                node(".", 1, 4, 11),
+                node("empty", 0, 0, 0),
+                node("src", 1, 3, 6),
+                node("src/deep", 2, 2, 3),
+            ]
+        );
+        assert_eq!(nodes(Some(1)), nodes(None)[..3].to_vec());
+    }
+
+    #[test]
+    fn test_newer_than() {
+        let temp = TempDir::new().unwrap();
+        let stamp = temp.path().join("build/.stamp");
+        fs::create_dir_all(temp.path().join("build")).unwrap();
+        fs::create_dir(temp.path().join("src")).unwrap();
+        let base = SystemTime::now() - std::time::Duration::from_secs(3600);
+        for (name, offset) in [("build/.stamp", 10), ("old.rs", 0), ("src/new.rs", 20)] {
+            let file = fs::File::create(temp.path().join(name)).unwrap();
+            file.set_modified(base + std::time::Duration::from_secs(offset)).unwrap();
+        }
+
+        let newer = |reference: &Path| match newer_than(temp.path(), reference, None).unwrap() {
+            FsReadResult::NewerThan {
+                entries,
+                reference_missing,
+                ..
+            } => (entries.into_iter().map(|e| e.path).collect::<Vec<_>>(), reference_missing),
+            _ => panic!("Expected NewerThan result"),
+        };
+
+        assert_eq!(newer(&stamp), (vec!["src/new.rs".to_string()], false));
+        let missing = temp.path().join("nope");
+        assert_eq!(newer(&missing), (vec!["old.rs".to_string(), "src/new.rs".to_string()], true));
+    }
+
+    #[test]
+    fn test_walk_skips_vanished_entries() {
+        let temp = TempDir::new().unwrap();
+        for name in ["a.txt", "b.txt", "c.txt"] {
+            fs::write(temp.path().join(name), "x").unwrap();
+        }
+
+        // The directory has already been read, so the other files are still
+        // yielded after they're deleted and only fail when stat'ed
+        let mut visited = Vec::new();
+        let skipped = walk_entries(temp.path(), &WalkOptions::default(), |relative_path, _| {
+            for name in ["a.txt", "b.txt", "c.txt"] {
+                if name != relative_path {
+                    let _ = fs::remove_file(temp.path().join(name));
+                }
+            }
+            visited.push(relative_path);
+        })
+        .unwrap();
+
+        assert_eq!(visited.len(), 1);
+        assert_eq!(skipped.len(), 2);
+        assert!(skipped.iter().all(|s| s.reason == SkipReason::Vanished));
+        assert!(!skipped.iter().any(|s| s.path == visited[0]));
+    }
+
+    #[test]
+    fn test_group_by() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("tests")).unwrap();
+        fs::create_dir_all(temp.path().join("node_modules")).unwrap();
+        fs::write(temp.path().join("main.rs"), "fn main() {}").unwrap();
+        fs::write(temp.path().join("Makefile"), "all:").unwrap();
+        fs::write(temp.path().join("tests/a.rs"), "a").unwrap();
+        fs::write(temp.path().join("tests/main.py"), "b").unwrap();
+        fs::write(temp.path().join("node_modules/dep.rs"), "c").unwrap();
+
+        let groups = |key| match group_by(temp.path(), key, None).unwrap() {
+            FsReadResult::Groups { groups, .. } => groups
+                .into_iter()
+                .map(|g| (g.key, g.files.len(), g.total_bytes))
+                .collect::<Vec<_>>(),
+            _ => panic!("Expected Groups result"),
+        };
+
+        let by_extension = groups(GroupKey::Extension);
+        assert_eq!(
+            by_extension,
+            vec![("".to_string(), 1, 4), ("py".to_string(), 1, 1), ("rs".to_string(), 2, 13)]
+        );
+
+        let by_directory = groups(GroupKey::Directory);
+        assert_eq!(by_directory, vec![(".".to_string(), 2, 16), ("tests".to_string(), 2, 2)]);
+
+        let by_stem = groups(GroupKey::FilenameStem);
+        assert_eq!(by_stem[1], ("a".to_string(), 1, 1));
+        assert_eq!(by_stem[2], ("main".to_string(), 2, 13));
+    }
+
+    #[test]
+    fn test_language_breakdown() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        fs::create_dir_all(temp.path().join("vendor")).unwrap();
+        fs::write(temp.path().join("src/app.ts"), "x".repeat(300)).unwrap();
+        fs::write(temp.path().join("src/util.ts"), "x".repeat(100)).unwrap();
+        fs::write(temp.path().join("README.md"), "x".repeat(1000)).unwrap();
+        fs::write(temp.path().join("vendor/lib.js"), "x".repeat(600)).unwrap();
+        fs::write(temp.path().join("LICENSE"), "x".repeat(5000)).unwrap();
+
+        let breakdown = |skip_generated| {
+            match language_breakdown(temp.path(), None, skip_generated).unwrap() {
+                FsReadResult::LanguageBreakdown { languages, primary, .. } => (languages, primary),
+                _ => panic!("Expected LanguageBreakdown result"),
+            }
+        };
+
+        let (languages, primary) = breakdown(false);
+        let order: Vec<_> = languages.iter().map(|s| (s.language, s.files)).collect();
+        assert_eq!(
+            order,
+            vec![(Language::Markdown, 1), (Language::JavaScript, 1), (Language::TypeScript, 2)]
+        );
+        assert_eq!(languages[0].percentage, 50.0);
+        // Markdown leads on bytes but doesn't make this a Markdown project
+        assert_eq!(primary, Some(Language::JavaScript));
+
+        let (languages, primary) = breakdown(true);
+        assert_eq!(languages.len(), 2);
+        assert_eq!(primary, Some(Language::TypeScript));
+    }
+
+    #[test]
+    fn test_tree_hash() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        fs::write(temp.path().join("src/lib.rs"), "pub fn a() {}").unwrap();
+        fs::write(temp.path().join("README.md"), "# a").unwrap();
+
+        let hash = || match tree_hash(temp.path(), HashAlgorithm::Sha256).unwrap() {
+            FsReadResult::TreeHash { digest, file_count } => (digest, file_count),
+            _ => panic!("Expected TreeHash result"),
+        };
+
+        let (original, file_count) = hash();
+        assert_eq!(file_count, 2);
+        assert_eq!(original.len(), 64);
+        assert_eq!(hash().0, original);
+
+        // Ignored entries don't count
+        fs::create_dir_all(temp.path().join("node_modules/x")).unwrap();
+        fs::write(temp.path().join("node_modules/x/index.js"), "").unwrap();
+        assert_eq!(hash().0, original);
+
+        fs::create_dir(temp.path().join("docs")).unwrap();
+        let with_dir = hash().0;
+        assert_ne!(with_dir, original);
+
+        fs::write(temp.path().join("src/lib.rs"), "pub fn b() {}").unwrap();
+        let edited = hash().0;
+        assert_ne!(edited, with_dir);
+
+        fs::rename(temp.path().join("README.md"), temp.path().join("docs/README.md")).unwrap();
+        assert_ne!(hash().0, edited);
+    }
+
+    #[test]
+    fn test_largest_files() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src")).unwrap();
+        fs::create_dir_all(temp.path().join("vendor")).unwrap();
+        fs::write(temp.path().join("a.txt"), "x".repeat(10)).unwrap();
+        fs::write(temp.path().join("b.txt"), "x".repeat(30)).unwrap();
+        fs::write(temp.path().join("c.txt"), "x".repeat(10)).unwrap();
+        fs::write(temp.path().join("src/big.rs"), "x".repeat(50)).unwrap();
+        fs::write(temp.path().join("vendor/huge.js"), "x".repeat(100)).unwrap();
+        let ignore_file = temp.path().join(".contextignore");
+        fs::write(&ignore_file, "/vendor/\n").unwrap();
+
+        let largest = |limit, ignore_file: Option<&Path>| {
+            match largest_files(temp.path(), limit, ignore_file).unwrap() {
+                FsReadResult::LargestFiles { files, .. } => files
+                    .into_iter()
+                    .map(|f| (to_slash_path(Path::new(&f.path)), f.size))
+                    .collect::<Vec<_>>(),
+                _ => panic!("Expected LargestFiles result"),
+            }
+        };
+        let file = |path: &str, size| (path.to_string(), size);
+
+        assert_eq!(largest(2, None), vec![file("vendor/huge.js", 100), file("src/big.rs", 50)]);
+        assert_eq!(
+            largest(3, Some(&ignore_file)),
+            vec![file("src/big.rs", 50), file("b.txt", 30), file("a.txt", 10)]
+        );
+        // Fewer files than the limit: all of them, hidden ones excepted
+        assert_eq!(largest(10, Some(&ignore_file)).len(), 4);
+        assert!(largest(0, None).is_empty());
+        assert_eq!(largest(usize::MAX, Some(&ignore_file)).len(), 4);
+    }
+
+    #[test]
+    fn test_nested_tree() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("src/deep/deeper")).unwrap();
+        fs::create_dir(temp.path().join("node_modules")).unwrap();
+        fs::write(temp.path().join("a.txt"), "abc").unwrap();
+        fs::write(temp.path().join("src/lib.rs"), "").unwrap();
+
+        let result = nested_tree(temp.path(), 2).unwrap();
+
+        if let FsReadResult::NestedTree { root } = result {
+            assert!(root.is_dir);
+            let names: Vec<&str> = root.children.iter().map(|c| c.name.as_str()).collect();
+            assert_eq!(names, vec!["src", "a.txt"]);
+            assert_eq!(root.children[1].size, 3);
+
+            let src = &root.children[0];
+            let names: Vec<&str> = src.children.iter().map(|c| c.name.as_str()).collect();
+            assert_eq!(names, vec!["deep", "lib.rs"]);
+            assert!(src.children[0].children.is_empty());
+        } else {
+            panic!("Expected NestedTree result");
+        }
+    }
+
+    #[test]
+    fn test_list_directory_sorted() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join("b")).unwrap();
+        for name in ["c.txt", "b/z.txt", "a.txt", "b/a.txt", "b-c.txt"] {
+            fs::write(temp.path().join(name), "").unwrap();
+        }
+
+        let options = DirectoryOptions {
+            depth: 2,
+            ..Default::default()
+        };
+        let result = list_directory(temp.path(), &options).unwrap();
+
+        if let FsReadResult::Directory { entries, .. } = result {
+            let paths: Vec<&str> = entries.iter().map(|e| e.path.as_str()).collect();
+            let mut sorted = paths.clone();
+            sorted.sort();
+            assert_eq!(paths, sorted);
+            assert_eq!(paths.len(), 6);
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
+
+    #[test]
+    fn test_changed_since() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("file1.txt"), "content").unwrap();
+
+        let result = changed_since(temp.path(), 0, 0).unwrap();
+        if let FsReadResult::Directory { entries, total_count, .. } = result {
+            assert_eq!(total_count, 1);
+            assert!(entries[0].change.is_some());
+        } else {
+            panic!("Expected Directory result");
+        }
+
+        let result = changed_since(temp.path(), u64::MAX, 0).unwrap();
+        if let FsReadResult::Directory { total_count, .. } = result {
+            assert_eq!(total_count, 0);
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
 }
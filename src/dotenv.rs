@@ -0,0 +1,164 @@
+use crate::line::with_file_content;
+use crate::types::{kind_check, FsReadError, FsReadResult, PathKind};
+use std::path::Path;
+
+/// Parses `KEY=VALUE` lines as found in `.env` and `.properties` files.
+/// Blank lines and `#` comments are skipped, an `export ` prefix is
+/// dropped, and quoted values may span lines. Values are returned raw:
+/// quotes are removed but escapes and `${VAR}` references are left as is.
+pub fn read_dotenv(path: &Path) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    with_file_content(path, |content| {
+        let (pairs, malformed_lines) = parse(content);
+        Ok(FsReadResult::KeyValues {
+            pairs,
+            malformed_lines,
+            truncated: false,
+            omitted_count: 0,
+        })
+    })
+}
+
+fn parse(content: &str) -> (Vec<(String, String)>, Vec<usize>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut pairs = Vec::new();
+    let mut malformed = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line_number = i + 1;
+        let line = lines[i].trim();
+        i += 1;
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let line = line.strip_prefix("export ").map_or(line, str::trim_start);
+        let Some((key, value)) = line.split_once('=') else {
+            malformed.push(line_number);
+            continue;
+        };
+        let key = key.trim_end();
+        if key.is_empty() || key.contains(char::is_whitespace) {
+            malformed.push(line_number);
+            continue;
+        }
+
+        let value = value.trim_start();
+        let quote = match value.chars().next() {
+            Some(q @ ('"' | '\'')) => q,
+            _ => {
+                pairs.push((key.to_string(), strip_inline_comment(value).to_string()));
+                continue;
+            }
+        };
+
+        // Join following lines until the closing quote; an unterminated
+        // value marks only its first line, and parsing resumes after it
+        let mut quoted = value[1..].to_string();
+        let mut next = i;
+        loop {
+            if let Some(end) = closing_quote(&quoted, quote) {
+                let rest = quoted[end + 1..].trim();
+                if rest.is_empty() || rest.starts_with('#') {
+                    quoted.truncate(end);
+                    pairs.push((key.to_string(), quoted));
+                    i = next;
+                } else {
+                    malformed.push(line_number);
+                }
+                break;
+            }
+            let Some(line) = lines.get(next) else {
+                malformed.push(line_number);
+                break;
+            };
+            quoted.push('\n');
+            quoted.push_str(line);
+            next += 1;
+        }
+    }
+
+    (pairs, malformed)
+}
+
+/// Byte offset of the quote closing `value`; inside double quotes a
+/// backslash escapes the next character.
+fn closing_quote(value: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quote == '"' => escaped = true,
+            _ if c == quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// An unquoted value ends at a `#` preceded by whitespace.
+fn strip_inline_comment(value: &str) -> &str {
+    let end = value
+        .char_indices()
+        .find(|&(i, c)| c == '#' && value[..i].ends_with(char::is_whitespace))
+        .map_or(value.len(), |(i, _)| i);
+    value[..end].trim_end()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_read_dotenv() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join(".env");
+        let content = [
+            "# database",
+            "export DB_HOST=localhost",
+            "DB_URL = postgres://u:p@host/db#main # primary",
+            "GREETING=\"say \\\"hi\\\" to ${USER}\"",
+            "KEY='-----BEGIN",
+            "abc",
+            "-----END'",
+            "",
+            "not a pair",
+            "BAD KEY=1",
+            "EMPTY=",
+            "OPEN=\"never closed",
+            "AFTER=ok",
+        ]
+        .join("\n");
+        fs::write(&path, content).unwrap();
+
+        let result = read_dotenv(&path).unwrap();
+
+        if let FsReadResult::KeyValues {
+            pairs,
+            malformed_lines,
+            ..
+        } = result
+        {
+            let pairs: Vec<(&str, &str)> =
+                pairs.iter().map(|(k, v)| (k.as_str(), v.as_str())).collect();
+            assert_eq!(
+                pairs,
+                vec![
+                    ("DB_HOST", "localhost"),
+                    ("DB_URL", "postgres://u:p@host/db#main"),
+                    ("GREETING", "say \\\"hi\\\" to ${USER}"),
+                    ("KEY", "-----BEGIN\nabc\n-----END"),
+                    ("EMPTY", ""),
+                    ("AFTER", "ok"),
+                ]
+            );
+            assert_eq!(malformed_lines, vec![9, 10, 12]);
+        } else {
+            panic!("Expected KeyValues result");
+        }
+    }
+}
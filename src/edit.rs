@@ -0,0 +1,469 @@
+use crate::line::with_file_content;
+use crate::types::{kind_check, FsReadError, FsReadResult, LineEdit, PathKind};
+use std::ops::Range;
+use std::path::Path;
+
+// Unchanged lines shown around each change in the diff, as `diff -u` does
+const DIFF_CONTEXT: usize = 3;
+
+/// An edit resolved to 0-based, end-exclusive line indices.
+struct Resolved<'a> {
+    range: Range<usize>,
+    replacement: Vec<&'a str>,
+}
+
+/// Applies `edits` to the file in memory and returns the new content with a
+/// unified diff against the original. Nothing is written to disk.
+pub fn edit_preview(path: &Path, edits: &[LineEdit]) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    with_file_content(path, |original| {
+        let lines: Vec<&str> = original.lines().collect();
+        let resolved = resolve_edits(edits, lines.len())?;
+
+        // Applied bottom to top so earlier ranges keep their positions
+        let mut edited: Vec<&str> = lines.clone();
+        for edit in resolved.iter().rev() {
+            edited.splice(edit.range.clone(), edit.replacement.iter().copied());
+        }
+
+        let mut content = edited.join("\n");
+        if original.ends_with('\n') && !content.is_empty() {
+            content.push('\n');
+        }
+
+        let name = path.to_string_lossy();
+        Ok(FsReadResult::EditPreview {
+            content,
+            diff: unified_diff(&name, &lines, &resolved, DIFF_CONTEXT, false),
+        })
+    })
+}
+
+/// Unified diff from the file on disk to `content`, with `context_lines`
+/// unchanged lines around each change. Lines are compared exactly,
+/// terminators included, so a change in line endings, trailing whitespace
+/// or the final newline shows up as a change.
+pub fn diff_against(
+    path: &Path,
+    content: &str,
+    context_lines: u32,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    with_file_content(path, |original| {
+        let old: Vec<&str> = original.split_inclusive('\n').collect();
+        let new: Vec<&str> = content.split_inclusive('\n').collect();
+        let changes = diff_lines(&old, &new);
+
+        let name = path.to_string_lossy();
+        Ok(FsReadResult::Diff {
+            diff: unified_diff(&name, &old, &changes, context_lines as usize, true),
+            lines_added: changes.iter().map(|c| c.replacement.len()).sum(),
+            lines_removed: changes.iter().map(|c| c.range.len()).sum(),
+        })
+    })
+}
+
+/// The changes turning `old` into `new`, as ranges of `old` and the lines
+/// replacing them, found with Myers' algorithm so the diff is minimal. The
+/// linear-space variant keeps memory proportional to the inputs however
+/// different they are.
+fn diff_lines<'a>(old: &[&str], new: &[&'a str]) -> Vec<Resolved<'a>> {
+    let mut deleted = vec![false; old.len()];
+    let mut inserted = vec![false; new.len()];
+    mark_changes(old, new, &mut deleted, &mut inserted);
+
+    // Each run of marked lines between unchanged ones is one change
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        let is_deleted = |i: usize| i < old.len() && deleted[i];
+        let is_inserted = |j: usize| j < new.len() && inserted[j];
+        if !is_deleted(i) && !is_inserted(j) {
+            i += 1;
+            j += 1;
+            continue;
+        }
+
+        let start = i;
+        let mut replacement = Vec::new();
+        while is_deleted(i) || is_inserted(j) {
+            if is_deleted(i) {
+                i += 1;
+            } else {
+                replacement.push(new[j]);
+                j += 1;
+            }
+        }
+        changes.push(Resolved {
+            range: start..i,
+            replacement,
+        });
+    }
+    changes
+}
+
+/// Marks the lines of `a` deleted and of `b` inserted by a shortest edit
+/// script, splitting both at a middle snake and recursing on each side.
+fn mark_changes(a: &[&str], b: &[&str], deleted: &mut [bool], inserted: &mut [bool]) {
+    // Common ends are cheap to strip and keep the search small
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let a = &a[prefix..a.len() - suffix];
+    let b = &b[prefix..b.len() - suffix];
+    let deleted = &mut deleted[prefix..prefix + a.len()];
+    let inserted = &mut inserted[prefix..prefix + b.len()];
+
+    if a.is_empty() || b.is_empty() {
+        deleted.fill(true);
+        inserted.fill(true);
+        return;
+    }
+
+    let (x, y) = middle_snake(a, b);
+    let (deleted_head, deleted_tail) = deleted.split_at_mut(x);
+    let (inserted_head, inserted_tail) = inserted.split_at_mut(y);
+    mark_changes(&a[..x], &b[..y], deleted_head, inserted_head);
+    mark_changes(&a[x..], &b[y..], deleted_tail, inserted_tail);
+}
+
+/// A point `(x, y)` on some shortest edit path from the start of `a` and
+/// `b` to their ends, found by searching forward from the start and
+/// backward from the end until the two searches overlap. Both inputs are
+/// non-empty and differ in their first and last lines, so the point is
+/// neither the start nor the end.
+fn middle_snake(a: &[&str], b: &[&str]) -> (usize, usize) {
+    let (n, m) = (a.len() as isize, b.len() as isize);
+    let delta = n - m;
+    let odd = delta % 2 != 0;
+    let max_d = (n + m + 1) / 2;
+
+    // `forward[k + offset]` is the furthest x reached on diagonal k = x - y
+    // from the start; `backward` the same counted from the ends
+    let offset = max_d + 1;
+    let mut forward = vec![0; 2 * offset as usize + 1];
+    let mut backward = vec![0; 2 * offset as usize + 1];
+    let at = |k: isize| (k + offset) as usize;
+
+    for d in 0..=max_d {
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && forward[at(k - 1)] < forward[at(k + 1)]) {
+                forward[at(k + 1)]
+            } else {
+                forward[at(k - 1)] + 1
+            };
+            let (x0, y0) = (x, x - k);
+            let mut y = y0;
+            while x < n && y < m && a[x as usize] == b[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[at(k)] = x;
+            if odd && (k - delta).abs() < d && x + backward[at(delta - k)] >= n {
+                return (x0 as usize, y0 as usize);
+            }
+        }
+
+        for k in (-d..=d).rev().step_by(2) {
+            let mut x = if k == -d || (k != d && backward[at(k - 1)] < backward[at(k + 1)]) {
+                backward[at(k + 1)]
+            } else {
+                backward[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            while x < n && y < m && a[(n - x - 1) as usize] == b[(m - y - 1) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[at(k)] = x;
+            if !odd && (k - delta).abs() <= d && x + forward[at(delta - k)] >= n {
+                return ((n - x) as usize, (m - y) as usize);
+            }
+        }
+    }
+    unreachable!("the searches meet within (n + m + 1) / 2 edits")
+}
+
+/// Validates each edit against the file length and sorts them, rejecting
+/// any pair that overlaps or inserts at the same position.
+fn resolve_edits(edits: &[LineEdit], total_lines: usize) -> Result<Vec<Resolved<'_>>, FsReadError> {
+    let mut resolved = Vec::with_capacity(edits.len());
+
+    for edit in edits {
+        // `end_line == start_line - 1` is an insertion before `start_line`
+        let valid = edit.start_line >= 1
+            && edit.end_line.saturating_add(1) >= edit.start_line
+            && edit.end_line as usize <= total_lines;
+        if !valid {
+            return Err(FsReadError::InvalidLineRange(
+                edit.start_line as i32,
+                edit.end_line as i32,
+            ));
+        }
+
+        let replacement = if edit.replacement.is_empty() {
+            Vec::new()
+        } else {
+            edit.replacement.lines().collect()
+        };
+        resolved.push(Resolved {
+            range: edit.start_line as usize - 1..edit.end_line as usize,
+            replacement,
+        });
+    }
+
+    resolved.sort_by_key(|e| (e.range.start, e.range.end));
+
+    for pair in resolved.windows(2) {
+        let (a, b) = (&pair[0].range, &pair[1].range);
+        if b.start < a.end || b.start == a.start {
+            return Err(FsReadError::InvalidEdit(format!(
+                "lines {}-{} overlap lines {}-{}",
+                a.start + 1,
+                a.end,
+                b.start + 1,
+                b.end
+            )));
+        }
+    }
+
+    Ok(resolved)
+}
+
+/// With `terminated`, `lines` and the replacements keep their line
+/// terminators, and a line without one is the last of a file with no final
+/// newline.
+fn unified_diff(
+    name: &str,
+    lines: &[&str],
+    edits: &[Resolved],
+    context: usize,
+    terminated: bool,
+) -> String {
+    if edits.is_empty() {
+        return String::new();
+    }
+
+    let mut diff = format!("--- a/{}\n+++ b/{}\n", name, name);
+    // Net line count change from all edits before the current hunk
+    let mut offset: isize = 0;
+    let mut i = 0;
+
+    while i < edits.len() {
+        // Edits whose context windows touch share a hunk
+        let mut j = i + 1;
+        while j < edits.len() && edits[j].range.start <= edits[j - 1].range.end + 2 * context {
+            j += 1;
+        }
+        let group = &edits[i..j];
+
+        let old_start = group[0].range.start.saturating_sub(context);
+        let old_end = (group[j - i - 1].range.end + context).min(lines.len());
+        let mut body = String::new();
+        let mut new_len = 0;
+        let mut pos = old_start;
+
+        for edit in group {
+            for line in &lines[pos..edit.range.start] {
+                push_line(&mut body, ' ', line, terminated);
+                new_len += 1;
+            }
+            for line in &lines[edit.range.clone()] {
+                push_line(&mut body, '-', line, terminated);
+            }
+            for line in &edit.replacement {
+                push_line(&mut body, '+', line, terminated);
+                new_len += 1;
+            }
+            pos = edit.range.end;
+        }
+        for line in &lines[pos..old_end] {
+            push_line(&mut body, ' ', line, terminated);
+            new_len += 1;
+        }
+
+        let old_len = old_end - old_start;
+        let new_start = (old_start as isize + offset) as usize;
+        diff.push_str(&format!(
+            "@@ -{} +{} @@\n",
+            hunk_range(old_start, old_len),
+            hunk_range(new_start, new_len)
+        ));
+        diff.push_str(&body);
+
+        offset += new_len as isize - old_len as isize;
+        i = j;
+    }
+
+    diff
+}
+
+fn push_line(body: &mut String, prefix: char, line: &str, terminated: bool) {
+    body.push(prefix);
+    body.push_str(line);
+    if !terminated {
+        body.push('\n');
+    } else if !line.ends_with('\n') {
+        body.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+/// `start,len` with a 1-based start, except that an empty range names the
+/// line before it, as `diff -u` does.
+fn hunk_range(start: usize, len: usize) -> String {
+    if len == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    fn edit(start_line: u32, end_line: u32, replacement: &str) -> LineEdit {
+        LineEdit {
+            start_line,
+            end_line,
+            replacement: replacement.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_edit_preview() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("f.txt");
+        let original: Vec<String> = (1..=12).map(|i| format!("l{}", i)).collect();
+        fs::write(&path, original.join("\n") + "\n").unwrap();
+
+        let edits = [edit(11, 11, ""), edit(2, 3, "two\nthree\nextra"), edit(6, 5, "new")];
+        let result = edit_preview(&path, &edits).unwrap();
+
+        if let FsReadResult::EditPreview { content, diff } = result {
+            assert_eq!(
+                content,
+                "l1\ntwo\nthree\nextra\nl4\nl5\nnew\nl6\nl7\nl8\nl9\nl10\nl12\n"
+            );
+            let name = path.to_string_lossy();
+            assert_eq!(
+                diff,
+                format!(
+                    "--- a/{n}\n+++ b/{n}\n@@ -1,12 +1,13 @@\n l1\n-l2\n-l3\n+two\n+three\n+extra\n l4\n l5\n+new\n l6\n l7\n l8\n l9\n l10\n-l11\n l12\n",
+                    n = name
+                )
+            );
+        } else {
+            panic!("Expected EditPreview result");
+        }
+
+        assert!(fs::read_to_string(&path).unwrap().contains("l11"));
+    }
+
+    #[test]
+    fn test_edit_preview_rejects_overlap() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("f.txt");
+        fs::write(&path, "a\nb\nc\nd").unwrap();
+
+        let err = edit_preview(&path, &[edit(1, 2, "x"), edit(2, 3, "y")]).unwrap_err();
+        assert!(matches!(err, FsReadError::InvalidEdit(_)));
+
+        let err = edit_preview(&path, &[edit(3, 5, "x")]).unwrap_err();
+        assert!(matches!(err, FsReadError::InvalidLineRange(3, 5)));
+
+        let err = edit_preview(&path, &[edit(1, u32::MAX, "x")]).unwrap_err();
+        assert!(matches!(err, FsReadError::InvalidLineRange(1, _)));
+    }
+
+    #[test]
+    fn test_diff_against() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("f.txt");
+        let original: Vec<String> = (1..=12).map(|i| format!("l{}", i)).collect();
+        fs::write(&path, original.join("\n") + "\n").unwrap();
+
+        let diff = |content: &str, context_lines| match diff_against(&path, content, context_lines)
+        {
+            Ok(FsReadResult::Diff {
+                diff,
+                lines_added,
+                lines_removed,
+            }) => (diff, lines_added, lines_removed),
+            _ => panic!("Expected Diff result"),
+        };
+        let name = path.to_string_lossy();
+
+        // The same edits as the EditPreview test give the same diff
+        let edited = "l1\ntwo\nthree\nextra\nl4\nl5\nnew\nl6\nl7\nl8\nl9\nl10\nl12\n";
+        let expected = format!(
+            "--- a/{n}\n+++ b/{n}\n@@ -1,12 +1,13 @@\n l1\n-l2\n-l3\n+two\n+three\n+extra\n l4\n l5\n+new\n l6\n l7\n l8\n l9\n l10\n-l11\n l12\n",
+            n = name
+        );
+        assert_eq!(diff(edited, 3), (expected, 4, 3));
+
+        let separate = format!(
+            "--- a/{n}\n+++ b/{n}\n@@ -2,0 +3,1 @@\n+new\n@@ -12,1 +12,0 @@\n-l12\n",
+            n = name
+        );
+        let content = "l1\nl2\nnew\nl3\nl4\nl5\nl6\nl7\nl8\nl9\nl10\nl11\n";
+        assert_eq!(diff(content, 0), (separate, 1, 1));
+
+        assert_eq!(diff(&(original.join("\n") + "\n"), 3), (String::new(), 0, 0));
+        assert_eq!(diff("", 3).2, 12);
+
+        // Dropping the final newline changes the last line
+        let no_newline = format!(
+            "--- a/{n}\n+++ b/{n}\n@@ -12,1 +12,1 @@\n-l12\n+l12\n\\ No newline at end of file\n",
+            n = name
+        );
+        assert_eq!(diff(&original.join("\n"), 0), (no_newline, 1, 1));
+
+        // So does switching line endings
+        fs::write(&path, "a\r\nb\r\n").unwrap();
+        let crlf = format!(
+            "--- a/{n}\n+++ b/{n}\n@@ -1,2 +1,2 @@\n-a\r\n-b\r\n+a\n+b\n",
+            n = name
+        );
+        assert_eq!(diff("a\nb\n", 0), (crlf, 2, 2));
+    }
+
+    #[test]
+    fn test_diff_lines_large() {
+        // Nothing in common: one change replacing every line
+        let old: Vec<String> = (0..4000).map(|i| format!("old {}", i)).collect();
+        let new: Vec<String> = (0..3000).map(|i| format!("new {}", i)).collect();
+        let old: Vec<&str> = old.iter().map(String::as_str).collect();
+        let new: Vec<&str> = new.iter().map(String::as_str).collect();
+        let changes = diff_lines(&old, &new);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].range, 0..4000);
+        assert_eq!(changes[0].replacement, new);
+
+        // Every third line changed: applying the changes gives `new` back
+        // and each line is only touched once
+        let edited: Vec<String> = (0..4000)
+            .map(|i| match i % 3 {
+                0 => format!("changed {}", i),
+                _ => format!("old {}", i),
+            })
+            .collect();
+        let edited: Vec<&str> = edited.iter().map(String::as_str).collect();
+        let changes = diff_lines(&old, &edited);
+        assert_eq!(changes.len(), 1334);
+        let mut applied = old.clone();
+        for change in changes.iter().rev() {
+            assert_eq!(change.range.len(), 1);
+            applied.splice(change.range.clone(), change.replacement.iter().copied());
+        }
+        assert_eq!(applied, edited);
+    }
+}
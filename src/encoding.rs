@@ -0,0 +1,173 @@
+use crate::binary::looks_binary;
+use crate::types::{kind_check, FsReadError, FsReadResult, PathKind};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+/// How many leading bytes the encoding sniff looks at.
+const SNIFF_BYTES: usize = 8 * 1024;
+
+pub const UTF8: &str = "UTF-8";
+pub const UTF8_BOM: &str = "UTF-8-BOM";
+pub const UTF16_LE: &str = "UTF-16LE";
+pub const UTF16_BE: &str = "UTF-16BE";
+pub const LATIN1: &str = "Latin-1";
+
+/// Lightweight encoding guess from the head of the file: a BOM wins, then
+/// UTF-16 without BOM is recognised by NUL bytes in alternating positions,
+/// then valid UTF-8, and anything else is assumed to be Latin-1.
+pub fn sniff_file_encoding(path: &Path) -> std::io::Result<&'static str> {
+    let mut head = Vec::with_capacity(SNIFF_BYTES);
+    File::open(path)?.take(SNIFF_BYTES as u64).read_to_end(&mut head)?;
+    Ok(sniff_encoding(&head))
+}
+
+pub fn sniff_encoding(bytes: &[u8]) -> &'static str {
+    if bytes.starts_with(&[0xEF, 0xBB, 0xBF]) {
+        return UTF8_BOM;
+    }
+    if bytes.starts_with(&[0xFF, 0xFE]) {
+        return UTF16_LE;
+    }
+    if bytes.starts_with(&[0xFE, 0xFF]) {
+        return UTF16_BE;
+    }
+
+    if bytes.len() >= 2 {
+        let pairs = bytes.len() / 2;
+        let even_nuls = bytes.iter().step_by(2).filter(|&&b| b == 0).count();
+        let odd_nuls = bytes.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+        if odd_nuls * 2 > pairs && even_nuls * 10 < pairs {
+            return UTF16_LE;
+        }
+        if even_nuls * 2 > pairs && odd_nuls * 10 < pairs {
+            return UTF16_BE;
+        }
+    }
+
+    match std::str::from_utf8(bytes) {
+        Ok(_) => UTF8,
+        // The sample may end in the middle of a multi-byte sequence
+        Err(e) if e.error_len().is_none() => UTF8,
+        Err(_) => LATIN1,
+    }
+}
+
+/// `bytes` decoded from an `encoding` returned by `sniff_encoding`, with any
+/// BOM dropped and undecodable units replaced by U+FFFD. `None` for the
+/// UTF-8 encodings, which are read as they are, and for Latin-1 content that
+/// looks binary rather than like text.
+pub fn decode(bytes: &[u8], encoding: &str) -> Option<String> {
+    let utf16 = |bom: [u8; 2], unit: fn([u8; 2]) -> u16| {
+        let bytes = bytes.strip_prefix(&bom).unwrap_or(bytes);
+        let chunks = bytes.chunks_exact(2);
+        let odd_byte = !chunks.remainder().is_empty();
+        let mut text: String = char::decode_utf16(chunks.map(|c| unit([c[0], c[1]])))
+            .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+            .collect();
+        if odd_byte {
+            text.push(char::REPLACEMENT_CHARACTER);
+        }
+        text
+    };
+
+    match encoding {
+        UTF16_LE => Some(utf16([0xFF, 0xFE], u16::from_le_bytes)),
+        UTF16_BE => Some(utf16([0xFE, 0xFF], u16::from_be_bytes)),
+        LATIN1 if !looks_binary(bytes) => Some(bytes.iter().map(|&b| b as char).collect()),
+        _ => None,
+    }
+}
+
+/// Checks whether the file is valid UTF-8 and, with `repair`, returns a copy
+/// with each invalid sequence replaced by U+FFFD.
+pub fn validate_utf8(path: &Path, repair: bool) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+    let bytes = std::fs::read(path)?;
+
+    let first_invalid_offset = std::str::from_utf8(&bytes).err().map(|e| e.valid_up_to());
+
+    let (repaired, replacements) = if repair {
+        let (content, replacements) = repair_utf8(&bytes);
+        (Some(content), replacements)
+    } else {
+        (None, 0)
+    };
+
+    Ok(FsReadResult::Validate {
+        is_valid_utf8: first_invalid_offset.is_none(),
+        first_invalid_offset,
+        repaired,
+        replacements,
+    })
+}
+
+/// Same output as `String::from_utf8_lossy`, plus the number of
+/// replacement characters it inserted.
+fn repair_utf8(bytes: &[u8]) -> (String, usize) {
+    let mut content = String::with_capacity(bytes.len());
+    let mut replacements = 0;
+
+    for chunk in bytes.utf8_chunks() {
+        content.push_str(chunk.valid());
+        if !chunk.invalid().is_empty() {
+            content.push(char::REPLACEMENT_CHARACTER);
+            replacements += 1;
+        }
+    }
+
+    (content, replacements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sniff_encoding() {
+        assert_eq!(sniff_encoding(b""), UTF8);
+        assert_eq!(sniff_encoding("héllo".as_bytes()), UTF8);
+        assert_eq!(sniff_encoding(&"héllo".as_bytes()[..2]), UTF8);
+        assert_eq!(sniff_encoding(b"\xEF\xBB\xBFhi"), UTF8_BOM);
+        assert_eq!(sniff_encoding(b"\xFF\xFEh\x00i\x00"), UTF16_LE);
+        assert_eq!(sniff_encoding(b"h\x00i\x00!\x00"), UTF16_LE);
+        assert_eq!(sniff_encoding(b"\x00h\x00i\x00!"), UTF16_BE);
+        assert_eq!(sniff_encoding(b"caf\xE9 cr\xE8me"), LATIN1);
+    }
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode(b"\xFF\xFEh\x00\xE9\x00", UTF16_LE).unwrap(), "hé");
+        assert_eq!(decode(b"\x00h\x00i\x00", UTF16_BE).unwrap(), "hi\u{FFFD}");
+        assert_eq!(decode(b"caf\xE9", LATIN1).unwrap(), "café");
+        assert_eq!(decode(b"\x7fELF\x00\xE9", LATIN1), None);
+        assert_eq!(decode(b"plain", UTF8), None);
+    }
+
+    #[test]
+    fn test_repair_utf8() {
+        let bytes = b"caf\xE9 ok \xF0\x9F\x98 \xFF\xFE";
+        let (content, replacements) = repair_utf8(bytes);
+
+        assert_eq!(content, String::from_utf8_lossy(bytes));
+        assert_eq!(replacements, 4);
+        assert_eq!(repair_utf8("héllo".as_bytes()), ("héllo".to_string(), 0));
+
+        let temp = crate::test_util::TempDir::new().unwrap();
+        let path = temp.path().join("bad.txt");
+        std::fs::write(&path, bytes).unwrap();
+        if let FsReadResult::Validate {
+            is_valid_utf8,
+            first_invalid_offset,
+            repaired,
+            ..
+        } = validate_utf8(&path, false).unwrap()
+        {
+            assert!(!is_valid_utf8);
+            assert_eq!(first_invalid_offset, Some(3));
+            assert!(repaired.is_none());
+        } else {
+            panic!("Expected Validate result");
+        }
+    }
+}
@@ -0,0 +1,209 @@
+use crate::directory::{walk_entries, WalkOptions};
+use crate::types::{kind_check, FsReadError, FsReadResult, FuzzyMatch, PathKind};
+use std::path::Path;
+
+// Skim-style scoring: every matched char earns SCORE_MATCH, matches at word
+// boundaries or continuing a run earn bonuses, and gaps between matches are
+// penalised (opening a gap costs more than extending one).
+const SCORE_MATCH: i64 = 16;
+const BONUS_BOUNDARY: i64 = 8;
+const BONUS_FIRST_CHAR: i64 = 8;
+const BONUS_CAMEL: i64 = 7;
+const BONUS_CONSECUTIVE: i64 = 4;
+const PENALTY_GAP_START: i64 = 3;
+const PENALTY_GAP_EXTENSION: i64 = 1;
+
+/// Scores every path under `path` (ignore rules applied) against `query` and
+/// returns the best `max_results`, highest score first.
+pub fn fuzzy_find(
+    path: &Path,
+    query: &str,
+    max_results: usize,
+    min_score: Option<i64>,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let mut matches = Vec::new();
+
+    let walk = WalkOptions {
+        depth: u32::MAX,
+        ..Default::default()
+    };
+
+    walk_entries(path, &walk, |relative_path, _| {
+        if let Some((score, match_indices)) = fuzzy_score(query, &relative_path) {
+            if min_score.is_none_or(|min| score >= min) {
+                matches.push(FuzzyMatch {
+                    path: relative_path,
+                    score,
+                    match_indices,
+                });
+            }
+        }
+    })?;
+
+    matches.sort_by(|a, b| b.score.cmp(&a.score).then_with(|| a.path.cmp(&b.path)));
+    matches.truncate(max_results);
+
+    Ok(FsReadResult::FuzzyFind {
+        matches,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// Best-scoring alignment of `query` as a subsequence of `candidate`, with
+/// the char indices it matched. Smart case: case-insensitive unless the
+/// query contains an uppercase letter.
+pub(crate) fn fuzzy_score(query: &str, candidate: &str) -> Option<(i64, Vec<usize>)> {
+    let case_sensitive = query.chars().any(char::is_uppercase);
+    let fold = |c: char| {
+        if case_sensitive {
+            c
+        } else {
+            c.to_lowercase().next().unwrap_or(c)
+        }
+    };
+
+    let query: Vec<char> = query.chars().map(fold).collect();
+    let original: Vec<char> = candidate.chars().collect();
+    let chars: Vec<char> = original.iter().copied().map(fold).collect();
+    let (m, n) = (query.len(), chars.len());
+
+    if m == 0 {
+        return Some((0, Vec::new()));
+    }
+
+    // Cheap subsequence check before the quadratic pass
+    let mut qi = 0;
+    for &c in &chars {
+        if qi < m && c == query[qi] {
+            qi += 1;
+        }
+    }
+    if qi < m {
+        return None;
+    }
+
+    let bonus: Vec<i64> = (0..n).map(|j| position_bonus(&original, j)).collect();
+
+    // score[i][j]: best score with query[i] matched at candidate[j]
+    // from[i][j]: where query[i - 1] was matched on that best path
+    let mut score = vec![vec![None::<i64>; n]; m];
+    let mut from = vec![vec![0usize; n]; m];
+
+    for j in 0..n {
+        if chars[j] == query[0] {
+            score[0][j] = Some(SCORE_MATCH + bonus[j]);
+        }
+    }
+
+    for i in 1..m {
+        // Best of score[i - 1][k] + PENALTY_GAP_EXTENSION * k over k <= j - 2,
+        // so a gap from k to j costs a constant offset from it.
+        let mut best_gap: Option<(i64, usize)> = None;
+
+        for j in 1..n {
+            if j >= 2 {
+                if let Some(prev) = score[i - 1][j - 2] {
+                    let value = prev + PENALTY_GAP_EXTENSION * (j as i64 - 2);
+                    if best_gap.is_none_or(|(best, _)| value > best) {
+                        best_gap = Some((value, j - 2));
+                    }
+                }
+            }
+
+            if chars[j] != query[i] {
+                continue;
+            }
+
+            let consecutive = score[i - 1][j - 1].map(|prev| (prev + BONUS_CONSECUTIVE, j - 1));
+            let gapped = best_gap.map(|(value, k)| {
+                let gap_cost = PENALTY_GAP_START + PENALTY_GAP_EXTENSION * (j as i64 - 2);
+                (value - gap_cost, k)
+            });
+
+            let best = match (consecutive, gapped) {
+                (Some(c), Some(g)) => Some(if g.0 > c.0 { g } else { c }),
+                (c, g) => c.or(g),
+            };
+
+            if let Some((prev, k)) = best {
+                score[i][j] = Some(prev + SCORE_MATCH + bonus[j]);
+                from[i][j] = k;
+            }
+        }
+    }
+
+    let (mut j, best) = (0..n)
+        .filter_map(|j| score[m - 1][j].map(|s| (j, s)))
+        .max_by(|a, b| a.1.cmp(&b.1).then_with(|| b.0.cmp(&a.0)))?;
+
+    let mut indices = vec![0; m];
+    for i in (0..m).rev() {
+        indices[i] = j;
+        j = from[i][j];
+    }
+
+    Some((best, indices))
+}
+
+fn position_bonus(chars: &[char], j: usize) -> i64 {
+    if j == 0 {
+        return BONUS_FIRST_CHAR + BONUS_BOUNDARY;
+    }
+
+    let (prev, current) = (chars[j - 1], chars[j]);
+    if matches!(prev, '/' | '\\' | '_' | '-' | '.' | ' ') {
+        BONUS_BOUNDARY
+    } else if prev.is_lowercase() && current.is_uppercase() {
+        BONUS_CAMEL
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_fuzzy_score_prefers_boundaries_and_runs() {
+        let (score, indices) = fuzzy_score("srch", "src/search.rs").unwrap();
+        assert_eq!(indices.len(), 4);
+        assert!(score > 0);
+
+        let (boundary, _) = fuzzy_score("fb", "foo_bar").unwrap();
+        let (inner, _) = fuzzy_score("fb", "fooxbar").unwrap();
+        assert!(boundary > inner);
+
+        let (run, indices) = fuzzy_score("main", "src/main.rs").unwrap();
+        assert_eq!(indices, vec![4, 5, 6, 7]);
+        let (scattered, _) = fuzzy_score("main", "src/mxaxixn.rs").unwrap();
+        assert!(run > scattered);
+
+        assert!(fuzzy_score("xyz", "src/main.rs").is_none());
+        assert!(fuzzy_score("Main", "src/main.rs").is_none());
+    }
+
+    #[test]
+    fn test_fuzzy_find_ranks_results() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir(temp.path().join("src")).unwrap();
+        fs::write(temp.path().join("src/directory.rs"), "").unwrap();
+        fs::write(temp.path().join("src/search.rs"), "").unwrap();
+        fs::create_dir(temp.path().join("node_modules")).unwrap();
+        fs::write(temp.path().join("node_modules/directory.js"), "").unwrap();
+
+        let result = fuzzy_find(temp.path(), "dirrs", 10, None).unwrap();
+
+        if let FsReadResult::FuzzyFind { matches, .. } = result {
+            assert_eq!(matches.len(), 1);
+            assert!(matches[0].path.ends_with("directory.rs"));
+        } else {
+            panic!("Expected FuzzyFind result");
+        }
+    }
+}
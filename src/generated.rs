@@ -0,0 +1,114 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+/// Only this much of a file's head is sniffed for a generated marker.
+const MARKER_SNIFF_BYTES: u64 = 1024;
+const MARKER_SNIFF_LINES: usize = 5;
+
+const GENERATED_MARKERS: &[&str] = &["@generated", "DO NOT EDIT"];
+
+/// Vendored or build-output directories, skipped as a whole.
+const GENERATED_DIRS: &[&str] = &["vendor", "third_party", "dist", "generated"];
+
+/// Lockfiles and other files that are always machine-written.
+const GENERATED_NAMES: &[&str] = &[
+    "package-lock.json",
+    "yarn.lock",
+    "pnpm-lock.yaml",
+    "Cargo.lock",
+    "poetry.lock",
+    "Pipfile.lock",
+    "composer.lock",
+    "Gemfile.lock",
+    "go.sum",
+];
+
+const GENERATED_SUFFIXES: &[&str] = &[
+    ".pb.go",
+    ".pb.cc",
+    ".pb.h",
+    "_pb2.py",
+    "_pb2.pyi",
+    "_pb2_grpc.py",
+    ".g.dart",
+    ".freezed.dart",
+    ".min.js",
+    ".min.css",
+    ".js.map",
+    "_generated.go",
+];
+
+/// Whether `path` looks generated or vendored: a known directory name, a
+/// known file name or suffix, or a `@generated` / `DO NOT EDIT` marker in
+/// the first few lines. Only the file head is read for the marker.
+pub fn is_generated(path: &Path, is_dir: bool) -> bool {
+    let Some(name) = path.file_name().map(|n| n.to_string_lossy()) else {
+        return false;
+    };
+
+    if is_dir {
+        return GENERATED_DIRS.contains(&name.as_ref());
+    }
+
+    GENERATED_NAMES.contains(&name.as_ref())
+        || GENERATED_SUFFIXES.iter().any(|s| name.ends_with(s))
+        || name.starts_with("zz_generated")
+        || has_generated_marker(path)
+}
+
+fn has_generated_marker(path: &Path) -> bool {
+    let Ok(file) = File::open(path) else {
+        return false;
+    };
+    let mut head = BufReader::new(file.take(MARKER_SNIFF_BYTES));
+    let mut line = Vec::new();
+
+    for _ in 0..MARKER_SNIFF_LINES {
+        line.clear();
+        if !matches!(head.read_until(b'\n', &mut line), Ok(n) if n > 0) {
+            break;
+        }
+        let text = String::from_utf8_lossy(&line);
+        if GENERATED_MARKERS.iter().any(|m| text.contains(m)) {
+            return true;
+        }
+    }
+
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_is_generated() {
+        let temp = TempDir::new().unwrap();
+        let write = |name: &str, content: &str| {
+            let path = temp.path().join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+
+        assert!(is_generated(&write("api.pb.go", "package api"), false));
+        assert!(is_generated(&write("msg_pb2.py", ""), false));
+        assert!(is_generated(&write("Cargo.lock", ""), false));
+        assert!(is_generated(
+            &write("schema.go", "// Code generated by sqlc. DO NOT EDIT.\npackage db\n"),
+            false
+        ));
+        assert!(is_generated(&write("types.ts", "/**\n * @generated\n */\n"), false));
+        assert!(!is_generated(&write("main.go", "package main\n// DO NOT EDIT below"), true));
+        assert!(!is_generated(&write("lib.rs", "fn main() {}\n"), false));
+
+        // The marker only counts in the file head
+        let late = format!("{}// @generated\n", "x\n".repeat(MARKER_SNIFF_LINES));
+        assert!(!is_generated(&write("late.rs", &late), false));
+
+        assert!(is_generated(Path::new("vendor"), true));
+        assert!(!is_generated(Path::new("src"), true));
+    }
+}
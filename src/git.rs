@@ -0,0 +1,382 @@
+use crate::line::resolve_line_range;
+use crate::types::{kind_check, FsReadError, FsReadResult, GitStatus, PathKind};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Working tree status for a repository, loaded with a single
+/// `git status --porcelain` call rather than one query per file.
+pub struct RepoStatus {
+    // Walk root relative to the repository top level
+    prefix: PathBuf,
+    statuses: HashMap<PathBuf, GitStatus>,
+}
+
+impl RepoStatus {
+    /// Returns `None` when `root` is not inside a git work tree or git is
+    /// unavailable.
+    pub fn load(root: &Path) -> Option<Self> {
+        let toplevel = run_git(root, &["rev-parse", "--show-toplevel"])?;
+        let toplevel = PathBuf::from(toplevel.trim_end()).canonicalize().ok()?;
+        let prefix = root
+            .canonicalize()
+            .ok()?
+            .strip_prefix(&toplevel)
+            .ok()?
+            .to_path_buf();
+
+        let output = run_git(
+            &toplevel,
+            &["status", "--porcelain=v1", "-z", "--untracked-files=all"],
+        )?;
+
+        Some(Self {
+            prefix,
+            statuses: parse_porcelain(&output),
+        })
+    }
+
+    /// Status of a path relative to the walk root. Directories report the
+    /// most significant status of anything beneath them.
+    pub fn status_of(&self, relative: &Path, is_dir: bool) -> GitStatus {
+        let path = self.prefix.join(relative);
+
+        if is_dir {
+            return self
+                .statuses
+                .iter()
+                .filter(|(p, _)| p.starts_with(&path))
+                .map(|(_, s)| *s)
+                .max()
+                .unwrap_or(GitStatus::Clean);
+        }
+
+        self.statuses.get(&path).copied().unwrap_or(GitStatus::Clean)
+    }
+}
+
+/// Files changed between `base` and `head` (the working tree when `None`),
+/// with paths relative to `path` and limited to files beneath it. Untracked
+/// files aren't part of a diff and are not reported.
+pub fn changed_between(
+    path: &Path,
+    base: &str,
+    head: Option<&str>,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::Directory))?;
+
+    let base = resolve_commit(path, base)?;
+    let head = head.map(|head| resolve_commit(path, head)).transpose()?;
+    let mut args = vec![
+        "diff",
+        "--name-status",
+        "-z",
+        "-M",
+        "--relative",
+        "--end-of-options",
+        &base,
+    ];
+    args.extend(head.as_deref());
+    // Refs can't be mistaken for paths
+    args.push("--");
+
+    let output = run_git_checked(path, &args)?;
+    Ok(parse_name_status(&output))
+}
+
+/// Content of the file at `path` as of `revision`, without checking it
+/// out, cut to `start_line..=end_line` as in Line mode. The file doesn't
+/// have to exist in the working tree any more, but its directory does.
+pub fn show_at_revision(
+    path: &Path,
+    revision: &str,
+    start_line: Option<i32>,
+    end_line: Option<i32>,
+) -> Result<FsReadResult, FsReadError> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Err(FsReadError::InvalidPath(path.to_string_lossy().to_string()));
+    };
+    let dir = if dir.as_os_str().is_empty() { Path::new(".") } else { dir };
+    kind_check(dir, Some(PathKind::Directory))?;
+
+    let commit = resolve_commit(dir, revision)?;
+    let commit = commit.as_str();
+    // `./` makes the path relative to `dir` rather than the repository root
+    let object = format!("{}:./{}", commit, name.to_string_lossy());
+    if run_git(dir, &["cat-file", "-e", &object]).is_none() {
+        return Err(FsReadError::Git(format!(
+            "{} did not exist at {}",
+            path.display(),
+            revision
+        )));
+    }
+    let content = run_git_checked(dir, &["cat-file", "blob", &object])?;
+
+    let lines: Vec<&str> = content.lines().collect();
+    let (start_idx, end_idx) = resolve_line_range(start_line, end_line, lines.len())?;
+
+    Ok(FsReadResult::GitShow {
+        content: lines[start_idx..end_idx].join("\n"),
+        total_lines: lines.len(),
+        lines_returned: end_idx - start_idx,
+        commit: commit.to_string(),
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// Full hash of the commit `revision` names. Resolving refs before passing
+/// them on keeps one starting with `-` from being read as an option.
+fn resolve_commit(dir: &Path, revision: &str) -> Result<String, FsReadError> {
+    let spec = format!("{}^{{commit}}", revision);
+    let commit = run_git_checked(dir, &["rev-parse", "--verify", "--end-of-options", &spec])?;
+    Ok(commit.trim().to_string())
+}
+
+/// Walks up from `path` to the nearest directory containing `.git`. A `.git`
+/// file is a pointer (`gitdir: ...`) to a git dir elsewhere; it marks a
+/// linked worktree when that git dir lives under a `worktrees` directory,
+/// and a submodule otherwise.
+pub fn repo_root(path: &Path) -> Result<FsReadResult, FsReadError> {
+    let kind = kind_check(path, None)?;
+    let path = path.canonicalize()?;
+    let start = match kind {
+        PathKind::Directory => path.as_path(),
+        _ => path.parent().unwrap_or(&path),
+    };
+
+    for dir in start.ancestors() {
+        let dot_git = dir.join(".git");
+        let Ok(metadata) = std::fs::metadata(&dot_git) else {
+            continue;
+        };
+
+        let is_worktree = metadata.is_file()
+            && std::fs::read_to_string(&dot_git)
+                .ok()
+                .and_then(|c| c.lines().next()?.strip_prefix("gitdir:").map(str::to_string))
+                .is_some_and(|gitdir| {
+                    Path::new(gitdir.trim())
+                        .parent()
+                        .is_some_and(|p| p.ends_with("worktrees"))
+                });
+
+        return Ok(FsReadResult::RepoRoot {
+            root: Some(dir.to_string_lossy().to_string()),
+            is_worktree,
+        });
+    }
+
+    Ok(FsReadResult::RepoRoot {
+        root: None,
+        is_worktree: false,
+    })
+}
+
+fn run_git(dir: &Path, args: &[&str]) -> Option<String> {
+    run_git_checked(dir, args).ok()
+}
+
+/// Like `run_git`, but reports git's stderr when the command fails.
+fn run_git_checked(dir: &Path, args: &[&str]) -> Result<String, FsReadError> {
+    let output = Command::new("git").arg("-C").arg(dir).args(args).output()?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(FsReadError::Git(stderr.trim().to_string()));
+    }
+    String::from_utf8(output.stdout).map_err(|e| FsReadError::Git(e.to_string()))
+}
+
+fn parse_name_status(output: &str) -> FsReadResult {
+    let mut added = Vec::new();
+    let mut modified = Vec::new();
+    let mut deleted = Vec::new();
+    let mut renamed = Vec::new();
+    let mut records = output.split('\0').filter(|r| !r.is_empty());
+
+    while let Some(status) = records.next() {
+        let Some(path) = records.next() else { break };
+        let path = path.to_string();
+
+        // Renames and copies carry a similarity score and a second path
+        match status.as_bytes()[0] {
+            b'A' => added.push(path),
+            b'D' => deleted.push(path),
+            b'R' => renamed.extend(records.next().map(|to| (path, to.to_string()))),
+            b'C' => added.extend(records.next().map(String::from)),
+            _ => modified.push(path),
+        }
+    }
+
+    FsReadResult::ChangedFiles {
+        added,
+        modified,
+        deleted,
+        renamed,
+    }
+}
+
+fn parse_porcelain(output: &str) -> HashMap<PathBuf, GitStatus> {
+    let mut statuses = HashMap::new();
+    let mut records = output.split('\0');
+
+    while let Some(record) = records.next() {
+        if record.len() < 4 {
+            continue;
+        }
+        let (code, path) = record.split_at(3);
+        let bytes = code.as_bytes();
+        let (index, worktree) = (bytes[0], bytes[1]);
+
+        let status = if index == b'?' {
+            GitStatus::Untracked
+        } else if worktree != b' ' {
+            GitStatus::Modified
+        } else {
+            GitStatus::Staged
+        };
+        statuses.insert(PathBuf::from(path), status);
+
+        // Renames and copies are followed by the original path
+        if index == b'R' || index == b'C' {
+            records.next();
+        }
+    }
+
+    statuses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_porcelain() {
+        let output = " M src/lib.rs\0A  new.rs\0?? notes.txt\0R  renamed.rs\0old.rs\0MM both.rs\0";
+        let statuses = parse_porcelain(output);
+
+        assert_eq!(statuses[Path::new("src/lib.rs")], GitStatus::Modified);
+        assert_eq!(statuses[Path::new("new.rs")], GitStatus::Staged);
+        assert_eq!(statuses[Path::new("notes.txt")], GitStatus::Untracked);
+        assert_eq!(statuses[Path::new("renamed.rs")], GitStatus::Staged);
+        assert_eq!(statuses[Path::new("both.rs")], GitStatus::Modified);
+        assert!(!statuses.contains_key(Path::new("old.rs")));
+    }
+
+    #[test]
+    fn test_repo_root() {
+        let temp = crate::test_util::TempDir::new().unwrap();
+        let repo = temp.path().join("repo");
+        let worktree = temp.path().join("wt");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("src/deep")).unwrap();
+        std::fs::create_dir_all(worktree.join("src")).unwrap();
+        std::fs::write(repo.join("src/deep/lib.rs"), "").unwrap();
+        std::fs::write(worktree.join(".git"), "gitdir: /x/repo/.git/worktrees/wt\n").unwrap();
+
+        let root_of = |path: &Path| match repo_root(path).unwrap() {
+            FsReadResult::RepoRoot { root, is_worktree } => (root, is_worktree),
+            _ => panic!("Expected RepoRoot result"),
+        };
+
+        let repo_str = repo.canonicalize().unwrap().to_string_lossy().to_string();
+        assert_eq!(root_of(&repo.join("src/deep/lib.rs")), (Some(repo_str), false));
+
+        let wt_str = worktree.canonicalize().unwrap().to_string_lossy().to_string();
+        assert_eq!(root_of(&worktree.join("src")), (Some(wt_str), true));
+    }
+
+    #[test]
+    fn test_parse_name_status() {
+        let output = "M\0src/lib.rs\0A\0new.rs\0D\0gone.rs\0R087\0old.rs\0moved.rs\0C100\0a.rs\0b.rs\0T\0link\0";
+
+        if let FsReadResult::ChangedFiles {
+            added,
+            modified,
+            deleted,
+            renamed,
+        } = parse_name_status(output)
+        {
+            assert_eq!(added, vec!["new.rs", "b.rs"]);
+            assert_eq!(modified, vec!["src/lib.rs", "link"]);
+            assert_eq!(deleted, vec!["gone.rs"]);
+            assert_eq!(renamed, vec![("old.rs".to_string(), "moved.rs".to_string())]);
+        } else {
+            panic!("Expected ChangedFiles result");
+        }
+    }
+
+    #[test]
+    fn test_show_at_revision() {
+        let temp = crate::test_util::TempDir::new().unwrap();
+        let dir = temp.path();
+        let path = dir.join("notes.txt");
+        std::fs::write(&path, "one\ntwo\nthree\n").unwrap();
+
+        let git = |args: &[&str]| {
+            let mut all = vec!["-c", "user.name=t", "-c", "user.email=t@t"];
+            all.extend(args);
+            run_git_checked(dir, &all)
+        };
+        // Needs a git binary; nothing to check without one
+        if git(&["init", "-q"]).is_err() {
+            return;
+        }
+        git(&["add", "notes.txt"]).unwrap();
+        git(&["commit", "-q", "-m", "first"]).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        if let FsReadResult::GitShow {
+            content,
+            total_lines,
+            lines_returned,
+            commit,
+            ..
+        } = show_at_revision(&path, "HEAD", Some(2), None).unwrap()
+        {
+            assert_eq!(content, "two\nthree");
+            assert_eq!((total_lines, lines_returned), (3, 2));
+            assert_eq!(commit.len(), 40);
+        } else {
+            panic!("Expected GitShow result");
+        }
+
+        let missing = show_at_revision(&dir.join("other.txt"), "HEAD", None, None);
+        assert!(matches!(missing, Err(FsReadError::Git(e)) if e.contains("did not exist at HEAD")));
+        assert!(show_at_revision(&path, "no-such-ref", None, None).is_err());
+    }
+
+    #[test]
+    fn test_changed_between() {
+        let temp = crate::test_util::TempDir::new().unwrap();
+        let dir = temp.path();
+        std::fs::write(dir.join("notes.txt"), "one\n").unwrap();
+
+        let git = |args: &[&str]| {
+            let mut all = vec!["-c", "user.name=t", "-c", "user.email=t@t"];
+            all.extend(args);
+            run_git_checked(dir, &all)
+        };
+        // Needs a git binary; nothing to check without one
+        if git(&["init", "-q"]).is_err() {
+            return;
+        }
+        git(&["add", "notes.txt"]).unwrap();
+        git(&["commit", "-q", "-m", "first"]).unwrap();
+        std::fs::write(dir.join("notes.txt"), "two\n").unwrap();
+
+        if let FsReadResult::ChangedFiles { modified, .. } =
+            changed_between(dir, "HEAD", None).unwrap()
+        {
+            assert_eq!(modified, vec!["notes.txt"]);
+        } else {
+            panic!("Expected ChangedFiles result");
+        }
+
+        let output = dir.join("injected.txt");
+        let option = format!("--output={}", output.display());
+        assert!(changed_between(dir, &option, None).is_err());
+        assert!(changed_between(dir, "HEAD", Some(&option)).is_err());
+        assert!(!output.exists());
+    }
+}
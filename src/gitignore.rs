@@ -0,0 +1,213 @@
+use crate::glob::{glob_to_regex, to_slash_path};
+use crate::types::{kind_check, FsReadError, PathKind};
+use regex::Regex;
+use std::path::Path;
+
+#[derive(Debug, Clone)]
+struct Rule {
+    regex: Regex,
+    negated: bool,
+    dir_only: bool,
+    /// Walk root relative to the directory the rule came from, for rules
+    /// read from a parent directory's `.gitignore`
+    prefix: Option<String>,
+}
+
+/// Rules parsed from a file in gitignore syntax. Patterns are matched
+/// against `/`-separated paths relative to the walk root.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Gitignore {
+    rules: Vec<Rule>,
+}
+
+impl Gitignore {
+    pub fn from_file(path: &Path) -> Result<Self, FsReadError> {
+        kind_check(path, Some(PathKind::File))?;
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(content: &str) -> Result<Self, FsReadError> {
+        let rules = content
+            .lines()
+            .filter_map(parse_rule)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// One pattern per entry, in the same syntax as a file's lines.
+    pub fn from_patterns(patterns: &[String]) -> Result<Self, FsReadError> {
+        let rules = patterns
+            .iter()
+            .filter_map(|p| parse_rule(p))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// The `.gitignore` files of `root` and its ancestors up to the enclosing
+    /// repository's top level, or up to `boundary` when given (which must
+    /// contain `root`). Outside a repository and without a boundary only
+    /// `root`'s own file is read. Deeper files take precedence, as in git.
+    pub fn from_parents(root: &Path, boundary: Option<&Path>) -> Result<Self, FsReadError> {
+        let root = root.canonicalize()?;
+        let boundary = boundary.map(Path::canonicalize).transpose()?;
+        if let Some(boundary) = &boundary {
+            if !root.starts_with(boundary) {
+                return Err(FsReadError::InvalidPath(format!(
+                    "{} is not inside the gitignore boundary {}",
+                    root.display(),
+                    boundary.display()
+                )));
+            }
+        }
+
+        let mut dirs = Vec::new();
+        for dir in root.ancestors() {
+            dirs.push(dir);
+            let is_top = match &boundary {
+                Some(boundary) => dir == boundary,
+                None => dir.join(".git").exists(),
+            };
+            if is_top {
+                break;
+            }
+        }
+        // Not in a repository: nothing above the root applies
+        if boundary.is_none() && !dirs.last().is_some_and(|d| d.join(".git").exists()) {
+            dirs.truncate(1);
+        }
+
+        let mut combined = Self::default();
+        for dir in dirs.into_iter().rev() {
+            let file = dir.join(".gitignore");
+            if !file.is_file() {
+                continue;
+            }
+            let mut rules = Self::from_file(&file)?;
+            let prefix = to_slash_path(root.strip_prefix(dir).unwrap_or(Path::new("")));
+            if !prefix.is_empty() {
+                for rule in &mut rules.rules {
+                    rule.prefix = Some(prefix.clone());
+                }
+            }
+            combined.extend(rules);
+        }
+
+        Ok(combined)
+    }
+
+    /// Appends `other`'s rules, which then take precedence over these.
+    pub fn extend(&mut self, other: Gitignore) {
+        self.rules.extend(other.rules);
+    }
+
+    /// Whether `relative` is ignored; the last matching rule wins, so a later
+    /// `!pattern` re-includes what an earlier rule excluded. As in git, a
+    /// file can't be re-included once its parent directory is excluded,
+    /// since the walk never descends into it.
+    pub fn is_ignored(&self, relative: &Path, is_dir: bool) -> bool {
+        let path = to_slash_path(relative);
+        let matches = |rule: &Rule| match &rule.prefix {
+            Some(prefix) => rule.regex.is_match(&format!("{}/{}", prefix, path)),
+            None => rule.regex.is_match(&path),
+        };
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| (is_dir || !rule.dir_only) && matches(rule))
+            .is_some_and(|rule| !rule.negated)
+    }
+}
+
+fn parse_rule(line: &str) -> Option<Result<Rule, FsReadError>> {
+    // Trailing spaces are dropped unless escaped
+    let mut pattern = line.trim_end_matches(['\r', '\n']);
+    while pattern.ends_with(' ') && !pattern.ends_with("\\ ") {
+        pattern = &pattern[..pattern.len() - 1];
+    }
+    if pattern.is_empty() || pattern.starts_with('#') {
+        return None;
+    }
+
+    let (negated, pattern) = match pattern.strip_prefix('!') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    let (dir_only, pattern) = match pattern.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+    if pattern.is_empty() {
+        return None;
+    }
+
+    // A slash anywhere but the end ties the pattern to the root; otherwise it
+    // matches a name at any depth
+    let anchored = pattern.contains('/');
+    let pattern = pattern.strip_prefix('/').unwrap_or(pattern);
+    let prefix = if anchored { "^" } else { "^(?:.*/)?" };
+    let source = format!("{}{}$", prefix, glob_to_regex(pattern));
+
+    Some(
+        Regex::new(&source)
+            .map(|regex| Rule {
+                regex,
+                negated,
+                dir_only,
+                prefix: None,
+            })
+            .map_err(|e| FsReadError::InvalidPattern(e.to_string())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gitignore_rules() {
+        let rules = Gitignore::parse(
+            "# comment\n*.log\n!keep.log\n/build\ndocs/generated/\ncache/  \n\\#notes\n",
+        )
+        .unwrap();
+        let ignored = |path: &str, is_dir: bool| rules.is_ignored(Path::new(path), is_dir);
+
+        assert!(ignored("debug.log", false));
+        assert!(ignored("a/b/debug.log", false));
+        assert!(!ignored("keep.log", false));
+        assert!(ignored("build", true));
+        assert!(!ignored("src/build", true));
+        assert!(ignored("docs/generated", true));
+        assert!(!ignored("docs/generated", false));
+        assert!(ignored("x/cache", true));
+        assert!(ignored("#notes", false));
+        assert!(!ignored("src/main.rs", false));
+    }
+
+    #[test]
+    fn test_gitignore_from_parents() {
+        let temp = crate::test_util::TempDir::new().unwrap();
+        let repo = temp.path().join("repo");
+        let sub = repo.join("pkg/sub");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(&sub).unwrap();
+        std::fs::write(temp.path().join(".gitignore"), "*.rs\n").unwrap();
+        std::fs::write(repo.join(".gitignore"), "*.log\n/pkg/sub/gen/\n").unwrap();
+        std::fs::write(repo.join("pkg/.gitignore"), "!keep.log\n").unwrap();
+
+        let rules = Gitignore::from_parents(&sub, None).unwrap();
+        let ignored = |path: &str, is_dir: bool| rules.is_ignored(Path::new(path), is_dir);
+
+        assert!(ignored("debug.log", false));
+        assert!(ignored("deep/debug.log", false));
+        assert!(!ignored("keep.log", false));
+        assert!(ignored("gen", true));
+        assert!(!ignored("other/gen", true));
+        // Above the repository root
+        assert!(!ignored("main.rs", false));
+
+        let bounded = Gitignore::from_parents(&sub, Some(&repo.join("pkg"))).unwrap();
+        assert!(!bounded.is_ignored(Path::new("debug.log"), false));
+        assert!(Gitignore::from_parents(&repo, Some(&sub)).is_err());
+    }
+}
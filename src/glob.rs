@@ -0,0 +1,122 @@
+use regex::Regex;
+use std::path::{Path, MAIN_SEPARATOR};
+
+/// Compiles `glob` to match whole `/`-separated relative paths.
+pub(crate) fn compile_glob(glob: &str) -> Result<Regex, regex::Error> {
+    Regex::new(&format!("^{}$", glob_to_regex(glob)))
+}
+
+/// Joins the components of a relative path with `/` whatever the platform.
+pub(crate) fn to_slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Rewrites the platform separator in a reported path to `/`. This only
+/// changes how the path is displayed; nothing on disk is touched, and on
+/// platforms whose separator is already `/` the path is left as is (a `\`
+/// there is part of a file name).
+pub(crate) fn normalize_separators(path: &mut String) {
+    replace_separator(path, MAIN_SEPARATOR);
+}
+
+fn replace_separator(path: &mut String, separator: char) {
+    if separator != '/' && path.contains(separator) {
+        *path = path.replace(separator, "/");
+    }
+}
+
+/// Translates a gitignore-style glob into an unanchored regex fragment:
+/// `*` and `?` stay within a path segment, `**` spans segments, and `[...]`
+/// classes (with `!` negation) pass through.
+pub(crate) fn glob_to_regex(glob: &str) -> String {
+    let chars: Vec<char> = glob.chars().collect();
+    let mut regex = String::with_capacity(glob.len() * 2);
+    let mut i = 0;
+
+    while i < chars.len() {
+        match chars[i] {
+            '*' if chars.get(i + 1) == Some(&'*') => {
+                let at_segment_start = i == 0 || chars[i - 1] == '/';
+                if at_segment_start && chars.get(i + 2) == Some(&'/') {
+                    // `**/` also matches zero directories
+                    regex.push_str("(?:.*/)?");
+                    i += 3;
+                } else {
+                    regex.push_str(".*");
+                    i += 2;
+                }
+            }
+            '*' => {
+                regex.push_str("[^/]*");
+                i += 1;
+            }
+            '?' => {
+                regex.push_str("[^/]");
+                i += 1;
+            }
+            '[' => match chars[i + 1..].iter().position(|&c| c == ']') {
+                // `[]` and `[!]` aren't classes on their own; the `]` is a member
+                Some(offset) if offset > 0 => {
+                    let class: String = chars[i + 1..i + 1 + offset].iter().collect();
+                    let class = match class.strip_prefix('!') {
+                        Some(rest) => format!("^{}", rest),
+                        None => class,
+                    };
+                    regex.push('[');
+                    regex.push_str(&class.replace('\\', "\\\\").replace('[', "\\["));
+                    regex.push(']');
+                    i += offset + 2;
+                }
+                _ => {
+                    regex.push_str("\\[");
+                    i += 1;
+                }
+            },
+            '\\' if i + 1 < chars.len() => {
+                regex.push_str(&regex::escape(&chars[i + 1].to_string()));
+                i += 2;
+            }
+            c => {
+                regex.push_str(&regex::escape(&c.to_string()));
+                i += 1;
+            }
+        }
+    }
+
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replace_separator() {
+        let mut windows = r"src\util\mod.rs".to_string();
+        replace_separator(&mut windows, '\\');
+        assert_eq!(windows, "src/util/mod.rs");
+
+        let mut unix = r"odd\name.txt".to_string();
+        replace_separator(&mut unix, '/');
+        assert_eq!(unix, r"odd\name.txt");
+    }
+
+    #[test]
+    fn test_glob_to_regex() {
+        let matches = |glob: &str, path: &str| compile_glob(glob).unwrap().is_match(path);
+
+        assert!(matches("*.rs", "lib.rs"));
+        assert!(!matches("*.rs", "src/lib.rs"));
+        assert!(matches("**/*.rs", "lib.rs"));
+        assert!(matches("**/*.rs", "src/a/lib.rs"));
+        assert!(matches("src/**", "src/a/b"));
+        assert!(matches("a/**/b", "a/b"));
+        assert!(matches("a/**/b", "a/x/y/b"));
+        assert!(matches("file?.[ch]", "file1.h"));
+        assert!(!matches("file[!0-9].c", "file1.c"));
+        assert!(matches("\\*.txt", "*.txt"));
+    }
+}
@@ -0,0 +1,73 @@
+use crate::types::{FsReadError, FsReadResult};
+use std::fmt::Write;
+
+/// Serializes Search and SearchGrouped results as `file:line:col:content`
+/// lines, the grep/quickfix format editors jump from. Matches without a
+/// column start at column 1. Other result types are rejected.
+pub fn to_grep(result: &FsReadResult) -> Result<String, FsReadError> {
+    let mut out = String::new();
+
+    match result {
+        FsReadResult::Search { matches, .. } => {
+            for m in matches {
+                write_line(&mut out, &m.file_path, m.line_number, m.column, &m.line_content);
+            }
+        }
+        FsReadResult::SearchGrouped { files, .. } => {
+            for file in files {
+                for m in &file.matches {
+                    write_line(&mut out, &file.file_path, m.line_number, m.column, &m.line_content);
+                }
+            }
+        }
+        _ => {
+            return Err(FsReadError::UnsupportedFormat(
+                "grep format is only available for Search results".to_string(),
+            ))
+        }
+    }
+
+    Ok(out)
+}
+
+fn write_line(out: &mut String, path: &str, line: usize, column: Option<usize>, content: &str) {
+    let _ = writeln!(out, "{}:{}:{}:{}", path, line, column.unwrap_or(1), content);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::{search_pattern, SearchOptions};
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_grep_format() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("main.rs");
+        fs::write(&file, "fn main() {\n    let x = todo!();\n}\n").unwrap();
+
+        let result = search_pattern(&file, "todo", &SearchOptions::default()).unwrap();
+        assert_eq!(
+            to_grep(&result).unwrap(),
+            format!("{}:2:13:    let x = todo!();\n", file.display())
+        );
+
+        let options = SearchOptions {
+            group_by_file: true,
+            ..Default::default()
+        };
+        let grouped = search_pattern(&file, r"\{", &options).unwrap();
+        assert_eq!(
+            to_grep(&grouped).unwrap(),
+            format!("{}:1:11:fn main() {{\n", file.display())
+        );
+
+        let ratio = FsReadResult::MatchRatio {
+            per_file: Vec::new(),
+            truncated: false,
+            omitted_count: 0,
+        };
+        assert!(to_grep(&ratio).is_err());
+    }
+}
@@ -0,0 +1,246 @@
+use crate::types::{kind_check, FsReadError, FsReadResult, HashAlgorithm, PathKind};
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+const SHA256_INIT: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+const SHA256_K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+/// Incremental content hash, rendered as lowercase hex.
+pub enum ContentHasher {
+    Sha256(Sha256),
+    Fnv1a(u64),
+}
+
+impl ContentHasher {
+    pub fn new(algorithm: HashAlgorithm) -> Self {
+        match algorithm {
+            HashAlgorithm::Sha256 => Self::Sha256(Sha256::new()),
+            HashAlgorithm::Fnv1a => Self::Fnv1a(FNV_OFFSET),
+        }
+    }
+
+    pub fn update(&mut self, bytes: &[u8]) {
+        match self {
+            Self::Sha256(sha) => sha.update(bytes),
+            Self::Fnv1a(state) => {
+                for &b in bytes {
+                    *state = (*state ^ b as u64).wrapping_mul(FNV_PRIME);
+                }
+            }
+        }
+    }
+
+    pub fn finish(self) -> String {
+        match self {
+            Self::Sha256(sha) => to_hex(&sha.finish()),
+            Self::Fnv1a(state) => format!("{:016x}", state),
+        }
+    }
+}
+
+/// Hex digest of `bytes` in one call.
+pub fn hash_bytes(algorithm: HashAlgorithm, bytes: &[u8]) -> String {
+    let mut hasher = ContentHasher::new(algorithm);
+    hasher.update(bytes);
+    hasher.finish()
+}
+
+/// Hex digest of a file's content, read in buffer-sized chunks.
+pub fn hash_file(algorithm: HashAlgorithm, path: &Path) -> io::Result<String> {
+    let mut hasher = ContentHasher::new(algorithm);
+    let mut reader = BufReader::new(File::open(path)?);
+    loop {
+        let buf = reader.fill_buf()?;
+        if buf.is_empty() {
+            break;
+        }
+        hasher.update(buf);
+        let len = buf.len();
+        reader.consume(len);
+    }
+    Ok(hasher.finish())
+}
+
+/// Whether the file's digest is `expected`, compared case-insensitively
+/// and ignoring surrounding whitespace, along with the actual digest.
+pub fn verify_hash(
+    path: &Path,
+    algorithm: HashAlgorithm,
+    expected: &str,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let actual = hash_file(algorithm, path)?;
+    Ok(FsReadResult::VerifyHash {
+        matches: actual.eq_ignore_ascii_case(expected.trim()),
+        actual,
+    })
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// SHA-256 (FIPS 180-4).
+pub struct Sha256 {
+    state: [u32; 8],
+    block: [u8; 64],
+    block_len: usize,
+    total_len: u64,
+}
+
+impl Sha256 {
+    fn new() -> Self {
+        Self {
+            state: SHA256_INIT,
+            block: [0; 64],
+            block_len: 0,
+            total_len: 0,
+        }
+    }
+
+    fn update(&mut self, mut bytes: &[u8]) {
+        self.total_len += bytes.len() as u64;
+
+        while !bytes.is_empty() {
+            let take = (64 - self.block_len).min(bytes.len());
+            self.block[self.block_len..self.block_len + take].copy_from_slice(&bytes[..take]);
+            self.block_len += take;
+            bytes = &bytes[take..];
+
+            if self.block_len == 64 {
+                compress(&mut self.state, &self.block);
+                self.block_len = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> [u8; 32] {
+        let bit_len = self.total_len.wrapping_mul(8);
+
+        // A 1 bit, zeros up to 56 bytes into a block, then the length
+        let mut padding = vec![0x80];
+        let used = (self.block_len + 1) % 64;
+        let zeros = if used <= 56 { 56 - used } else { 120 - used };
+        padding.extend(std::iter::repeat_n(0, zeros));
+        padding.extend(bit_len.to_be_bytes());
+        self.update(&padding);
+
+        let mut digest = [0u8; 32];
+        for (chunk, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            chunk.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+}
+
+fn compress(state: &mut [u32; 8], block: &[u8; 64]) {
+    let mut w = [0u32; 64];
+    for (i, chunk) in block.chunks_exact(4).enumerate() {
+        w[i] = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+    }
+    for i in 16..64 {
+        let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+        let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+        w[i] = w[i - 16]
+            .wrapping_add(s0)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(s1);
+    }
+
+    let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h] = *state;
+    for i in 0..64 {
+        let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+        let ch = (e & f) ^ (!e & g);
+        let t1 = h
+            .wrapping_add(s1)
+            .wrapping_add(ch)
+            .wrapping_add(SHA256_K[i])
+            .wrapping_add(w[i]);
+        let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t2 = s0.wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+    }
+
+    for (s, v) in state.iter_mut().zip([a, b, c, d, e, f, g, h]) {
+        *s = s.wrapping_add(v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha256() {
+        assert_eq!(
+            hash_bytes(HashAlgorithm::Sha256, b""),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+        assert_eq!(
+            hash_bytes(HashAlgorithm::Sha256, b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        // Split updates, with the length spilling into a second padding block
+        let long = "a".repeat(119);
+        let mut hasher = ContentHasher::new(HashAlgorithm::Sha256);
+        hasher.update(&long.as_bytes()[..50]);
+        hasher.update(&long.as_bytes()[50..]);
+        assert_eq!(
+            hasher.finish(),
+            "31eba51c313a5c08226adf18d4a359cfdfd8d2e816b13f4af952f7ea6584dcfb"
+        );
+    }
+
+    #[test]
+    fn test_fnv1a() {
+        assert_eq!(hash_bytes(HashAlgorithm::Fnv1a, b""), "cbf29ce484222325");
+        assert_eq!(hash_bytes(HashAlgorithm::Fnv1a, b"a"), "af63dc4c8601ec8c");
+    }
+
+    #[test]
+    fn test_verify_hash() {
+        let temp = crate::test_util::TempDir::new().unwrap();
+        let path = temp.path().join("a.txt");
+        std::fs::write(&path, "abc").unwrap();
+        let digest = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+        let verify = |expected: &str| match verify_hash(&path, HashAlgorithm::Sha256, expected) {
+            Ok(FsReadResult::VerifyHash { matches, actual }) => {
+                assert_eq!(actual, digest);
+                matches
+            }
+            _ => panic!("Expected VerifyHash result"),
+        };
+        assert!(verify(digest));
+        assert!(verify(&digest.to_uppercase()));
+        assert!(!verify(&digest[1..]));
+        assert!(verify_hash(temp.path(), HashAlgorithm::Sha256, digest).is_err());
+    }
+}
@@ -0,0 +1,230 @@
+use crate::language::{detect_language, Language};
+use crate::line::with_file_content;
+use crate::types::{kind_check, FsReadError, FsReadResult, ImportStatement, PathKind};
+use regex::Regex;
+use std::path::Path;
+
+// Multi-line statements are joined until their terminator, up to this many
+// lines, so an unterminated statement can't swallow the rest of the file.
+const MAX_STATEMENT_LINES: usize = 50;
+
+/// Extracts import statements with per-language regexes. Languages without
+/// rules yield an empty list.
+pub fn extract_imports(path: &Path) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let imports = match detect_language(path) {
+        Some(Language::Python) => with_file_content(path, |c| Ok(python_imports(c)))?,
+        Some(Language::JavaScript | Language::TypeScript) => {
+            with_file_content(path, |c| Ok(js_imports(c)))?
+        }
+        Some(Language::Rust) => with_file_content(path, |c| Ok(rust_imports(c)))?,
+        _ => Vec::new(),
+    };
+
+    Ok(FsReadResult::Imports {
+        imports,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// Joins lines from `start` until `is_complete` holds, returning the
+/// statement text and the index of its last line.
+fn collect_statement(
+    lines: &[&str],
+    start: usize,
+    is_complete: impl Fn(&str) -> bool,
+) -> (String, usize) {
+    let mut raw = lines[start].to_string();
+    let mut end = start;
+
+    while !is_complete(&raw) && end + 1 < lines.len() && end - start < MAX_STATEMENT_LINES {
+        end += 1;
+        raw.push('\n');
+        raw.push_str(lines[end]);
+    }
+
+    (raw, end)
+}
+
+fn python_imports(content: &str) -> Vec<ImportStatement> {
+    let import_re = Regex::new(r"^\s*import\s+(.+)").unwrap();
+    let from_re = Regex::new(r"^\s*from\s+(\.*[\w.]*)\s+import\b").unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    let mut imports = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+
+        if let Some(caps) = from_re.captures(line) {
+            let module = caps[1].to_string();
+            let (raw, end) = if line.contains('(') {
+                collect_statement(&lines, i, |s| s.contains(')'))
+            } else {
+                collect_statement(&lines, i, |s| !s.trim_end().ends_with('\\'))
+            };
+            imports.push(ImportStatement {
+                raw,
+                module,
+                line_number: i + 1,
+            });
+            i = end;
+        } else if let Some(caps) = import_re.captures(line) {
+            // `import a, b as c` names several modules
+            let names = caps[1].split('#').next().unwrap_or_default();
+            for name in names.split(',') {
+                if let Some(module) = name.split_whitespace().next() {
+                    imports.push(ImportStatement {
+                        raw: line.trim().to_string(),
+                        module: module.to_string(),
+                        line_number: i + 1,
+                    });
+                }
+            }
+        }
+
+        i += 1;
+    }
+
+    imports
+}
+
+fn js_imports(content: &str) -> Vec<ImportStatement> {
+    let import_start_re = Regex::new(r"^\s*(?:import|export)\b").unwrap();
+    let from_re =
+        Regex::new(r#"^\s*(?:import|export)\b(?:[^'"]*?\bfrom\s*|\s*)['"]([^'"]+)['"]"#).unwrap();
+    let call_re = Regex::new(r#"\b(?:require|import)\(\s*['"]([^'"]+)['"]\s*\)"#).unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    let mut imports = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+
+        // Static `import ... from 'x'` / `export ... from 'x'`, possibly spanning
+        // several lines
+        if import_start_re.is_match(line) && !call_re.is_match(line) {
+            let is_braced = line.contains('{') && !line.contains('}');
+            let (raw, end) = if is_braced {
+                collect_statement(&lines, i, |s| from_re.is_match(s))
+            } else {
+                (line.to_string(), i)
+            };
+            if let Some(caps) = from_re.captures(&raw) {
+                imports.push(ImportStatement {
+                    module: caps[1].to_string(),
+                    raw: raw.trim().to_string(),
+                    line_number: i + 1,
+                });
+                i = end + 1;
+                continue;
+            }
+        }
+
+        for caps in call_re.captures_iter(line) {
+            imports.push(ImportStatement {
+                raw: line.trim().to_string(),
+                module: caps[1].to_string(),
+                line_number: i + 1,
+            });
+        }
+
+        i += 1;
+    }
+
+    imports
+}
+
+fn rust_imports(content: &str) -> Vec<ImportStatement> {
+    let use_re = Regex::new(r"^\s*(?:pub(?:\([^)]*\))?\s+)?use\s+").unwrap();
+    let path_re = Regex::new(r"use\s+((?:::)?[\w:]+?)(?:::\{|::\*|\s+as\s|\s*;)").unwrap();
+    let extern_re = Regex::new(r"^\s*extern\s+crate\s+(\w+)").unwrap();
+    let lines: Vec<&str> = content.lines().collect();
+    let mut imports = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+
+        if use_re.is_match(line) {
+            let (raw, end) = collect_statement(&lines, i, |s| s.contains(';'));
+            if let Some(caps) = path_re.captures(&raw) {
+                imports.push(ImportStatement {
+                    module: caps[1].to_string(),
+                    raw: raw.trim().to_string(),
+                    line_number: i + 1,
+                });
+            }
+            i = end;
+        } else if let Some(caps) = extern_re.captures(line) {
+            imports.push(ImportStatement {
+                raw: line.trim().to_string(),
+                module: caps[1].to_string(),
+                line_number: i + 1,
+            });
+        }
+
+        i += 1;
+    }
+
+    imports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn modules(imports: &[ImportStatement]) -> Vec<(&str, usize)> {
+        imports.iter().map(|i| (i.module.as_str(), i.line_number)).collect()
+    }
+
+    #[test]
+    fn test_python_imports() {
+        let source = "import os, sys as system\nfrom . import utils\nfrom ..pkg.mod import (\n    a,\n    b,\n)\nx = 'import nothing'\n";
+        let imports = python_imports(source);
+
+        assert_eq!(
+            modules(&imports),
+            vec![("os", 1), ("sys", 1), (".", 2), ("..pkg.mod", 3)]
+        );
+        assert!(imports[3].raw.ends_with(')'));
+    }
+
+    #[test]
+    fn test_js_imports() {
+        let source = "import React from 'react';\nimport {\n  a,\n  b,\n} from \"./local\";\nimport './styles.css';\nconst fs = require('fs');\nexport { x } from './x';\nconst lazy = import('./lazy');\n";
+        let imports = js_imports(source);
+
+        assert_eq!(
+            modules(&imports),
+            vec![
+                ("react", 1),
+                ("./local", 2),
+                ("./styles.css", 6),
+                ("fs", 7),
+                ("./x", 8),
+                ("./lazy", 9),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rust_imports() {
+        let source = "use std::io::{self, Read};\npub(crate) use crate::types::FsReadError;\nuse regex::Regex as Re;\nuse std::collections::{\n    HashMap,\n};\nextern crate alloc;\nuse super::*;\n";
+        let imports = rust_imports(source);
+
+        assert_eq!(
+            modules(&imports),
+            vec![
+                ("std::io", 1),
+                ("crate::types::FsReadError", 2),
+                ("regex::Regex", 3),
+                ("std::collections", 4),
+                ("alloc", 7),
+                ("super", 8),
+            ]
+        );
+    }
+}
@@ -0,0 +1,274 @@
+use std::io::{Error, ErrorKind};
+
+// Base values and extra bits for length codes 257..285 and distance codes
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// Order in which dynamic blocks list the code length code lengths
+const CODE_LENGTH_ORDER: [usize; 19] =
+    [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+/// Decompresses a raw DEFLATE stream (RFC 1951). Only as much as the archive
+/// search needs: no streaming, the whole output is held in memory. Output
+/// past `max_output` bytes fails with `ErrorKind::FileTooLarge`, so a small
+/// stream can't expand without bound.
+pub(crate) fn inflate(data: &[u8], max_output: usize) -> std::io::Result<Vec<u8>> {
+    let mut bits = BitReader { data, pos: 0, buf: 0, count: 0 };
+    let mut out = Output {
+        bytes: Vec::with_capacity(data.len().saturating_mul(3).min(max_output)),
+        max: max_output,
+    };
+
+    loop {
+        let last = bits.read(1)? == 1;
+        match bits.read(2)? {
+            0 => stored_block(&mut bits, &mut out)?,
+            1 => {
+                let (lengths, distances) = fixed_tables();
+                huffman_block(&mut bits, &mut out, &lengths, &distances)?;
+            }
+            2 => {
+                let (lengths, distances) = dynamic_tables(&mut bits)?;
+                huffman_block(&mut bits, &mut out, &lengths, &distances)?;
+            }
+            _ => return Err(corrupt("invalid block type")),
+        }
+        if last {
+            return Ok(out.bytes);
+        }
+    }
+}
+
+fn corrupt(message: &str) -> Error {
+    Error::new(ErrorKind::InvalidData, format!("corrupt deflate stream: {}", message))
+}
+
+/// Decompressed bytes so far, refusing to grow past `max`.
+struct Output {
+    bytes: Vec<u8>,
+    max: usize,
+}
+
+impl Output {
+    fn check_room(&self, additional: usize) -> std::io::Result<()> {
+        if self.bytes.len() + additional > self.max {
+            return Err(Error::new(
+                ErrorKind::FileTooLarge,
+                format!("inflated data exceeds {} bytes", self.max),
+            ));
+        }
+        Ok(())
+    }
+}
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buf: u32,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    /// Next `n` (at most 16) bits, least significant first.
+    fn read(&mut self, n: u32) -> std::io::Result<u32> {
+        while self.count < n {
+            let byte = *self.data.get(self.pos).ok_or_else(|| corrupt("unexpected end"))?;
+            self.buf |= (byte as u32) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        let value = self.buf & ((1 << n) - 1);
+        self.buf >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.buf = 0;
+        self.count = 0;
+    }
+}
+
+/// Canonical Huffman code as symbol counts per bit length plus the symbols
+/// in code order, decoded a bit at a time.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+
+        Self { counts, symbols }
+    }
+
+    fn decode(&self, bits: &mut BitReader) -> std::io::Result<u16> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= bits.read(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(corrupt("invalid Huffman code"))
+    }
+}
+
+fn stored_block(bits: &mut BitReader, out: &mut Output) -> std::io::Result<()> {
+    bits.align_to_byte();
+    let header = bits
+        .data
+        .get(bits.pos..bits.pos + 4)
+        .ok_or_else(|| corrupt("unexpected end"))?;
+    let len = u16::from_le_bytes([header[0], header[1]]);
+    let nlen = u16::from_le_bytes([header[2], header[3]]);
+    if len != !nlen {
+        return Err(corrupt("stored block length mismatch"));
+    }
+    bits.pos += 4;
+
+    let end = bits.pos + len as usize;
+    let stored = bits.data.get(bits.pos..end).ok_or_else(|| corrupt("unexpected end"))?;
+    out.check_room(stored.len())?;
+    out.bytes.extend_from_slice(stored);
+    bits.pos = end;
+    Ok(())
+}
+
+fn fixed_tables() -> (Huffman, Huffman) {
+    let mut lengths = [0u8; 288];
+    lengths[..144].fill(8);
+    lengths[144..256].fill(9);
+    lengths[256..280].fill(7);
+    lengths[280..].fill(8);
+    (Huffman::new(&lengths), Huffman::new(&[5; 30]))
+}
+
+fn dynamic_tables(bits: &mut BitReader) -> std::io::Result<(Huffman, Huffman)> {
+    let literal_count = bits.read(5)? as usize + 257;
+    let distance_count = bits.read(5)? as usize + 1;
+    let code_length_count = bits.read(4)? as usize + 4;
+
+    let mut code_lengths = [0u8; 19];
+    for &i in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[i] = bits.read(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (value, repeat) = match code_length_code.decode(bits)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths.last().ok_or_else(|| corrupt("repeat with no length"))?;
+                (previous, 3 + bits.read(2)?)
+            }
+            17 => (0, 3 + bits.read(3)?),
+            _ => (0, 11 + bits.read(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err(corrupt("too many code lengths"));
+    }
+
+    let (literal_lengths, distance_lengths) = lengths.split_at(literal_count);
+    Ok((Huffman::new(literal_lengths), Huffman::new(distance_lengths)))
+}
+
+fn huffman_block(
+    bits: &mut BitReader,
+    out: &mut Output,
+    lengths: &Huffman,
+    distances: &Huffman,
+) -> std::io::Result<()> {
+    loop {
+        let symbol = lengths.decode(bits)? as usize;
+        match symbol {
+            0..=255 => {
+                out.check_room(1)?;
+                out.bytes.push(symbol as u8);
+            }
+            256 => return Ok(()),
+            _ => {
+                let code = symbol - 257;
+                if code >= LENGTH_BASE.len() {
+                    return Err(corrupt("invalid length code"));
+                }
+                let extra = bits.read(LENGTH_EXTRA[code] as u32)? as usize;
+                let len = LENGTH_BASE[code] as usize + extra;
+
+                let code = distances.decode(bits)? as usize;
+                if code >= DIST_BASE.len() {
+                    return Err(corrupt("invalid distance code"));
+                }
+                let extra = bits.read(DIST_EXTRA[code] as u32)? as usize;
+                let dist = DIST_BASE[code] as usize + extra;
+                if dist > out.bytes.len() {
+                    return Err(corrupt("distance too far back"));
+                }
+                out.check_room(len)?;
+
+                // Copies may overlap their own output, so go byte by byte
+                let start = out.bytes.len() - dist;
+                for i in 0..len {
+                    out.bytes.push(out.bytes[start + i]);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inflate() {
+        // zlib.compressobj(9, zlib.DEFLATED, -15) over "hello hello hello hello\n"
+        let fixed = [203, 72, 205, 201, 201, 87, 200, 64, 39, 185, 0];
+        assert_eq!(inflate(&fixed, 1024).unwrap(), b"hello hello hello hello\n");
+        let err = inflate(&fixed, 10).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::FileTooLarge);
+
+        // A single final stored block
+        let stored = [1, 3, 0, 252, 255, b'a', b'b', b'c'];
+        assert_eq!(inflate(&stored, 3).unwrap(), b"abc");
+        assert_eq!(inflate(&stored, 2).unwrap_err().kind(), ErrorKind::FileTooLarge);
+
+        assert!(inflate(&[0xff], 1024).is_err());
+    }
+}
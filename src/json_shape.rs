@@ -0,0 +1,140 @@
+use crate::line::with_file_content;
+use crate::types::{kind_check, FsReadError, FsReadResult, PathKind};
+use serde_json::Value;
+use std::io;
+use std::path::Path;
+
+/// The structure of a JSON file without its values. Objects map each key to
+/// the shape of its value, arrays become `[element_shape, length]` and
+/// scalars the name of their type. Elements of differing shapes are merged:
+/// objects take the union of their keys, and different scalar types are
+/// joined as `"number|string"`. Containers nested deeper than `max_depth`
+/// are summarised as `"object"` or `"array"`.
+pub fn json_shape(path: &Path, max_depth: u32) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let value: Value = with_file_content(path, |content| {
+        Ok(serde_json::from_str(content).map_err(io::Error::from)?)
+    })?;
+
+    Ok(FsReadResult::JsonShape {
+        shape: shape_of(&value, max_depth),
+    })
+}
+
+fn shape_of(value: &Value, depth_left: u32) -> Value {
+    match value {
+        Value::Null => "null".into(),
+        Value::Bool(_) => "boolean".into(),
+        Value::Number(_) => "number".into(),
+        Value::String(_) => "string".into(),
+        Value::Array(_) if depth_left == 0 => "array".into(),
+        Value::Object(_) if depth_left == 0 => "object".into(),
+        Value::Array(items) => {
+            let element = items
+                .iter()
+                .map(|item| shape_of(item, depth_left - 1))
+                .reduce(merge)
+                .unwrap_or_else(|| "unknown".into());
+            Value::Array(vec![element, items.len().into()])
+        }
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, value)| (key.clone(), shape_of(value, depth_left - 1)))
+                .collect(),
+        ),
+    }
+}
+
+/// One shape covering both `a` and `b`.
+fn merge(a: Value, b: Value) -> Value {
+    if a == b {
+        return a;
+    }
+
+    match (a, b) {
+        (Value::Object(mut a), Value::Object(b)) => {
+            for (key, shape) in b {
+                let merged = match a.remove(&key) {
+                    Some(existing) => merge(existing, shape),
+                    None => shape,
+                };
+                a.insert(key, merged);
+            }
+            Value::Object(a)
+        }
+        // Arrays are `[element, length]` pairs; the longer length is kept
+        (Value::Array(a), Value::Array(b)) if a.len() == 2 && b.len() == 2 => {
+            let [a_element, a_len]: [Value; 2] = a.try_into().unwrap();
+            let [b_element, b_len]: [Value; 2] = b.try_into().unwrap();
+            let len = a_len.as_u64().max(b_len.as_u64());
+            Value::Array(vec![merge(a_element, b_element), len.into()])
+        }
+        (a, b) => {
+            let mut names: Vec<String> = type_names(&a).chain(type_names(&b)).collect();
+            names.sort();
+            names.dedup();
+            // An empty array's element shape says nothing about the others
+            if names.len() > 1 {
+                names.retain(|name| name != "unknown");
+            }
+            names.join("|").into()
+        }
+    }
+}
+
+fn type_names(shape: &Value) -> impl Iterator<Item = String> + '_ {
+    let names: Vec<&str> = match shape {
+        Value::String(names) => names.split('|').collect(),
+        Value::Array(_) => vec!["array"],
+        _ => vec!["object"],
+    };
+    names.into_iter().map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_json_shape() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("data.json");
+        let data = json!({
+            "name": "app",
+            "version": 3,
+            "tags": [],
+            "users": [
+                {"id": 1, "email": "a@example.com"},
+                {"id": 2, "email": null, "admin": true}
+            ],
+            "matrix": [[1, 2], [], [3, 4, 5]]
+        });
+        fs::write(&path, data.to_string()).unwrap();
+
+        let shape = |max_depth| match json_shape(&path, max_depth).unwrap() {
+            FsReadResult::JsonShape { shape } => shape,
+            _ => panic!("Expected JsonShape result"),
+        };
+
+        assert_eq!(
+            shape(3),
+            json!({
+                "name": "string",
+                "version": "number",
+                "tags": ["unknown", 0],
+                "users": [{"id": "number", "email": "null|string", "admin": "boolean"}, 2],
+                "matrix": [["number", 3], 3]
+            })
+        );
+        assert_eq!(shape(1)["users"], json!("array"));
+        assert_eq!(shape(0), json!("object"));
+
+        fs::write(&path, "{not json").unwrap();
+        assert!(json_shape(&path, 1).is_err());
+    }
+}
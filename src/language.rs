@@ -0,0 +1,114 @@
+use crate::line::{parse_shebang, read_first_line};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    TypeScript,
+    Go,
+    Java,
+    Kotlin,
+    C,
+    Cpp,
+    CSharp,
+    Ruby,
+    Php,
+    Swift,
+    Scala,
+    Shell,
+    Html,
+    Css,
+    Markdown,
+    Json,
+    Yaml,
+    Toml,
+    Sql,
+}
+
+impl Language {
+    pub fn from_extension(ext: &str) -> Option<Self> {
+        let language = match ext.to_ascii_lowercase().as_str() {
+            "rs" => Self::Rust,
+            "py" | "pyi" | "pyw" => Self::Python,
+            "js" | "mjs" | "cjs" | "jsx" => Self::JavaScript,
+            "ts" | "mts" | "cts" | "tsx" => Self::TypeScript,
+            "go" => Self::Go,
+            "java" => Self::Java,
+            "kt" | "kts" => Self::Kotlin,
+            "c" | "h" => Self::C,
+            "cc" | "cpp" | "cxx" | "hh" | "hpp" | "hxx" => Self::Cpp,
+            "cs" => Self::CSharp,
+            "rb" => Self::Ruby,
+            "php" => Self::Php,
+            "swift" => Self::Swift,
+            "scala" | "sc" => Self::Scala,
+            "sh" | "bash" | "zsh" => Self::Shell,
+            "html" | "htm" => Self::Html,
+            "css" | "scss" | "less" => Self::Css,
+            "md" | "markdown" => Self::Markdown,
+            "json" => Self::Json,
+            "yaml" | "yml" => Self::Yaml,
+            "toml" => Self::Toml,
+            "sql" => Self::Sql,
+            _ => return None,
+        };
+        Some(language)
+    }
+
+    /// Documentation and data formats, which don't make a project a
+    /// "Markdown project" however much of them it has.
+    pub fn is_markup_or_data(self) -> bool {
+        matches!(self, Self::Markdown | Self::Json | Self::Yaml | Self::Toml)
+    }
+
+    pub fn from_interpreter(interpreter: &str) -> Option<Self> {
+        let name = interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+        let language = match name {
+            "python" => Self::Python,
+            "node" | "nodejs" | "deno" | "bun" => Self::JavaScript,
+            "ts-node" => Self::TypeScript,
+            "sh" | "bash" | "zsh" | "dash" | "ksh" => Self::Shell,
+            "ruby" => Self::Ruby,
+            "php" => Self::Php,
+            _ => return None,
+        };
+        Some(language)
+    }
+}
+
+/// Detects by extension, falling back to the shebang for extensionless
+/// scripts.
+pub fn detect_language(path: &Path) -> Option<Language> {
+    match path.extension() {
+        Some(ext) => Language::from_extension(&ext.to_string_lossy()),
+        None => read_first_line(path)
+            .ok()
+            .flatten()
+            .as_deref()
+            .and_then(parse_shebang)
+            .as_deref()
+            .and_then(Language::from_interpreter),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_detect_language() {
+        let temp = TempDir::new().unwrap();
+        let script = temp.path().join("run");
+        fs::write(&script, "#!/usr/bin/env python3\nprint(1)").unwrap();
+
+        assert_eq!(detect_language(Path::new("src/lib.rs")), Some(Language::Rust));
+        assert_eq!(detect_language(Path::new("App.TSX")), Some(Language::TypeScript));
+        assert_eq!(detect_language(&script), Some(Language::Python));
+        assert_eq!(detect_language(Path::new("notes.xyz")), None);
+    }
+}
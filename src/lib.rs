@@ -1,30 +1,292 @@
+// pyo3 0.22 `#[pyfunction]` expansion trips this lint on `PyResult` returns
+#![allow(clippy::useless_conversion)]
+
 mod types;
 mod line;
 mod directory;
+mod dotenv;
 mod search;
+mod multi_file;
+mod binary;
+mod git;
+mod csv_output;
+mod fuzzy;
+mod encoding;
+mod language;
+mod imports;
+mod comments;
+mod scope;
+mod retry;
+mod glob;
+mod gitignore;
+mod accents;
+mod archive;
+mod edit;
+mod generated;
+mod grep_output;
+mod hash;
+mod inflate;
+mod json_shape;
+mod output_limit;
+mod pool;
+mod redact;
+mod tokens;
+#[cfg(test)]
+mod test_util;
 
-pub use types::{FsReadError, FsReadMode, FsReadParams, FsReadResult};
+pub use csv_output::to_csv;
+pub use grep_output::to_grep;
+pub use line::{set_read_buffer_capacity, DEFAULT_READ_BUFFER_CAPACITY};
+pub use types::{FsReadError, FsReadMode, FsReadParams, FsReadResult, FsWarning};
 
 use pyo3::prelude::*;
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 
 pub fn fs_read(params: FsReadParams) -> Result<FsReadResult, FsReadError> {
+    let max_output_bytes = params.max_output_bytes;
+    let redact_prefix = params.redact_paths.clone();
+    let mut result = read_mode(params)?;
+
+    // Before the output limit, which has to hold for the redacted result
+    if let Some(prefix) = redact_prefix {
+        result = redact::redact_paths(result, &prefix)?;
+    }
+
+    Ok(match max_output_bytes {
+        Some(max) => output_limit::limit_output(result, max)?,
+        None => result,
+    })
+}
+
+fn read_mode(params: FsReadParams) -> Result<FsReadResult, FsReadError> {
     match params.mode {
-        FsReadMode::Line { start_line, end_line } => {
-            line::read_lines(&params.path, start_line, end_line)
+        FsReadMode::Line {
+            start_line,
+            end_line,
+            max_line_bytes,
+            detect_shebang,
+            collapse_blank_lines,
+            trim_trailing_whitespace,
+            include_hash,
+            filter,
+            normalize_line_endings,
+            expand_tabs,
+            expand_all_tabs,
+        } => line::read_lines(
+            &params.path,
+            &line::LineOptions {
+                start_line,
+                end_line,
+                max_line_bytes,
+                detect_shebang,
+                collapse_blank_lines,
+                trim_trailing_whitespace,
+                include_hash,
+                filter,
+                normalize_line_endings,
+                expand_tabs,
+                expand_all_tabs,
+                max_output_bytes: params.max_output_bytes,
+            },
+        ),
+        FsReadMode::Directory {
+            depth,
+            resolve_root,
+            git_status,
+            include_hidden,
+            extra_ignores,
+            prune_empty_dirs,
+            ignore_file,
+            exclude_globs,
+            parent_gitignores,
+            gitignore_boundary,
+            skip_generated,
+            entries_kind,
+            normalize_separators,
+            text_only,
+            max_entries,
+        } => directory::list_directory(
+            &params.path,
+            &directory::DirectoryOptions {
+                depth,
+                resolve_root,
+                git_status,
+                include_hidden,
+                extra_ignores,
+                prune_empty_dirs,
+                ignore_file,
+                exclude_globs,
+                parent_gitignores,
+                gitignore_boundary,
+                skip_generated,
+                entries_kind,
+                normalize_separators,
+                text_only,
+                max_entries,
+            },
+        ),
+        FsReadMode::Base64 { max_bytes } => binary::read_base64(&params.path, max_bytes),
+        FsReadMode::LineBytes {
+            start_line,
+            end_line,
+        } => line::read_line_bytes(&params.path, start_line, end_line),
+        FsReadMode::NestedTree { depth } => directory::nested_tree(&params.path, depth),
+        FsReadMode::GlobFind { glob, depth } => directory::glob_find(&params.path, &glob, depth),
+        FsReadMode::NewerThan { reference, depth } => {
+            directory::newer_than(&params.path, &reference, depth)
+        }
+        FsReadMode::GroupBy { key, depth } => directory::group_by(&params.path, key, depth),
+        FsReadMode::LanguageBreakdown {
+            depth,
+            skip_generated,
+        } => directory::language_breakdown(&params.path, depth, skip_generated),
+        FsReadMode::DirTreemap { depth } => directory::dir_treemap(&params.path, depth),
+        FsReadMode::TreeHash { algorithm } => directory::tree_hash(&params.path, algorithm),
+        FsReadMode::HashManifest {
+            algorithm,
+            ignore_file,
+        } => directory::hash_manifest(&params.path, algorithm, ignore_file.as_deref()),
+        FsReadMode::VerifyHash {
+            algorithm,
+            expected,
+        } => hash::verify_hash(&params.path, algorithm, &expected),
+        FsReadMode::JsonShape { max_depth } => json_shape::json_shape(&params.path, max_depth),
+        FsReadMode::LargestFiles { limit, ignore_file } => {
+            directory::largest_files(&params.path, limit, ignore_file.as_deref())
+        }
+        FsReadMode::After { pattern, occurrence } => {
+            search::after_pattern(&params.path, &pattern, occurrence)
         }
-        FsReadMode::Directory { depth } => {
-            directory::list_directory(&params.path, depth)
+        FsReadMode::HeadTail { head, tail } => line::read_head_tail(&params.path, head, tail),
+        FsReadMode::Validate { repair } => encoding::validate_utf8(&params.path, repair),
+        FsReadMode::EditPreview { edits } => edit::edit_preview(&params.path, &edits),
+        FsReadMode::DiffAgainst {
+            content,
+            context_lines,
+        } => edit::diff_against(&params.path, &content, context_lines),
+        FsReadMode::RepoRoot => git::repo_root(&params.path),
+        FsReadMode::LineStats => line::line_stats(&params.path),
+        FsReadMode::IndentStyle => line::indent_style(&params.path),
+        FsReadMode::WhitespaceLint => line::whitespace_lint(&params.path),
+        FsReadMode::Section {
+            separator,
+            index,
+            regex,
+        } => line::read_section(&params.path, &separator, index, regex),
+        FsReadMode::DotEnv => dotenv::read_dotenv(&params.path),
+        FsReadMode::TokenEstimate { model } => tokens::token_estimate(&params.path, model),
+        FsReadMode::EnclosingScope { line } => scope::enclosing_scope(&params.path, line),
+        FsReadMode::Lines {
+            numbers,
+            relative_numbering,
+        } => line::read_line_numbers(&params.path, &numbers, relative_numbering),
+        FsReadMode::Imports => imports::extract_imports(&params.path),
+        FsReadMode::StripComments => comments::strip_comments(&params.path),
+        FsReadMode::CommentRatio => comments::comment_ratio(&params.path),
+        FsReadMode::Skeleton => scope::skeleton(&params.path),
+        FsReadMode::MatchRatio { pattern } => search::match_ratio(&params.path, &pattern),
+        FsReadMode::Extract { pattern } => search::extract(&params.path, &pattern),
+        FsReadMode::FilesContaining { patterns, require } => {
+            search::files_containing(&params.path, &patterns, require)
         }
-        FsReadMode::Search { pattern, context_lines } => {
-            search::search_pattern(&params.path, &pattern, context_lines)
+        FsReadMode::FuzzyFind {
+            query,
+            max_results,
+            min_score,
+        } => fuzzy::fuzzy_find(&params.path, &query, max_results, min_score),
+        FsReadMode::MultiFile {
+            paths,
+            separator,
+            max_threads,
+        } => multi_file::read_files(&params.path, &paths, separator.as_deref(), max_threads),
+        FsReadMode::ChangedSince { since, depth } => {
+            directory::changed_since(&params.path, since, depth)
         }
+        FsReadMode::ChangedBetween { base, head } => {
+            git::changed_between(&params.path, &base, head.as_deref())
+        }
+        FsReadMode::GitShow {
+            revision,
+            start_line,
+            end_line,
+        } => git::show_at_revision(&params.path, &revision, start_line, end_line),
+        FsReadMode::Search {
+            pattern,
+            context_lines,
+            start_line,
+            end_line,
+            include_summary,
+            literal,
+            max_line_bytes,
+            group_by_file,
+            depth,
+            anchor_start,
+            anchor_end,
+            numbered_context,
+            expand_to,
+            ignore_file,
+            sort_by_match_count,
+            dedupe_context_lines,
+            max_matches_per_file,
+            search_archives,
+            recent_first,
+            skip_generated,
+            relative_to,
+            max_context_width,
+            render_block,
+            normalize_separators,
+            all_of,
+            scope,
+            within,
+            context_mode,
+            whole_word,
+            case_insensitive,
+            hunk_format,
+            normalize_unicode,
+            rank,
+        } => search::search_pattern(
+            &params.path,
+            &pattern,
+            &search::SearchOptions {
+                context_lines,
+                start_line,
+                end_line,
+                include_summary,
+                literal,
+                max_line_bytes,
+                group_by_file,
+                depth,
+                anchor_start,
+                anchor_end,
+                numbered_context,
+                expand_to,
+                ignore_file,
+                sort_by_match_count,
+                dedupe_context_lines,
+                max_matches_per_file,
+                search_archives,
+                recent_first,
+                skip_generated,
+                relative_to,
+                max_context_width,
+                render_block,
+                normalize_separators,
+                all_of,
+                scope,
+                within,
+                context_mode,
+                whole_word,
+                case_insensitive,
+                hunk_format,
+                normalize_unicode,
+                rank,
+            },
+        ),
     }
 }
 
-#[pyfunction]
-#[pyo3(signature = (path, mode, start_line=None, end_line=None, depth=None, pattern=None, context_lines=None))]
-fn fs_read_py(
+fn build_params(
     path: String,
     mode: String,
     start_line: Option<i32>,
@@ -32,27 +294,102 @@ fn fs_read_py(
     depth: Option<u32>,
     pattern: Option<String>,
     context_lines: Option<u32>,
-) -> PyResult<String> {
+) -> PyResult<FsReadParams> {
     let path_buf = PathBuf::from(path);
     
     let fs_mode = match mode.as_str() {
-        "Line" => FsReadMode::Line { start_line, end_line },
-        "Directory" => FsReadMode::Directory { depth: depth.unwrap_or(0) },
+        "Line" => FsReadMode::Line {
+            start_line,
+            end_line,
+            max_line_bytes: None,
+            detect_shebang: false,
+            collapse_blank_lines: false,
+            trim_trailing_whitespace: false,
+            include_hash: None,
+            filter: None,
+            normalize_line_endings: None,
+            expand_tabs: None,
+            expand_all_tabs: false,
+        },
+        "Directory" => FsReadMode::Directory {
+            depth: depth.unwrap_or(0),
+            resolve_root: true,
+            git_status: false,
+            include_hidden: false,
+            extra_ignores: Vec::new(),
+            prune_empty_dirs: false,
+            ignore_file: None,
+            exclude_globs: Vec::new(),
+            parent_gitignores: false,
+            gitignore_boundary: None,
+            skip_generated: false,
+            entries_kind: Default::default(),
+            normalize_separators: true,
+            text_only: false,
+            max_entries: None,
+        },
         "Search" => FsReadMode::Search {
             pattern: pattern.ok_or_else(|| {
                 PyErr::new::<pyo3::exceptions::PyValueError, _>("pattern required for Search mode")
             })?,
             context_lines: context_lines.unwrap_or(2),
+            start_line,
+            end_line,
+            include_summary: false,
+            literal: false,
+            max_line_bytes: None,
+            group_by_file: false,
+            depth: None,
+            anchor_start: false,
+            anchor_end: false,
+            numbered_context: false,
+            expand_to: None,
+            ignore_file: None,
+            sort_by_match_count: false,
+            dedupe_context_lines: false,
+            max_matches_per_file: None,
+            search_archives: false,
+            recent_first: false,
+            skip_generated: false,
+            relative_to: None,
+            max_context_width: None,
+            render_block: false,
+            normalize_separators: true,
+            all_of: Vec::new(),
+            scope: Default::default(),
+            within: None,
+            context_mode: None,
+            whole_word: false,
+            case_insensitive: false,
+            hunk_format: false,
+            normalize_unicode: false,
+            rank: false,
         },
         _ => return Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(
             format!("Invalid mode: {}", mode)
         )),
     };
 
-    let params = FsReadParams {
+    Ok(FsReadParams {
         path: path_buf,
         mode: fs_mode,
-    };
+        max_output_bytes: None,
+        redact_paths: None,
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (path, mode, start_line=None, end_line=None, depth=None, pattern=None, context_lines=None))]
+fn fs_read_py(
+    path: String,
+    mode: String,
+    start_line: Option<i32>,
+    end_line: Option<i32>,
+    depth: Option<u32>,
+    pattern: Option<String>,
+    context_lines: Option<u32>,
+) -> PyResult<String> {
+    let params = build_params(path, mode, start_line, end_line, depth, pattern, context_lines)?;
 
     let result = fs_read(params).map_err(|e| {
         PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string())
@@ -64,31 +401,251 @@ fn fs_read_py(
 }
 
 #[pyfunction]
-fn fs_read_batch_py(operations: Vec<String>) -> PyResult<Vec<String>> {
-    let mut results = Vec::new();
+#[pyo3(signature = (path, mode, start_line=None, end_line=None, depth=None, pattern=None, context_lines=None))]
+fn fs_read_csv_py(
+    path: String,
+    mode: String,
+    start_line: Option<i32>,
+    end_line: Option<i32>,
+    depth: Option<u32>,
+    pattern: Option<String>,
+    context_lines: Option<u32>,
+) -> PyResult<String> {
+    let params = build_params(path, mode, start_line, end_line, depth, pattern, context_lines)?;
+
+    let result = fs_read(params).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string())
+    })?;
+
+    csv_output::to_csv(&result).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
+    })
+}
+
+/// Search results as `file:line:col:content` lines for editor quickfix lists.
+/// Takes one operation's params JSON, as `fs_read_batch_py` does, so every
+/// Search option applies; a mode that doesn't produce a Search result is a
+/// `ValueError`.
+#[pyfunction]
+fn fs_read_grep_format_py(op_json: String) -> PyResult<String> {
+    let params: FsReadParams = serde_json::from_str(&op_json).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
+    })?;
+
+    let result = fs_read(params).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string())
+    })?;
+
+    grep_output::to_grep(&result).map_err(|e| {
+        PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
+    })
+}
+
+#[pyfunction]
+#[pyo3(signature = (
+    operations,
+    max_retries=retry::DEFAULT_MAX_RETRIES,
+    soft_fail=false,
+    dedupe=false,
+))]
+fn fs_read_batch_py(
+    operations: Vec<String>,
+    max_retries: u32,
+    soft_fail: bool,
+    dedupe: bool,
+) -> PyResult<Vec<String>> {
+    let mut results: Vec<String> = Vec::new();
+    // Dedupe key to the index of the first result computed for it
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
 
     for op_json in operations {
         let params: FsReadParams = serde_json::from_str(&op_json).map_err(|e| {
             PyErr::new::<pyo3::exceptions::PyValueError, _>(e.to_string())
         })?;
+        let key = if dedupe { dedupe_key(&params) } else { None };
+        if let Some(&index) = key.as_ref().and_then(|k| first_seen.get(k)) {
+            results.push(results[index].clone());
+            continue;
+        }
+        let path = params.path.to_string_lossy().to_string();
 
-        let result = fs_read(params).map_err(|e| {
-            PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string())
-        })?;
+        let result = retry::with_default_backoff(max_retries, || fs_read(params.clone()));
+        let result = match result {
+            Ok(result) => result,
+            // Soft failures keep the batch going with an error entry in place
+            Err(e) if soft_fail => FsReadResult::Error {
+                warnings: vec![FsWarning::from_error(path, &e)],
+            },
+            Err(e) => {
+                return Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))
+            }
+        };
 
         let result_json = serde_json::to_string(&result).map_err(|e| {
             PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string())
         })?;
 
+        if let Some(key) = key {
+            first_seen.insert(key, results.len());
+        }
         results.push(result_json);
     }
 
     Ok(results)
 }
 
+/// Identifies batch operations that would read the same thing: the params
+/// with the path resolved, so `./a.txt` and `a.txt` match. Paths that don't
+/// resolve aren't deduplicated.
+fn dedupe_key(params: &FsReadParams) -> Option<String> {
+    let mut params = params.clone();
+    params.path = params.path.canonicalize().ok()?;
+    serde_json::to_string(&params).ok()
+}
+
+/// Bytes of lines read per GIL release in `fs_read_foreach_py`
+const FOREACH_BATCH_BYTES: usize = 64 * 1024;
+
+/// Calls `callback(line_number, line)` for each line of the file, reading in
+/// batches with the GIL released. Iteration stops early when the callback
+/// returns `False`; the result is the number of lines passed to it.
+#[pyfunction]
+fn fs_read_foreach_py(
+    py: Python<'_>,
+    path: String,
+    callback: Bound<'_, PyAny>,
+) -> PyResult<usize> {
+    let to_py_err = |e: FsReadError| {
+        PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string())
+    };
+    let mut batches = line::LineBatches::open(Path::new(&path)).map_err(to_py_err)?;
+    let mut line_number = 0;
+
+    loop {
+        let batch = py
+            .allow_threads(|| batches.next_batch(FOREACH_BATCH_BYTES))
+            .map_err(to_py_err)?;
+        if batch.is_empty() {
+            return Ok(line_number);
+        }
+
+        for line in batch {
+            line_number += 1;
+            let keep_going = callback.call1((line_number, line))?;
+            if matches!(keep_going.extract::<bool>(), Ok(false)) {
+                return Ok(line_number);
+            }
+        }
+    }
+}
+
+#[pyfunction]
+fn set_read_buffer_capacity_py(bytes: usize) {
+    set_read_buffer_capacity(bytes);
+}
+
 #[pymodule]
 fn contextinator_core(m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_function(wrap_pyfunction!(fs_read_py, m)?)?;
     m.add_function(wrap_pyfunction!(fs_read_batch_py, m)?)?;
+    m.add_function(wrap_pyfunction!(fs_read_csv_py, m)?)?;
+    m.add_function(wrap_pyfunction!(fs_read_grep_format_py, m)?)?;
+    m.add_function(wrap_pyfunction!(fs_read_foreach_py, m)?)?;
+    m.add_function(wrap_pyfunction!(set_read_buffer_capacity_py, m)?)?;
+    m.add_class::<pool::PyFsReadPool>()?;
+    m.add_class::<pool::FsReadFuture>()?;
+    m.add("DEFAULT_READ_BUFFER_CAPACITY", DEFAULT_READ_BUFFER_CAPACITY)?;
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+
+    #[test]
+    fn test_dedupe_key() {
+        let temp = TempDir::new().unwrap();
+        std::fs::write(temp.path().join("a.txt"), "x").unwrap();
+        let op = |path: &str, mode: &str| {
+            let json = format!(r#"{{"path": {:?}, "mode": "{}"}}"#, path, mode);
+            dedupe_key(&serde_json::from_str(&json).unwrap())
+        };
+
+        let plain = temp.path().join("a.txt").to_string_lossy().to_string();
+        let dotted = temp.path().join("./a.txt").to_string_lossy().to_string();
+        assert!(op(&plain, "Line").is_some());
+        assert_eq!(op(&plain, "Line"), op(&dotted, "Line"));
+        assert_ne!(op(&plain, "Line"), op(&plain, "LineStats"));
+        assert_eq!(op("/no/such/file", "Line"), None);
+    }
+
+    #[test]
+    fn test_empty_file_every_mode() {
+        let temp = TempDir::new().unwrap();
+        let empty = temp.path().join("empty.py");
+        std::fs::write(&empty, "").unwrap();
+        let blank = temp.path().join("blank.txt");
+        std::fs::write(&blank, "\n\n").unwrap();
+
+        // `mode` is the quoted mode name followed by any other fields
+        let read = |path: &Path, mode: &str| {
+            let json = format!(r#"{{"path": {:?}, "mode": {}}}"#, path, mode);
+            let params: FsReadParams =
+                serde_json::from_str(&json).unwrap_or_else(|e| panic!("{}: {}", mode, e));
+            fs_read(params)
+        };
+
+        let file_modes = [
+            r#""Line""#,
+            r#""Line", "start_line": -3, "end_line": 5"#,
+            r#""Line", "end_line": 2"#,
+            r#""Line", "include_hash": "sha256""#,
+            r#""Lines", "numbers": [1, 2]"#,
+            r#""LineBytes", "start_line": 1"#,
+            r#""LineStats""#,
+            r#""IndentStyle""#,
+            r#""WhitespaceLint""#,
+            r#""DotEnv""#,
+            r#""TreeHash", "algorithm": "fnv1a""#,
+            r#""VerifyHash", "algorithm": "sha256", "expected": "x""#,
+            r#""TokenEstimate", "model": "o200k""#,
+            r#""Base64""#,
+            r#""Validate", "repair": true"#,
+            r#""HeadTail", "head": 3, "tail": 3"#,
+            r#""After", "pattern": "x""#,
+            r#""Imports""#,
+            r#""StripComments""#,
+            r#""Skeleton""#,
+            r#""CommentRatio""#,
+            r#""MatchRatio", "pattern": "x""#,
+            r#""Search", "pattern": "x""#,
+            r#""Search", "pattern": "", "max_context_width": 4"#,
+            r#""Search", "pattern": "x", "all_of": ["y"], "scope": "file""#,
+            r#""EditPreview", "edits": []"#,
+            r#""DiffAgainst", "content": "x""#,
+            r#""FilesContaining", "patterns": ["x"], "require": "any""#,
+            r#""Search", "pattern": "x", "hunk_format": true"#,
+        ];
+        for mode in file_modes {
+            let result = read(&empty, mode);
+            // TreeHash wants a directory; everything else must succeed
+            if mode.contains("TreeHash") {
+                assert!(result.is_err(), "{}", mode);
+            } else {
+                assert!(result.is_ok(), "{}: {:?}", mode, result.err());
+            }
+        }
+        assert!(read(&empty, r#""EnclosingScope", "line": 1"#).is_err());
+
+        let is_empty = |path: &Path, mode: &str| match read(path, mode).unwrap() {
+            FsReadResult::Line { is_empty, total_lines, .. } => (is_empty, total_lines),
+            FsReadResult::LineStats { is_empty, total_lines, .. } => (is_empty, total_lines),
+            _ => panic!("Expected Line or LineStats result"),
+        };
+        assert_eq!(is_empty(&empty, r#""Line""#), (true, 0));
+        assert_eq!(is_empty(&blank, r#""Line""#), (false, 2));
+        assert_eq!(is_empty(&empty, r#""LineStats""#), (true, 0));
+        assert_eq!(is_empty(&blank, r#""LineStats""#), (false, 2));
+    }
+}
@@ -1,45 +1,900 @@
-use crate::types::{FsReadError, FsReadResult};
+use crate::binary::encode_base64;
+use crate::encoding::{self, sniff_file_encoding, UTF8, UTF8_BOM};
+use crate::hash::hash_bytes;
+use crate::output_limit;
+use crate::types::{
+    kind_check, FsReadError, FsReadResult, HashAlgorithm, LineEnding, LineFilter, NumberedLine,
+    PathKind,
+};
+use regex::Regex;
+use std::borrow::Cow;
+use std::cell::RefCell;
+use std::cmp::Reverse;
 use std::fs::File;
-use std::io::{BufRead, BufReader};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
-pub fn read_lines(
+pub const DEFAULT_READ_BUFFER_CAPACITY: usize = 64 * 1024;
+
+pub const TRUNCATION_MARKER: &str = "…[truncated]";
+
+// Reused buffers grown past this are shrunk back after use so a single huge
+// file doesn't pin its memory for the lifetime of the thread
+const MAX_RETAINED_BUFFER: usize = 4 * 1024 * 1024;
+
+static READ_BUFFER_CAPACITY: AtomicUsize = AtomicUsize::new(DEFAULT_READ_BUFFER_CAPACITY);
+
+thread_local! {
+    static CONTENT_BUFFER: RefCell<String> = const { RefCell::new(String::new()) };
+}
+
+pub fn set_read_buffer_capacity(bytes: usize) {
+    READ_BUFFER_CAPACITY.store(bytes.max(1), Ordering::Relaxed);
+}
+
+pub fn read_buffer_capacity() -> usize {
+    READ_BUFFER_CAPACITY.load(Ordering::Relaxed)
+}
+
+/// Reads `path` into a thread-local buffer that is cleared rather than
+/// reallocated between calls, and hands the content to `f`.
+pub(crate) fn with_file_content<T>(
     path: &Path,
-    start_line: Option<i32>,
-    end_line: Option<i32>,
+    f: impl FnOnce(&str) -> Result<T, FsReadError>,
+) -> Result<T, FsReadError> {
+    CONTENT_BUFFER.with(|cell| match cell.try_borrow_mut() {
+        Ok(mut buffer) => {
+            buffer.clear();
+            let result = read_into(path, &mut buffer).and_then(|_| f(&buffer));
+            buffer.clear();
+            if buffer.capacity() > MAX_RETAINED_BUFFER {
+                buffer.shrink_to(MAX_RETAINED_BUFFER);
+            }
+            result
+        }
+        // Re-entrant use falls back to a fresh allocation
+        Err(_) => {
+            let mut buffer = String::new();
+            read_into(path, &mut buffer)?;
+            f(&buffer)
+        }
+    })
+}
+
+fn read_into(path: &Path, buffer: &mut String) -> Result<(), FsReadError> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::with_capacity(read_buffer_capacity(), file);
+    reader.read_to_string(buffer)?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LineOptions {
+    pub start_line: Option<i32>,
+    pub end_line: Option<i32>,
+    pub max_line_bytes: Option<usize>,
+    pub detect_shebang: bool,
+    /// Runs of blank (whitespace-only) lines become a single empty line
+    pub collapse_blank_lines: bool,
+    pub trim_trailing_whitespace: bool,
+    pub include_hash: Option<HashAlgorithm>,
+    /// Applied to the lines in range, before truncation or trimming
+    pub filter: Option<LineFilter>,
+    /// Terminator the returned lines are joined with, instead of `\n`
+    pub normalize_line_endings: Option<LineEnding>,
+    /// Tab stop width; only leading tabs are expanded unless `expand_all_tabs`
+    pub expand_tabs: Option<u8>,
+    pub expand_all_tabs: bool,
+    /// Drop whole lines from the end until the result serializes to at most
+    /// this many bytes; see `output_limit`
+    pub max_output_bytes: Option<usize>,
+}
+
+pub fn read_lines(path: &Path, options: &LineOptions) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let byte_size = std::fs::metadata(path)?.len();
+    let detected_encoding = sniff_file_encoding(path)?;
+    // UTF-16 and Latin-1 files are decoded up front; UTF-8 is read as is
+    let decoded = match detected_encoding {
+        UTF8 | UTF8_BOM => None,
+        other => {
+            let bytes = std::fs::read(path)?;
+            encoding::decode(&bytes, other).map(|text| (text, bytes))
+        }
+    };
+
+    let interpreter = if options.detect_shebang {
+        read_first_line(path)?.as_deref().and_then(parse_shebang)
+    } else {
+        None
+    };
+
+    // A range anchored at the start of the file doesn't need every line
+    // decoded, unless the whole file has to be hashed anyway
+    let leading_range = options.include_hash.is_none()
+        && decoded.is_none()
+        && options.start_line.is_none_or(|n| n >= 0)
+        && matches!(options.end_line, Some(n) if n >= 0);
+
+    let regex = match &options.filter {
+        Some(LineFilter::Regex(pattern)) => {
+            Some(Regex::new(pattern).map_err(|e| FsReadError::InvalidPattern(e.to_string()))?)
+        }
+        _ => None,
+    };
+    let keep = |line: &str| match &options.filter {
+        None => true,
+        Some(LineFilter::NonBlank) => !line.trim().is_empty(),
+        Some(LineFilter::MinLength(min)) => line.chars().count() >= *min,
+        Some(LineFilter::Regex(_)) => regex.as_ref().is_some_and(|r| r.is_match(line)),
+    };
+
+    let mut content_hash = None;
+    let (mut selected_lines, total_lines) = if leading_range {
+        read_leading_lines(path, options, keep)?
+    } else if let Some((text, bytes)) = &decoded {
+        content_hash = options.include_hash.map(|algorithm| hash_bytes(algorithm, bytes));
+        select_lines(text, options, keep)?
+    } else {
+        with_file_content(path, |file_content| {
+            content_hash = options
+                .include_hash
+                .map(|algorithm| hash_bytes(algorithm, file_content.as_bytes()));
+            select_lines(strip_bom(file_content), options, keep)
+        })?
+    };
+
+    // Only a truncated line (kept prefix plus marker) can exceed the cap
+    let truncated_lines = match options.max_line_bytes {
+        Some(max) => selected_lines.iter().filter(|(_, line, _)| line.len() > max).count(),
+        None => 0,
+    };
+
+    if options.trim_trailing_whitespace {
+        for (_, line, _) in &mut selected_lines {
+            line.truncate(line.trim_end().len());
+        }
+    }
+
+    let mut tabs_expanded = 0;
+    if let Some(tab_width) = options.expand_tabs {
+        for (_, line, _) in &mut selected_lines {
+            if let (Cow::Owned(expanded), count) =
+                expand_tabs(line, tab_width, options.expand_all_tabs)
+            {
+                *line = expanded;
+                tabs_expanded += count;
+            }
+        }
+    }
+
+    let lines_collapsed = if options.collapse_blank_lines {
+        collapse_blank_lines(&mut selected_lines)
+    } else {
+        0
+    };
+
+    // Per returned line, indexed like `ENDINGS`: its terminator plus any
+    // bare `\r` left inside it, which also ended a line
+    let ending_counts: Vec<[usize; 3]> = match options.normalize_line_endings {
+        Some(_) => selected_lines
+            .iter()
+            .map(|(_, line, ending)| count_endings(line, *ending))
+            .collect(),
+        None => Vec::new(),
+    };
+    let (line_numbers, mut selected_lines): (Vec<usize>, Vec<String>) =
+        selected_lines.into_iter().map(|(number, line, _)| (number, line)).unzip();
+
+    let separator = match options.normalize_line_endings {
+        Some(ending) => {
+            for line in &mut selected_lines {
+                if line.contains('\r') {
+                    *line = line.replace('\r', ending.as_str());
+                }
+            }
+            ending.as_str()
+        }
+        None => "\n",
+    };
+    let (original_line_ending, line_endings_normalized) = match options.normalize_line_endings {
+        Some(ending) => tally_endings(&ending_counts, ending),
+        None => (None, false),
+    };
+
+    let mut result = FsReadResult::Line {
+        content: selected_lines.join(separator),
+        total_lines,
+        lines_returned: selected_lines.len(),
+        truncated_lines,
+        byte_size,
+        is_empty: byte_size == 0,
+        detected_encoding: detected_encoding.to_string(),
+        interpreter,
+        lines_collapsed,
+        content_hash,
+        line_numbers: if options.filter.is_some() { line_numbers } else { Vec::new() },
+        original_line_ending,
+        line_endings_normalized,
+        tabs_expanded,
+        truncated: false,
+        omitted_count: 0,
+    };
+    // Trimmed here, while the lines are still apart, so any terminator works
+    if let Some(max) = options.max_output_bytes {
+        output_limit::trim_joined_lines(&mut result, &selected_lines, separator, max);
+    }
+    Ok(result)
+}
+
+/// Replaces each tab with spaces up to the next multiple of `tab_width`
+/// columns (a column per char), stopping at the first other non-blank char
+/// unless `all`. Returns the line and the number of tabs replaced. A width
+/// of 0 just removes the tabs.
+fn expand_tabs(line: &str, tab_width: u8, all: bool) -> (Cow<'_, str>, usize) {
+    let end = if all {
+        line.len()
+    } else {
+        line.find(|c| c != ' ' && c != '\t').unwrap_or(line.len())
+    };
+    if !line[..end].contains('\t') {
+        return (Cow::Borrowed(line), 0);
+    }
+
+    let tab_width = tab_width as usize;
+    let mut expanded = String::with_capacity(line.len());
+    let mut column = 0;
+    let mut count = 0;
+
+    for c in line[..end].chars() {
+        if c == '\t' {
+            let spaces = if tab_width == 0 { 0 } else { tab_width - column % tab_width };
+            expanded.extend(std::iter::repeat_n(' ', spaces));
+            column += spaces;
+            count += 1;
+        } else {
+            expanded.push(c);
+            column += 1;
+        }
+    }
+    expanded.push_str(&line[end..]);
+
+    (Cow::Owned(expanded), count)
+}
+
+const ENDINGS: [LineEnding; 3] = [LineEnding::Lf, LineEnding::CrLf, LineEnding::Cr];
+
+/// Terminators that ended `line`, indexed like `ENDINGS`: its own `ending`
+/// plus each bare `\r` inside it.
+fn count_endings(line: &str, ending: Option<LineEnding>) -> [usize; 3] {
+    let mut counts = [0, 0, line.matches('\r').count()];
+    if let Some(ending) = ending {
+        counts[ENDINGS.iter().position(|&e| e == ending).unwrap_or(0)] += 1;
+    }
+    counts
+}
+
+/// The most common terminator across `counts`, and whether any besides
+/// `target` was seen.
+fn tally_endings(counts: &[[usize; 3]], target: LineEnding) -> (Option<LineEnding>, bool) {
+    let mut totals = [0usize; 3];
+    for line in counts {
+        for (total, count) in totals.iter_mut().zip(line) {
+            *total += count;
+        }
+    }
+
+    // Ties go to the earlier style in `ENDINGS`
+    let original = ENDINGS
+        .into_iter()
+        .zip(totals)
+        .rev()
+        .filter(|&(_, count)| count > 0)
+        .max_by_key(|&(_, count)| count)
+        .map(|(ending, _)| ending);
+    let changed = ENDINGS
+        .into_iter()
+        .zip(totals)
+        .any(|(ending, count)| ending != target && count > 0);
+
+    (original, changed)
+}
+
+/// Reduces each run of blank lines to one empty line, returning how many
+/// lines were removed.
+fn collapse_blank_lines(lines: &mut Vec<SelectedLine>) -> usize {
+    let before = lines.len();
+    let mut previous_blank = false;
+
+    lines.retain_mut(|(_, line, _)| {
+        let blank = line.trim().is_empty();
+        let keep = !(blank && previous_blank);
+        previous_blank = blank;
+        if blank {
+            line.clear();
+        }
+        keep
+    });
+
+    before - lines.len()
+}
+
+/// A returned line: its 1-based number, its text and the terminator that
+/// ended it (`None` for a last line without one).
+type SelectedLine = (usize, String, Option<LineEnding>);
+
+/// The lines of `content` in the requested range that pass `keep`, plus the
+/// file's total line count.
+fn select_lines(
+    content: &str,
+    options: &LineOptions,
+    keep: impl Fn(&str) -> bool,
+) -> Result<(Vec<SelectedLine>, usize), FsReadError> {
+    let lines: Vec<(&str, Option<LineEnding>)> =
+        content.split_inclusive('\n').map(split_line_ending).collect();
+    let total_lines = lines.len();
+    let (start_idx, end_idx) =
+        resolve_line_range(options.start_line, options.end_line, total_lines)?;
+
+    let selected_lines = lines[start_idx..end_idx]
+        .iter()
+        .enumerate()
+        .filter(|(_, (line, _))| keep(line))
+        .map(|(i, &(line, ending))| {
+            let line = truncate_line(line, options.max_line_bytes).into_owned();
+            (start_idx + i + 1, line, ending)
+        })
+        .collect();
+    Ok((selected_lines, total_lines))
+}
+
+/// Splits `\n` or `\r\n` off a line, as `str::lines` does.
+fn split_line_ending(line: &str) -> (&str, Option<LineEnding>) {
+    match line.strip_suffix('\n') {
+        Some(line) => match line.strip_suffix('\r') {
+            Some(line) => (line, Some(LineEnding::CrLf)),
+            None => (line, Some(LineEnding::Lf)),
+        },
+        None => (line, None),
+    }
+}
+
+/// `text` without a leading UTF-8 byte order mark.
+fn strip_bom(text: &str) -> &str {
+    text.strip_prefix('\u{FEFF}').unwrap_or(text)
+}
+
+/// Decodes only the lines up to `end_line`; the rest of the file is just
+/// scanned for newlines so `total_lines` stays exact.
+fn read_leading_lines(
+    path: &Path,
+    options: &LineOptions,
+    keep: impl Fn(&str) -> bool,
+) -> Result<(Vec<SelectedLine>, usize), FsReadError> {
+    let end_line = options.end_line.unwrap_or(0).max(0) as usize;
+    let first_idx = options.start_line.unwrap_or(0).max(1) as usize - 1;
+
+    let file = File::open(path)?;
+    let mut reader = BufReader::with_capacity(read_buffer_capacity(), file);
+    let mut collected = Vec::new();
+    let mut total_lines = 0;
+    let mut line = String::new();
+
+    while total_lines < end_line {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        let (mut line, ending) = split_line_ending(&line);
+        if total_lines == 0 {
+            line = strip_bom(line);
+        }
+        if total_lines >= first_idx && keep(line) {
+            let line = truncate_line(line, options.max_line_bytes);
+            collected.push((total_lines + 1, line.into_owned(), ending));
+        }
+        total_lines += 1;
+    }
+
+    total_lines += count_remaining_lines(&mut reader)?;
+
+    // Validates the range the same way the full read does
+    resolve_line_range(options.start_line, options.end_line, total_lines)?;
+
+    Ok((collected, total_lines))
+}
+
+/// Reads the given 1-based line numbers, ascending and deduplicated, in one
+/// pass that stops after the highest one. Numbers past the end of the file
+/// (or 0) are reported in `out_of_range`. With `relative_numbering` the
+/// returned lines are renumbered so the first one is line 1; `out_of_range`
+/// always uses file positions.
+pub fn read_line_numbers(
+    path: &Path,
+    numbers: &[u32],
+    relative_numbering: bool,
 ) -> Result<FsReadResult, FsReadError> {
-    if !path.exists() {
-        return Err(FsReadError::PathNotFound(path.to_path_buf()));
+    kind_check(path, Some(PathKind::File))?;
+
+    let mut wanted = numbers.to_vec();
+    wanted.sort_unstable();
+    wanted.dedup();
+
+    let file = File::open(path)?;
+    let mut reader = BufReader::with_capacity(read_buffer_capacity(), file);
+    let mut lines = Vec::with_capacity(wanted.len());
+    let mut remaining = wanted.iter().copied().skip_while(|&n| n == 0).peekable();
+    let mut line_number = 0;
+    let mut line = String::new();
+
+    while let Some(&next) = remaining.peek() {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        if line_number == next {
+            lines.push(NumberedLine {
+                line_number: next as usize,
+                content: strip_line_ending(&line).to_string(),
+            });
+            remaining.next();
+        }
     }
 
-    if !path.is_file() {
-        return Err(FsReadError::InvalidPath(format!(
-            "{} is not a file",
-            path.display()
-        )));
+    let mut out_of_range: Vec<u32> = wanted.iter().copied().take_while(|&n| n == 0).collect();
+    out_of_range.extend(remaining);
+
+    if relative_numbering {
+        if let Some(offset) = lines.first().map(|l| l.line_number - 1) {
+            lines.iter_mut().for_each(|l| l.line_number -= offset);
+        }
     }
 
+    Ok(FsReadResult::Lines {
+        lines,
+        out_of_range,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// Line length statistics in bytes (excluding line endings), gathered in a
+/// streaming pass that holds one line at a time. Invalid UTF-8 is fine here.
+pub fn line_stats(path: &Path) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
     let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let lines: Vec<String> = reader
-        .lines()
-        .collect::<Result<_, _>>()
-        .map_err(FsReadError::IoError)?;
+    let mut reader = BufReader::with_capacity(read_buffer_capacity(), file);
+    let mut line = Vec::new();
+    let mut total_lines = 0;
+    let mut total_length = 0;
+    let mut max_line_length = 0;
+    let mut longest_line_number = 0;
 
-    let total_lines = lines.len();
-    let (start_idx, end_idx) = resolve_line_range(start_line, end_line, total_lines)?;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        total_lines += 1;
 
-    let selected_lines: Vec<String> = lines[start_idx..end_idx].to_vec();
-    let content = selected_lines.join("\n");
+        let content = line.strip_suffix(b"\n").unwrap_or(&line);
+        let length = content.strip_suffix(b"\r").unwrap_or(content).len();
+        total_length += length;
+        if length > max_line_length || total_lines == 1 {
+            max_line_length = length;
+            longest_line_number = total_lines;
+        }
+    }
 
-    Ok(FsReadResult::Line {
-        content,
+    // Any byte at all makes at least one line
+    Ok(FsReadResult::LineStats {
+        is_empty: total_lines == 0,
         total_lines,
-        lines_returned: selected_lines.len(),
+        max_line_length,
+        avg_line_length: if total_lines == 0 {
+            0.0
+        } else {
+            total_length as f64 / total_lines as f64
+        },
+        longest_line_number,
+    })
+}
+
+// Block size for scanning backwards from the end of a file
+const TAIL_BLOCK: u64 = 8 * 1024;
+
+/// Reads a file as batches of lossily decoded lines, so a caller can do the
+/// IO without holding a lock (such as the GIL) and handle each batch after.
+pub struct LineBatches {
+    reader: BufReader<File>,
+    bytes: Vec<u8>,
+}
+
+impl LineBatches {
+    pub fn open(path: &Path) -> Result<Self, FsReadError> {
+        kind_check(path, Some(PathKind::File))?;
+        let file = File::open(path)?;
+        Ok(Self {
+            reader: BufReader::with_capacity(read_buffer_capacity(), file),
+            bytes: Vec::new(),
+        })
+    }
+
+    /// The next lines, stopping once they total at least `max_bytes`; empty
+    /// at the end of the file.
+    pub fn next_batch(&mut self, max_bytes: usize) -> Result<Vec<String>, FsReadError> {
+        let mut batch = Vec::new();
+        let mut size = 0;
+
+        while size < max_bytes {
+            self.bytes.clear();
+            if self.reader.read_until(b'\n', &mut self.bytes)? == 0 {
+                break;
+            }
+            size += self.bytes.len();
+            batch.push(strip_line_ending(&String::from_utf8_lossy(&self.bytes)).to_string());
+        }
+
+        Ok(batch)
+    }
+}
+
+/// Guesses the indentation style from leading whitespace in one streaming
+/// pass. The space width is the most common step between a line and a
+/// deeper-indented line after it (blank lines don't count). A file is mixed
+/// when both tab- and space-indented lines appear, or a single indent
+/// contains both.
+pub fn indent_style(path: &Path) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let file = File::open(path)?;
+    let mut reader = BufReader::with_capacity(read_buffer_capacity(), file);
+    let mut line = Vec::new();
+    let mut tab_lines = 0usize;
+    let mut space_lines = 0usize;
+    let mut mixed_indent = false;
+    // Steps of 1..=8 columns; wider ones are alignment rather than nesting
+    let mut steps = [0usize; 9];
+    let mut previous_spaces = Some(0);
+
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        let indent = line.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+        if line[indent..].iter().all(|b| b.is_ascii_whitespace()) {
+            continue;
+        }
+
+        let leading = &line[..indent];
+        mixed_indent |= leading.contains(&b' ') && leading.contains(&b'\t');
+        match leading.first() {
+            Some(b'\t') => {
+                tab_lines += 1;
+                previous_spaces = None;
+            }
+            Some(_) => {
+                space_lines += 1;
+                if let Some(step) = previous_spaces.and_then(|p| indent.checked_sub(p)) {
+                    if (1..steps.len()).contains(&step) {
+                        steps[step] += 1;
+                    }
+                }
+                previous_spaces = Some(indent);
+            }
+            None => previous_spaces = Some(0),
+        }
+    }
+
+    let indented = tab_lines + space_lines;
+    // Ties go to the narrower step
+    let space_width = (1..steps.len())
+        .filter(|&w| steps[w] > 0)
+        .max_by_key(|&w| (steps[w], Reverse(w)))
+        .map(|w| w as u8);
+
+    Ok(FsReadResult::IndentStyle {
+        uses_tabs: tab_lines > space_lines,
+        space_width,
+        mixed: mixed_indent || (tab_lines > 0 && space_lines > 0),
+        confidence: if indented == 0 {
+            0.0
+        } else {
+            tab_lines.max(space_lines) as f32 / indented as f32
+        },
+    })
+}
+
+/// Trailing whitespace, tabs in indentation and a missing final newline,
+/// found in one pass without changing anything. Indentation is mixed as in
+/// `indent_style`: tab- and space-indented lines both appear, or one indent
+/// has both. Whitespace-only lines count as trailing whitespace but not
+/// towards indentation.
+pub fn whitespace_lint(path: &Path) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let file = File::open(path)?;
+    let mut reader = BufReader::with_capacity(read_buffer_capacity(), file);
+    let mut line = Vec::new();
+    let mut trailing_ws_lines = Vec::new();
+    let mut tabs_in_indent_lines = Vec::new();
+    let mut tab_indented = false;
+    let mut space_indented = false;
+    let mut mixed_indent = false;
+    let mut missing_final_newline = false;
+
+    for line_number in 1.. {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        missing_final_newline = line.last() != Some(&b'\n');
+
+        let text = line.strip_suffix(b"\n").unwrap_or(&line);
+        let text = text.strip_suffix(b"\r").unwrap_or(text);
+        if matches!(text.last(), Some(b' ' | b'\t')) {
+            trailing_ws_lines.push(line_number);
+        }
+
+        let indent = text.iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+        if indent == text.len() {
+            continue;
+        }
+        let leading = &text[..indent];
+        if leading.contains(&b'\t') {
+            tabs_in_indent_lines.push(line_number);
+            mixed_indent |= leading.contains(&b' ');
+        }
+        match leading.first() {
+            Some(b'\t') => tab_indented = true,
+            Some(_) => space_indented = true,
+            None => {}
+        }
+    }
+
+    Ok(FsReadResult::WhitespaceLint {
+        trailing_ws_lines,
+        mixed_indent: mixed_indent || (tab_indented && space_indented),
+        missing_final_newline,
+        tabs_in_indent_lines,
+    })
+}
+
+/// The `index`th (0-based) run of lines between separator lines, as in a
+/// multi-document YAML file. Separator lines belong to no section, and a
+/// separator on the first or last line doesn't start an empty section.
+pub fn read_section(
+    path: &Path,
+    separator: &str,
+    index: usize,
+    regex: bool,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let regex = if regex {
+        Some(Regex::new(separator).map_err(|e| FsReadError::InvalidPattern(e.to_string()))?)
+    } else {
+        None
+    };
+    let is_separator = |line: &str| match &regex {
+        Some(regex) => regex.is_match(line),
+        None => line.trim_end() == separator,
+    };
+
+    with_file_content(path, |content| {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut sections = Vec::new();
+        let mut start = 0;
+        for (i, line) in lines.iter().enumerate() {
+            if is_separator(line) {
+                sections.push(start..i);
+                start = i + 1;
+            }
+        }
+        sections.push(start..lines.len());
+
+        if sections.last().is_some_and(|s| s.is_empty()) {
+            sections.pop();
+        }
+        if sections.first().is_some_and(|s| s.is_empty() && s.start == 0) {
+            sections.remove(0);
+        }
+
+        let total_sections = sections.len();
+        let section = sections
+            .get(index)
+            .ok_or(FsReadError::SectionOutOfRange(index, total_sections))?;
+
+        Ok(FsReadResult::Section {
+            content: lines[section.clone()].join("\n"),
+            start_line: section.start + 1,
+            lines_returned: section.len(),
+            total_sections,
+            truncated: false,
+            omitted_count: 0,
+        })
+    })
+}
+
+/// First `head` and last `tail` lines of a file. The middle is only scanned
+/// for newlines to count it and the tail is read by seeking back from the
+/// end, so memory stays proportional to the returned lines. Lines are
+/// decoded lossily.
+pub fn read_head_tail(path: &Path, head: u32, tail: u32) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let file = File::open(path)?;
+    let mut reader = BufReader::with_capacity(read_buffer_capacity(), file);
+    let mut head_lines = Vec::new();
+    let mut bytes = Vec::new();
+
+    while head_lines.len() < head as usize {
+        bytes.clear();
+        if reader.read_until(b'\n', &mut bytes)? == 0 {
+            break;
+        }
+        head_lines.push(strip_line_ending(&String::from_utf8_lossy(&bytes)).to_string());
+    }
+
+    let total_lines = head_lines.len() + count_remaining_lines(&mut reader)?;
+    // Lines already returned as head are never repeated in the tail
+    let tail_count = (tail as usize).min(total_lines - head_lines.len());
+    let tail_lines = read_tail_lines(reader.get_mut(), tail_count)?;
+
+    Ok(FsReadResult::HeadTail {
+        head_content: head_lines.join("\n"),
+        tail_content: tail_lines.join("\n"),
+        total_lines,
+        omitted: total_lines - head_lines.len() - tail_lines.len(),
+    })
+}
+
+/// Exact on-disk bytes of a line range, terminators included, base64
+/// encoded. Lines are numbered as in Line mode; `byte_end` is exclusive.
+pub fn read_line_bytes(
+    path: &Path,
+    start_line: Option<i32>,
+    end_line: Option<i32>,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    let bytes = std::fs::read(path)?;
+    // Offset just past each line; a final unterminated line ends at EOF
+    let mut line_ends: Vec<usize> = memchr::memchr_iter(b'\n', &bytes).map(|i| i + 1).collect();
+    if line_ends.last().map_or(0, |&end| end) < bytes.len() {
+        line_ends.push(bytes.len());
+    }
+
+    let (start_idx, end_idx) = resolve_line_range(start_line, end_line, line_ends.len())?;
+    let offset = |idx: usize| if idx == 0 { 0 } else { line_ends[idx - 1] };
+    let (byte_start, byte_end) = (offset(start_idx), offset(end_idx));
+
+    Ok(FsReadResult::LineBytes {
+        content_b64: encode_base64(&bytes[byte_start..byte_end]),
+        byte_start: byte_start as u64,
+        byte_end: byte_end as u64,
     })
 }
 
-fn resolve_line_range(
+/// Last `count` lines of `file`, found by reading fixed-size blocks backwards
+/// from the end until enough line breaks have been seen.
+pub(crate) fn read_tail_lines(file: &mut File, count: usize) -> Result<Vec<String>, FsReadError> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut block = vec![0; TAIL_BLOCK as usize];
+    let mut end = len;
+    let mut newlines = 0;
+    let mut start = 0;
+
+    'scan: while end > 0 {
+        let block_start = end.saturating_sub(TAIL_BLOCK);
+        let chunk = &mut block[..(end - block_start) as usize];
+        file.seek(SeekFrom::Start(block_start))?;
+        file.read_exact(chunk)?;
+
+        for pos in memchr::memrchr_iter(b'\n', chunk) {
+            let offset = block_start + pos as u64;
+            // A final newline terminates the last line rather than starting one
+            if offset + 1 == len {
+                continue;
+            }
+            newlines += 1;
+            if newlines == count {
+                start = offset + 1;
+                break 'scan;
+            }
+        }
+        end = block_start;
+    }
+
+    let mut bytes = Vec::with_capacity((len - start) as usize);
+    file.seek(SeekFrom::Start(start))?;
+    file.read_to_end(&mut bytes)?;
+
+    Ok(String::from_utf8_lossy(&bytes).lines().map(String::from).collect())
+}
+
+/// Counts lines the way `str::lines` would, without decoding them.
+fn count_remaining_lines(reader: &mut impl BufRead) -> Result<usize, FsReadError> {
+    let mut count = 0;
+    let mut last_byte = None;
+
+    loop {
+        let buffer = reader.fill_buf()?;
+        if buffer.is_empty() {
+            break;
+        }
+        count += memchr::memchr_iter(b'\n', buffer).count();
+        last_byte = buffer.last().copied();
+        let len = buffer.len();
+        reader.consume(len);
+    }
+
+    if last_byte.is_some_and(|b| b != b'\n') {
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+fn strip_line_ending(line: &str) -> &str {
+    let line = line.strip_suffix('\n').unwrap_or(line);
+    line.strip_suffix('\r').unwrap_or(line)
+}
+
+/// Reads just the first line (lossily decoded), or `None` for an empty file.
+pub(crate) fn read_first_line(path: &Path) -> Result<Option<String>, FsReadError> {
+    let file = File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let mut bytes = Vec::new();
+
+    if reader.read_until(b'\n', &mut bytes)? == 0 {
+        return Ok(None);
+    }
+
+    let line = String::from_utf8_lossy(&bytes);
+    Ok(Some(strip_line_ending(&line).to_string()))
+}
+
+/// Interpreter name from a `#!` line, looking through `env` and its flags:
+/// `#!/usr/bin/env -S python3 -u` gives `python3`.
+pub(crate) fn parse_shebang(line: &str) -> Option<String> {
+    let mut parts = line.strip_prefix("#!")?.split_whitespace();
+    let program = parts.next()?.rsplit('/').next()?;
+
+    let interpreter = if program == "env" {
+        parts.find(|p| !p.starts_with('-') && !p.contains('='))?
+    } else {
+        program
+    };
+
+    Some(interpreter.to_string())
+}
+
+/// Cuts `line` to at most `max_bytes` (on a char boundary) and appends
+/// `TRUNCATION_MARKER`. Only truncated lines come back owned.
+pub(crate) fn truncate_line(line: &str, max_bytes: Option<usize>) -> Cow<'_, str> {
+    match max_bytes {
+        Some(max) if line.len() > max => {
+            let mut end = max;
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            Cow::Owned(format!("{}{}", &line[..end], TRUNCATION_MARKER))
+        }
+        _ => Cow::Borrowed(line),
+    }
+}
+
+pub(crate) fn resolve_line_range(
     start: Option<i32>,
     end: Option<i32>,
     total_lines: usize,
@@ -88,11 +943,269 @@ fn resolve_line_range(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_read_lines_collapse_blank_lines() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("test.txt");
+        fs::write(&path, "a  \n\n  \n\t\nb\t\n\nc").unwrap();
+
+        let options = LineOptions {
+            collapse_blank_lines: true,
+            trim_trailing_whitespace: true,
+            ..Default::default()
+        };
+        let result = read_lines(&path, &options).unwrap();
+
+        if let FsReadResult::Line {
+            content,
+            total_lines,
+            lines_returned,
+            lines_collapsed,
+            ..
+        } = result
+        {
+            assert_eq!(content, "a\n\nb\n\nc");
+            assert_eq!(total_lines, 7);
+            assert_eq!(lines_returned, 5);
+            assert_eq!(lines_collapsed, 2);
+        } else {
+            panic!("Expected Line result");
+        }
+    }
+
+    #[test]
+    fn test_read_lines_include_hash() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("test.txt");
+        fs::write(&path, "abc\ndef").unwrap();
+
+        let options = LineOptions {
+            start_line: Some(1),
+            end_line: Some(1),
+            include_hash: Some(HashAlgorithm::Sha256),
+            ..Default::default()
+        };
+        if let FsReadResult::Line {
+            content,
+            content_hash,
+            ..
+        } = read_lines(&path, &options).unwrap()
+        {
+            assert_eq!(content, "abc");
+            assert_eq!(
+                content_hash.as_deref(),
+                Some("d53d6b91af7caf8fe3d8021f116270137c0079d579a1e16965da80c2ed138ffb")
+            );
+        } else {
+            panic!("Expected Line result");
+        }
+    }
+
+    #[test]
+    fn test_read_head_tail() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("big.log");
+        let lines: Vec<String> = (1..=20_000).map(|i| format!("line {}", i)).collect();
+        fs::write(&path, lines.join("\n") + "\n").unwrap();
+
+        let head_tail = |head, tail| match read_head_tail(&path, head, tail).unwrap() {
+            FsReadResult::HeadTail { head_content, tail_content, total_lines, omitted } => {
+                (head_content, tail_content, total_lines, omitted)
+            }
+            _ => panic!("Expected HeadTail result"),
+        };
+
+        let (head, tail, total, omitted) = head_tail(2, 3);
+        assert_eq!(head, "line 1\nline 2");
+        assert_eq!(tail, "line 19998\nline 19999\nline 20000");
+        assert_eq!((total, omitted), (20_000, 19_995));
+
+        // Overlapping windows don't repeat lines
+        let (_, tail, _, omitted) = head_tail(19_999, 5);
+        assert_eq!(tail, "line 20000");
+        assert_eq!(omitted, 0);
+    }
+
+    #[test]
+    fn test_read_line_bytes() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("mixed.txt");
+        fs::write(&path, "one\r\ntwo\nthree\r\nfour").unwrap();
+
+        let line_bytes = |start, end| match read_line_bytes(&path, start, end).unwrap() {
+            FsReadResult::LineBytes { content_b64, byte_start, byte_end } => {
+                (content_b64, byte_start, byte_end)
+            }
+            _ => panic!("Expected LineBytes result"),
+        };
+
+        // "two\nthree\r\n"
+        assert_eq!(line_bytes(Some(2), Some(3)), ("dHdvCnRocmVlDQo=".to_string(), 5, 16));
+        // The unterminated last line runs to EOF
+        assert_eq!(line_bytes(Some(-1), None), ("Zm91cg==".to_string(), 16, 20));
+        assert_eq!(line_bytes(None, None).2, 20);
+        assert_eq!(line_bytes(Some(9), None), (String::new(), 20, 20));
+    }
+
+    #[test]
+    fn test_line_stats() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("test.txt");
+        fs::write(&path, "ab\r\nabcdef\n\nabcdef").unwrap();
+
+        let result = line_stats(&path).unwrap();
+
+        if let FsReadResult::LineStats {
+            total_lines,
+            max_line_length,
+            avg_line_length,
+            longest_line_number,
+            ..
+        } = result
+        {
+            assert_eq!(total_lines, 4);
+            assert_eq!(max_line_length, 6);
+            assert_eq!(avg_line_length, 3.5);
+            assert_eq!(longest_line_number, 2);
+        } else {
+            panic!("Expected LineStats result");
+        }
+    }
+
+    #[test]
+    fn test_line_batches() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("test.txt");
+        fs::write(&path, "one\r\ntwo\nthree\nfour").unwrap();
+
+        let mut batches = LineBatches::open(&path).unwrap();
+        assert_eq!(batches.next_batch(6).unwrap(), vec!["one", "two"]);
+        assert_eq!(batches.next_batch(1024).unwrap(), vec!["three", "four"]);
+        assert!(batches.next_batch(1024).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_indent_style() {
+        let temp = TempDir::new().unwrap();
+        let style = |name: &str, content: &str| {
+            let path = temp.path().join(name);
+            fs::write(&path, content).unwrap();
+            match indent_style(&path).unwrap() {
+                FsReadResult::IndentStyle {
+                    uses_tabs,
+                    space_width,
+                    mixed,
+                    confidence,
+                } => (uses_tabs, space_width, mixed, confidence),
+                _ => panic!("Expected IndentStyle result"),
+            }
+        };
+
+        let spaces = "def f():\n    if x:\n        y()\n\n    return 1\n";
+        assert_eq!(style("a.py", spaces), (false, Some(4), false, 1.0));
+
+        let tabs = "func f() {\n\tif x {\n\t\ty()\n\t}\n}\n";
+        assert_eq!(style("a.go", tabs), (true, None, false, 1.0));
+
+        let mixed = "fn f() {\n  a();\n\tb();\n  c();\n}\n";
+        let (uses_tabs, space_width, mixed, confidence) = style("a.rs", mixed);
+        assert!(!uses_tabs && mixed);
+        assert_eq!(space_width, Some(2));
+        assert!((confidence - 2.0 / 3.0).abs() < 1e-6);
+
+        assert_eq!(style("empty.txt", ""), (false, None, false, 0.0));
+    }
+
+    #[test]
+    fn test_read_section() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("docs.yaml");
+        fs::write(&file_path, "---\na: 1\n--- \nb: 2\nc: 3\n---\n---\nd: 4\n---\n").unwrap();
+
+        let section = |separator: &str, index, regex| {
+            match read_section(&file_path, separator, index, regex).unwrap() {
+                FsReadResult::Section {
+                    content,
+                    start_line,
+                    total_sections,
+                    ..
+                } => (content, start_line, total_sections),
+                _ => panic!("Expected Section result"),
+            }
+        };
+
+        assert_eq!(section("---", 0, false), ("a: 1".to_string(), 2, 4));
+        assert_eq!(section("---", 1, false), ("b: 2\nc: 3".to_string(), 4, 4));
+        // Back-to-back separators leave an empty section between them
+        assert_eq!(section("---", 2, false), (String::new(), 7, 4));
+        assert_eq!(section("^-+\\s*$", 3, true), ("d: 4".to_string(), 8, 4));
+
+        let err = read_section(&file_path, "---", 4, false).unwrap_err();
+        assert!(matches!(err, FsReadError::SectionOutOfRange(4, 4)));
+    }
+
+    #[test]
+    fn test_whitespace_lint() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("lint.py");
+
+        let lint = |content: &str| {
+            fs::write(&file_path, content).unwrap();
+            match whitespace_lint(&file_path).unwrap() {
+                FsReadResult::WhitespaceLint {
+                    trailing_ws_lines,
+                    mixed_indent,
+                    missing_final_newline,
+                    tabs_in_indent_lines,
+                } => (trailing_ws_lines, mixed_indent, missing_final_newline, tabs_in_indent_lines),
+                _ => panic!("Expected WhitespaceLint result"),
+            }
+        };
+
+        assert_eq!(lint("def f():\n    pass\n"), (vec![], false, false, vec![]));
+        assert_eq!(
+            lint("def f(): \r\n\tx = 1\n    y = 2\t\n  \n\t z"),
+            (vec![1, 3, 4], true, true, vec![2, 5])
+        );
+        assert_eq!(lint(""), (vec![], false, false, vec![]));
+    }
+
+    #[test]
+    fn test_read_line_numbers() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("test.txt");
+        fs::write(&path, "one\ntwo\r\nthree\nfour").unwrap();
+
+        let result = read_line_numbers(&path, &[4, 2, 9, 2, 0], false).unwrap();
+
+        if let FsReadResult::Lines { lines, out_of_range, .. } = result {
+            let got: Vec<(usize, &str)> =
+                lines.iter().map(|l| (l.line_number, l.content.as_str())).collect();
+            assert_eq!(got, vec![(2, "two"), (4, "four")]);
+            assert_eq!(out_of_range, vec![0, 9]);
+        } else {
+            panic!("Expected Lines result");
+        }
+
+        if let FsReadResult::Lines { lines, out_of_range, .. } =
+            read_line_numbers(&path, &[2, 3, 4, 9], true).unwrap()
+        {
+            let numbers: Vec<usize> = lines.iter().map(|l| l.line_number).collect();
+            assert_eq!(numbers, vec![1, 2, 3]);
+            assert_eq!(lines[0].content, "two");
+            assert_eq!(out_of_range, vec![9]);
+        } else {
+            panic!("Expected Lines result");
+        }
+    }
 
     #[test]
     fn test_resolve_line_range_positive() {
         assert_eq!(resolve_line_range(Some(0), Some(10), 100).unwrap(), (0, 10));
-        assert_eq!(resolve_line_range(Some(5), Some(15), 100).unwrap(), (5, 15));
+        assert_eq!(resolve_line_range(Some(5), Some(15), 100).unwrap(), (4, 15));
     }
 
     #[test]
@@ -106,4 +1219,275 @@ mod tests {
         assert_eq!(resolve_line_range(None, None, 100).unwrap(), (0, 100));
         assert_eq!(resolve_line_range(None, Some(50), 100).unwrap(), (0, 50));
     }
+
+    #[test]
+    fn test_read_lines_reuses_buffer_between_files() {
+        let temp = TempDir::new().unwrap();
+        let long = temp.path().join("long.txt");
+        let short = temp.path().join("short.txt");
+        fs::write(&long, "a\nb\nc\nd\ne\n").unwrap();
+        fs::write(&short, "x\ny").unwrap();
+
+        read_lines(&long, &LineOptions::default()).unwrap();
+        let result = read_lines(&short, &LineOptions::default()).unwrap();
+
+        if let FsReadResult::Line { content, total_lines, .. } = result {
+            assert_eq!(content, "x\ny");
+            assert_eq!(total_lines, 2);
+        } else {
+            panic!("Expected Line result");
+        }
+    }
+
+    #[test]
+    fn test_read_lines_max_line_bytes() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("bundle.js");
+        fs::write(&file_path, "short\nvar a=1;var b=2;var c=3;\nhé").unwrap();
+
+        let options = LineOptions {
+            max_line_bytes: Some(3),
+            ..Default::default()
+        };
+        let result = read_lines(&file_path, &options).unwrap();
+
+        if let FsReadResult::Line { content, truncated_lines, .. } = result {
+            assert_eq!(content, "sho…[truncated]\nvar…[truncated]\nhé");
+            assert_eq!(truncated_lines, 2);
+        } else {
+            panic!("Expected Line result");
+        }
+
+        assert_eq!(truncate_line("héllo", Some(2)), "h…[truncated]");
+    }
+
+    #[test]
+    fn test_read_lines_filter() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("notes.txt");
+        fs::write(&file_path, "a\n\nlonger line\n   \nTODO: x\nlong line three\n").unwrap();
+
+        let read = |filter, start_line, end_line| {
+            let options = LineOptions {
+                start_line,
+                end_line,
+                filter: Some(filter),
+                ..Default::default()
+            };
+            match read_lines(&file_path, &options).unwrap() {
+                FsReadResult::Line {
+                    content,
+                    lines_returned,
+                    line_numbers,
+                    ..
+                } => {
+                    assert_eq!(lines_returned, line_numbers.len());
+                    (content, line_numbers)
+                }
+                _ => panic!("Expected Line result"),
+            }
+        };
+
+        assert_eq!(
+            read(LineFilter::NonBlank, Some(2), None),
+            ("longer line\nTODO: x\nlong line three".to_string(), vec![3, 5, 6])
+        );
+        // A range anchored at the start takes the streaming path
+        assert_eq!(
+            read(LineFilter::MinLength(7), Some(1), Some(5)),
+            ("longer line\nTODO: x".to_string(), vec![3, 5])
+        );
+        assert_eq!(
+            read(LineFilter::Regex("^l".to_string()), None, None),
+            ("longer line\nlong line three".to_string(), vec![3, 6])
+        );
+
+        let options = LineOptions {
+            filter: Some(LineFilter::Regex("(".to_string())),
+            ..Default::default()
+        };
+        assert!(matches!(read_lines(&file_path, &options), Err(FsReadError::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn test_read_lines_normalize_line_endings() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("mixed.txt");
+        fs::write(&file_path, "a\r\nb\r\nc\rd\n").unwrap();
+
+        let read = |ending| {
+            let options = LineOptions {
+                normalize_line_endings: ending,
+                ..Default::default()
+            };
+            match read_lines(&file_path, &options).unwrap() {
+                FsReadResult::Line {
+                    content,
+                    original_line_ending,
+                    line_endings_normalized,
+                    ..
+                } => (content, original_line_ending, line_endings_normalized),
+                _ => panic!("Expected Line result"),
+            }
+        };
+
+        assert_eq!(
+            read(Some(LineEnding::Lf)),
+            ("a\nb\nc\nd".to_string(), Some(LineEnding::CrLf), true)
+        );
+        assert_eq!(
+            read(Some(LineEnding::CrLf)),
+            ("a\r\nb\r\nc\r\nd".to_string(), Some(LineEnding::CrLf), true)
+        );
+        // Without normalization a bare `\r` is left inside its line
+        assert_eq!(read(None), ("a\nb\nc\rd".to_string(), None, false));
+
+        fs::write(&file_path, "a\nb\n").unwrap();
+        assert_eq!(read(Some(LineEnding::Lf)), ("a\nb".to_string(), Some(LineEnding::Lf), false));
+        // Only the returned lines count: line 2 onwards is all LF
+        let options = LineOptions {
+            start_line: Some(2),
+            normalize_line_endings: Some(LineEnding::Lf),
+            ..Default::default()
+        };
+        fs::write(&file_path, "a\r\nb\nc\n").unwrap();
+        match read_lines(&file_path, &options).unwrap() {
+            FsReadResult::Line {
+                original_line_ending,
+                line_endings_normalized,
+                ..
+            } => {
+                assert_eq!(original_line_ending, Some(LineEnding::Lf));
+                assert!(!line_endings_normalized);
+            }
+            _ => panic!("Expected Line result"),
+        }
+    }
+
+    #[test]
+    fn test_read_lines_max_output_bytes_cr() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("cr.txt");
+        let content: Vec<String> = (0..100).map(|i| format!("line number {}", i)).collect();
+        fs::write(&file_path, content.join("\n")).unwrap();
+
+        let options = LineOptions {
+            normalize_line_endings: Some(LineEnding::Cr),
+            max_output_bytes: Some(400),
+            ..Default::default()
+        };
+        let result = read_lines(&file_path, &options).unwrap();
+        assert!(serde_json::to_string(&result).unwrap().len() <= 400);
+
+        if let FsReadResult::Line {
+            content,
+            lines_returned,
+            truncated,
+            omitted_count,
+            ..
+        } = result
+        {
+            assert!(truncated);
+            assert!(lines_returned > 0);
+            assert_eq!(lines_returned + omitted_count, 100);
+            assert_eq!(content.split('\r').count(), lines_returned);
+            assert!(content.starts_with("line number 0\rline number 1\r"));
+        } else {
+            panic!("Expected Line result");
+        }
+    }
+
+    #[test]
+    fn test_expand_tabs() {
+        assert_eq!(expand_tabs("\tx\ty", 4, false), (Cow::Borrowed("    x\ty"), 1));
+        // A tab after two columns only reaches the next stop
+        assert_eq!(expand_tabs("  \tx", 4, false).0, "    x");
+        assert_eq!(expand_tabs("ab\tc\t\td", 4, true), (Cow::Borrowed("ab  c       d"), 3));
+        assert_eq!(expand_tabs("é\tx", 4, true).0, "é   x");
+        assert_eq!(expand_tabs("\t\tx", 0, false).0, "x");
+        assert_eq!(expand_tabs("x\ty", 4, false), (Cow::Borrowed("x\ty"), 0));
+    }
+
+    #[test]
+    fn test_read_lines_encodings() {
+        let temp = TempDir::new().unwrap();
+        let read = |name: &str, bytes: &[u8], end_line| {
+            let path = temp.path().join(name);
+            fs::write(&path, bytes).unwrap();
+            let options = LineOptions {
+                end_line,
+                ..Default::default()
+            };
+            match read_lines(&path, &options).unwrap() {
+                FsReadResult::Line { content, detected_encoding, .. } => {
+                    (content, detected_encoding)
+                }
+                _ => panic!("Expected Line result"),
+            }
+        };
+        let expected = |encoding: &str| ("héllo\nwörld".to_string(), encoding.to_string());
+
+        // The BOM is dropped on the full read and the leading-lines fast path
+        let bom = "\u{FEFF}héllo\nwörld\n".as_bytes();
+        assert_eq!(read("bom.txt", bom, None), expected("UTF-8-BOM"));
+        assert_eq!(read("bom.txt", bom, Some(2)), expected("UTF-8-BOM"));
+
+        let utf16: Vec<u8> = "\u{FEFF}héllo\nwörld\n"
+            .encode_utf16()
+            .flat_map(u16::to_le_bytes)
+            .collect();
+        assert_eq!(read("utf16.txt", &utf16, Some(2)), expected("UTF-16LE"));
+
+        let latin1 = b"h\xE9llo\nw\xF6rld\n";
+        assert_eq!(read("latin1.txt", latin1, None), expected("Latin-1"));
+    }
+
+    #[test]
+    fn test_read_leading_lines_keeps_total() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("script");
+        fs::write(&file_path, "#!/usr/bin/env python3\r\nprint(1)\n\nprint(2)").unwrap();
+
+        let options = LineOptions {
+            start_line: Some(1),
+            end_line: Some(1),
+            detect_shebang: true,
+            ..Default::default()
+        };
+        let result = read_lines(&file_path, &options).unwrap();
+
+        if let FsReadResult::Line {
+            content,
+            total_lines,
+            byte_size,
+            detected_encoding,
+            interpreter,
+            ..
+        } = result
+        {
+            assert_eq!(content, "#!/usr/bin/env python3");
+            assert_eq!(total_lines, 4);
+            assert_eq!(byte_size, 42);
+            assert_eq!(detected_encoding, "UTF-8");
+            assert_eq!(interpreter.as_deref(), Some("python3"));
+        } else {
+            panic!("Expected Line result");
+        }
+
+        let options = LineOptions {
+            start_line: Some(4),
+            end_line: Some(2),
+            ..Default::default()
+        };
+        assert!(read_lines(&file_path, &options).is_err());
+    }
+
+    #[test]
+    fn test_parse_shebang() {
+        assert_eq!(parse_shebang("#!/bin/bash").as_deref(), Some("bash"));
+        assert_eq!(parse_shebang("#! /usr/bin/python3 -u").as_deref(), Some("python3"));
+        assert_eq!(parse_shebang("#!/usr/bin/env -S deno run").as_deref(), Some("deno"));
+        assert_eq!(parse_shebang("#!/usr/bin/env A=1 node").as_deref(), Some("node"));
+        assert_eq!(parse_shebang("# comment"), None);
+    }
 }
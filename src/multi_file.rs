@@ -0,0 +1,191 @@
+use crate::line::{read_lines, LineOptions};
+use crate::types::{FileContent, FsReadError, FsReadResult, FsWarning};
+use std::num::NonZeroUsize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+/// Reads each of `paths` (relative ones resolved against `base`) on up to
+/// `max_threads` threads (default: available parallelism), keeping the input
+/// order. A failing file records its error, and a matching warning, instead
+/// of aborting the whole bundle. When a separator is given, the successfully
+/// read contents are also joined with it.
+pub fn read_files(
+    base: &Path,
+    paths: &[PathBuf],
+    separator: Option<&str>,
+    max_threads: Option<usize>,
+) -> Result<FsReadResult, FsReadError> {
+    let mut warnings = Vec::new();
+    let files: Vec<FileContent> = read_parallel(base, paths, max_threads)
+        .into_iter()
+        .zip(paths)
+        .map(|(result, p)| {
+            result.unwrap_or_else(|e| {
+                let path = p.to_string_lossy().to_string();
+                warnings.push(FsWarning::from_error(path.clone(), &e));
+                FileContent {
+                    path,
+                    content: String::new(),
+                    total_lines: 0,
+                    error: Some(e.to_string()),
+                }
+            })
+        })
+        .collect();
+
+    let combined = separator.map(|sep| {
+        files
+            .iter()
+            .filter(|f| f.error.is_none())
+            .map(|f| f.content.as_str())
+            .collect::<Vec<_>>()
+            .join(sep)
+    });
+
+    Ok(FsReadResult::MultiFile {
+        files,
+        combined,
+        warnings,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// Workers pull the next index from a shared counter so a few large files
+/// don't leave the other threads idle; results are put back in input order.
+fn read_parallel(
+    base: &Path,
+    paths: &[PathBuf],
+    max_threads: Option<usize>,
+) -> Vec<Result<FileContent, FsReadError>> {
+    let threads = max_threads
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get))
+        .clamp(1, paths.len().max(1));
+
+    if threads == 1 {
+        return paths.iter().map(|p| read_one(base, p)).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut indexed: Vec<(usize, Result<FileContent, FsReadError>)> = thread::scope(|scope| {
+        let workers: Vec<_> = (0..threads)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(path) = paths.get(i) else { break };
+                        done.push((i, read_one(base, path)));
+                    }
+                    done
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .flat_map(|w| w.join().expect("multi-file reader thread panicked"))
+            .collect()
+    });
+
+    indexed.sort_unstable_by_key(|(i, _)| *i);
+    indexed.into_iter().map(|(_, result)| result).collect()
+}
+
+fn read_one(base: &Path, path: &Path) -> Result<FileContent, FsReadError> {
+    match read_lines(&base.join(path), &LineOptions::default())? {
+        FsReadResult::Line { content, total_lines, .. } => Ok(FileContent {
+            path: path.to_string_lossy().to_string(),
+            content,
+            total_lines,
+            error: None,
+        }),
+        _ => unreachable!("read_lines always returns a Line result"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_read_files_partial_failure() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.txt"), "alpha").unwrap();
+        fs::write(temp.path().join("b.txt"), "beta\ngamma").unwrap();
+
+        let paths = vec![
+            PathBuf::from("a.txt"),
+            PathBuf::from("missing.txt"),
+            PathBuf::from("b.txt"),
+        ];
+        let result = read_files(temp.path(), &paths, Some("\n---\n"), None).unwrap();
+
+        if let FsReadResult::MultiFile { files, combined, warnings, .. } = result {
+            assert_eq!(files.len(), 3);
+            assert_eq!(files[0].content, "alpha");
+            assert!(files[1].error.is_some());
+            assert_eq!(warnings.len(), 1);
+            assert_eq!(warnings[0].path, "missing.txt");
+            assert_eq!(warnings[0].kind, crate::types::WarningKind::NotFound);
+            assert_eq!(files[2].total_lines, 2);
+            assert_eq!(combined.unwrap(), "alpha\n---\nbeta\ngamma");
+        } else {
+            panic!("Expected MultiFile result");
+        }
+    }
+
+    #[test]
+    fn test_read_files_parallel_keeps_order() {
+        let temp = TempDir::new().unwrap();
+        let mut paths = Vec::new();
+        for i in 0..40 {
+            let name = format!("f{}.txt", i);
+            // Every seventh file is missing so errors must land on the right path
+            if i % 7 != 0 {
+                fs::write(temp.path().join(&name), "x\n".repeat(i)).unwrap();
+            }
+            paths.push(PathBuf::from(name));
+        }
+
+        let result = read_files(temp.path(), &paths, None, Some(4)).unwrap();
+
+        if let FsReadResult::MultiFile { files, .. } = result {
+            for (i, file) in files.iter().enumerate() {
+                assert_eq!(file.path, format!("f{}.txt", i));
+                assert_eq!(file.error.is_some(), i % 7 == 0);
+                if i % 7 != 0 {
+                    assert_eq!(file.total_lines, i);
+                }
+            }
+        } else {
+            panic!("Expected MultiFile result");
+        }
+    }
+
+    #[test]
+    fn test_read_files_many_small_files() {
+        let temp = TempDir::new().unwrap();
+        let paths: Vec<PathBuf> = (0..500)
+            .map(|i| {
+                let name = format!("f{}.txt", i);
+                fs::write(temp.path().join(&name), format!("file {}\n", i)).unwrap();
+                PathBuf::from(name)
+            })
+            .collect();
+
+        let contents = |max_threads| match read_files(temp.path(), &paths, None, max_threads) {
+            Ok(FsReadResult::MultiFile { files, .. }) => {
+                files.into_iter().map(|f| f.content).collect::<Vec<_>>()
+            }
+            _ => panic!("Expected MultiFile result"),
+        };
+        let serial = contents(Some(1));
+        assert_eq!(serial.len(), 500);
+        assert_eq!(serial[499], "file 499");
+        assert_eq!(contents(None), serial);
+    }
+}
@@ -0,0 +1,788 @@
+use crate::types::{FsReadError, FsReadResult};
+use serde::Serialize;
+
+/// Trims a result from the end until it serializes to at most `max_bytes`,
+/// and records how many items were dropped in its `truncated` and
+/// `omitted_count` fields. Text results lose whole lines, list results
+/// whole items, grouped search results whole files and Base64 whole 3-byte
+/// groups. Line results are trimmed as they're read, by
+/// `trim_joined_lines`, since their terminator can be any
+/// `normalize_line_endings` style. Counts such as `total_lines`, `total_count` and `total_matches`
+/// keep describing the full result. A result that can't be trimmed, or
+/// that is still too large once its content or list is empty, fails with
+/// `OutputTooLarge` rather than exceeding the limit.
+pub fn limit_output(
+    mut result: FsReadResult,
+    max_bytes: usize,
+) -> Result<FsReadResult, FsReadError> {
+    if serialized_len(&result) <= max_bytes {
+        return Ok(result);
+    }
+
+    match result {
+        FsReadResult::After { .. }
+        | FsReadResult::Section { .. }
+        | FsReadResult::StripComments { .. }
+        | FsReadResult::Skeleton { .. }
+        | FsReadResult::GitShow { .. } => trim_lines(&mut result, max_bytes),
+        FsReadResult::Base64 { .. } => trim_base64(&mut result, max_bytes),
+        FsReadResult::MultiFile { .. } => trim_multi_file(&mut result, max_bytes),
+        FsReadResult::Directory { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::Directory { entries, .. } => Some(entries),
+            _ => None,
+        }),
+        FsReadResult::Search { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::Search { matches, .. } => Some(matches),
+            _ => None,
+        }),
+        FsReadResult::SearchGrouped { .. } => trim_items(
+            &mut result,
+            max_bytes,
+            |r| match r {
+                FsReadResult::SearchGrouped { files, .. } => Some(files),
+                _ => None,
+            },
+            |file| file.matches.len(),
+        ),
+        FsReadResult::Imports { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::Imports { imports, .. } => Some(imports),
+            _ => None,
+        }),
+        FsReadResult::Groups { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::Groups { groups, .. } => Some(groups),
+            _ => None,
+        }),
+        FsReadResult::LanguageBreakdown { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::LanguageBreakdown { languages, .. } => Some(languages),
+            _ => None,
+        }),
+        FsReadResult::DirTreemap { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::DirTreemap { nodes, .. } => Some(nodes),
+            _ => None,
+        }),
+        FsReadResult::HashManifest { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::HashManifest { entries, .. } => Some(entries),
+            _ => None,
+        }),
+        FsReadResult::LargestFiles { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::LargestFiles { files, .. } => Some(files),
+            _ => None,
+        }),
+        FsReadResult::NewerThan { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::NewerThan { entries, .. } => Some(entries),
+            _ => None,
+        }),
+        FsReadResult::KeyValues { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::KeyValues { pairs, .. } => Some(pairs),
+            _ => None,
+        }),
+        FsReadResult::Lines { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::Lines { lines, .. } => Some(lines),
+            _ => None,
+        }),
+        FsReadResult::MatchRatio { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::MatchRatio { per_file, .. } => Some(per_file),
+            _ => None,
+        }),
+        FsReadResult::Extract { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::Extract { rows, .. } => Some(rows),
+            _ => None,
+        }),
+        FsReadResult::FileSet { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::FileSet { files, .. } => Some(files),
+            _ => None,
+        }),
+        FsReadResult::FuzzyFind { .. } => trim_list(&mut result, max_bytes, |r| match r {
+            FsReadResult::FuzzyFind { matches, .. } => Some(matches),
+            _ => None,
+        }),
+        _ => {}
+    }
+
+    match serialized_len(&result) {
+        size if size > max_bytes => Err(FsReadError::OutputTooLarge(size, max_bytes)),
+        _ => Ok(result),
+    }
+}
+
+/// The text a line-trimmed result carries.
+fn text_content(result: &mut FsReadResult) -> Option<&mut String> {
+    match result {
+        FsReadResult::After { content, .. }
+        | FsReadResult::Section { content, .. }
+        | FsReadResult::StripComments { content, .. }
+        | FsReadResult::Skeleton { content, .. }
+        | FsReadResult::GitShow { content, .. } => Some(content),
+        _ => None,
+    }
+}
+
+/// Keeps the leading lines of the result's text that fit. A final newline
+/// is kept only when the full text ended with one.
+fn trim_lines(result: &mut FsReadResult, max_bytes: usize) {
+    let full = match text_content(result) {
+        Some(content) => std::mem::take(content),
+        None => return,
+    };
+    let lines: Vec<&str> = full.split_inclusive('\n').collect();
+
+    // Mark with the worst-case count first so the digits are accounted for
+    mark_omitted(result, lines.len());
+    let base = serialized_len(result);
+    // Each line's quotes are dropped; its escaped terminator stays with it
+    let sizes = lines.iter().map(|l| serialized_len(l) - 2);
+    let keep = fitting_prefix(base, sizes, 0, max_bytes);
+    mark_omitted(result, lines.len() - keep);
+
+    let mut kept = lines[..keep].concat();
+    if keep < lines.len() && kept.ends_with('\n') {
+        kept.pop();
+    }
+    if let Some(content) = text_content(result) {
+        *content = kept;
+    }
+    match result {
+        FsReadResult::After { lines_returned, .. }
+        | FsReadResult::Section { lines_returned, .. }
+        | FsReadResult::GitShow { lines_returned, .. } => *lines_returned = keep,
+        _ => {}
+    }
+}
+
+/// Sets a Line result's content to the leading `lines` that fit, joined
+/// with `separator`.
+pub(crate) fn trim_joined_lines(
+    result: &mut FsReadResult,
+    lines: &[String],
+    separator: &str,
+    max_bytes: usize,
+) {
+    if serialized_len(result) <= max_bytes {
+        return;
+    }
+    let FsReadResult::Line { content, .. } = result else {
+        return;
+    };
+    content.clear();
+
+    mark_omitted(result, lines.len());
+    let base = serialized_len(result);
+    let sizes = lines.iter().map(|l| serialized_len(l) - 2);
+    let keep = fitting_prefix(base, sizes, serialized_len(separator) - 2, max_bytes);
+    mark_omitted(result, lines.len() - keep);
+
+    if let FsReadResult::Line {
+        content,
+        lines_returned,
+        line_numbers,
+        ..
+    } = result
+    {
+        *content = lines[..keep].join(separator);
+        *lines_returned = keep;
+        line_numbers.truncate(keep);
+    }
+}
+
+/// Keeps whole 4-character base64 groups, so the kept prefix still decodes
+/// to the first `bytes_read` bytes.
+fn trim_base64(result: &mut FsReadResult, max_bytes: usize) {
+    let FsReadResult::Base64 {
+        content_b64,
+        truncated,
+        ..
+    } = result
+    else {
+        return;
+    };
+    let full = std::mem::take(content_b64);
+    *truncated = true;
+
+    let room = max_bytes.saturating_sub(serialized_len(result));
+    let FsReadResult::Base64 {
+        content_b64,
+        bytes_read,
+        ..
+    } = result
+    else {
+        return;
+    };
+    let groups = (room / 4).min(full.len() / 4);
+    *bytes_read = (*bytes_read).min(groups as u64 * 3);
+    *content_b64 = full[..groups * 4].to_string();
+}
+
+/// Drops whole files from the end, along with their share of `combined`.
+fn trim_multi_file(result: &mut FsReadResult, max_bytes: usize) {
+    let FsReadResult::MultiFile { files, combined, .. } = result else {
+        return;
+    };
+    let combined_full = combined.take();
+    let all = std::mem::take(files);
+    let read: Vec<usize> = all
+        .iter()
+        .filter(|f| f.error.is_none())
+        .map(|f| f.content.len())
+        .collect();
+    // `combined` is the read contents joined with one separator
+    let separator_len = match (&combined_full, read.len()) {
+        (Some(text), n) if n > 1 => (text.len() - read.iter().sum::<usize>()) / (n - 1),
+        _ => 0,
+    };
+
+    mark_omitted(result, all.len());
+    let base = serialized_len(result) + if combined_full.is_some() { 2 } else { 0 };
+    let sizes = all.iter().map(|file| {
+        let in_combined = match (&combined_full, &file.error) {
+            (Some(_), None) => serialized_len(&file.content) - 2 + separator_len,
+            _ => 0,
+        };
+        serialized_len(file) + in_combined
+    });
+    let keep = fitting_prefix(base, sizes, 1, max_bytes);
+    mark_omitted(result, all.len() - keep);
+
+    let kept_read = all[..keep].iter().filter(|f| f.error.is_none()).count();
+    let combined_len = read[..kept_read].iter().sum::<usize>()
+        + separator_len * kept_read.saturating_sub(1);
+    if let FsReadResult::MultiFile { files, combined, .. } = result {
+        *combined = combined_full.map(|text| text[..combined_len].to_string());
+        *files = all;
+        files.truncate(keep);
+    }
+}
+
+fn trim_list<T: Serialize>(
+    result: &mut FsReadResult,
+    max_bytes: usize,
+    slot: fn(&mut FsReadResult) -> Option<&mut Vec<T>>,
+) {
+    trim_items(result, max_bytes, slot, |_| 1)
+}
+
+fn trim_items<T: Serialize>(
+    result: &mut FsReadResult,
+    max_bytes: usize,
+    slot: fn(&mut FsReadResult) -> Option<&mut Vec<T>>,
+    weight: fn(&T) -> usize,
+) {
+    let mut items = match slot(result) {
+        Some(items) => std::mem::take(items),
+        None => return,
+    };
+    let total: usize = items.iter().map(weight).sum();
+
+    mark_omitted(result, total);
+    let base = serialized_len(result);
+    let keep = fitting_prefix(base, items.iter().map(serialized_len), 1, max_bytes);
+    let omitted: usize = items[keep..].iter().map(weight).sum();
+    mark_omitted(result, omitted);
+
+    items.truncate(keep);
+    if let Some(slot) = slot(result) {
+        *slot = items;
+    }
+}
+
+fn mark_omitted(result: &mut FsReadResult, count: usize) {
+    match result {
+        FsReadResult::Line {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::Directory {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::Search {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::SearchGrouped {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::After {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::Section {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::StripComments {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::Skeleton {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::GitShow {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::MultiFile {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::Imports {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::Groups {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::LanguageBreakdown {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::DirTreemap {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::HashManifest {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::LargestFiles {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::NewerThan {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::KeyValues {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::Lines {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::MatchRatio {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::Extract {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::FileSet {
+            truncated,
+            omitted_count,
+            ..
+        }
+        | FsReadResult::FuzzyFind {
+            truncated,
+            omitted_count,
+            ..
+        } => {
+            *truncated = true;
+            *omitted_count = count;
+        }
+        _ => {}
+    }
+}
+
+fn serialized_len<T: Serialize + ?Sized>(value: &T) -> usize {
+    serde_json::to_vec(value).map(|v| v.len()).unwrap_or(0)
+}
+
+/// Largest `k` such that `base` plus the first `k` item sizes and the
+/// separators between them stays within `max_bytes`.
+fn fitting_prefix(
+    base: usize,
+    item_sizes: impl Iterator<Item = usize>,
+    separator: usize,
+    max_bytes: usize,
+) -> usize {
+    let mut total = base;
+    let mut keep = 0;
+
+    for (i, size) in item_sizes.enumerate() {
+        total += size + if i > 0 { separator } else { 0 };
+        if total > max_bytes {
+            break;
+        }
+        keep += 1;
+    }
+
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FileContent, FsReadMode, FsReadParams, NumberedLine};
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_limit_output_line() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("big.txt");
+        let content: Vec<String> = (0..100).map(|i| format!("line number {}", i)).collect();
+        fs::write(&path, content.join("\n")).unwrap();
+
+        let params = FsReadParams {
+            path,
+            mode: FsReadMode::Line {
+                start_line: None,
+                end_line: None,
+                max_line_bytes: None,
+                detect_shebang: false,
+                collapse_blank_lines: false,
+                trim_trailing_whitespace: false,
+                include_hash: None,
+                filter: None,
+                normalize_line_endings: None,
+                expand_tabs: None,
+                expand_all_tabs: false,
+            },
+            max_output_bytes: Some(400),
+            redact_paths: None,
+        };
+        let result = crate::fs_read(params).unwrap();
+        let json = serde_json::to_string(&result).unwrap();
+        assert!(json.len() <= 400);
+
+        if let FsReadResult::Line {
+            content,
+            lines_returned,
+            total_lines,
+            truncated,
+            omitted_count,
+            ..
+        } = result
+        {
+            assert!(truncated);
+            assert_eq!(total_lines, 100);
+            assert!(lines_returned > 0);
+            assert_eq!(lines_returned + omitted_count, 100);
+            assert!(content.starts_with("line number 0\n"));
+        } else {
+            panic!("Expected Line result");
+        }
+    }
+
+    #[test]
+    fn test_limit_output_directory() {
+        let temp = TempDir::new().unwrap();
+        for i in 0..20 {
+            fs::write(temp.path().join(format!("file{}.txt", i)), "x").unwrap();
+        }
+
+        let params = FsReadParams {
+            path: temp.path().to_path_buf(),
+            mode: FsReadMode::Directory {
+                depth: 0,
+                resolve_root: true,
+                git_status: false,
+                include_hidden: false,
+                extra_ignores: Vec::new(),
+                prune_empty_dirs: false,
+                ignore_file: None,
+                exclude_globs: Vec::new(),
+                parent_gitignores: false,
+                gitignore_boundary: None,
+                skip_generated: false,
+                entries_kind: Default::default(),
+                normalize_separators: true,
+                text_only: false,
+                max_entries: None,
+            },
+            max_output_bytes: Some(300),
+            redact_paths: None,
+        };
+        let result = crate::fs_read(params).unwrap();
+        assert!(serde_json::to_string(&result).unwrap().len() <= 300);
+
+        if let FsReadResult::Directory {
+            entries,
+            total_count,
+            truncated,
+            omitted_count,
+            ..
+        } = result
+        {
+            assert!(truncated);
+            assert_eq!(total_count, 20);
+            assert_eq!(entries.len() + omitted_count, 20);
+        } else {
+            panic!("Expected Directory result");
+        }
+    }
+
+    #[test]
+    fn test_limit_output_text_results() {
+        let content: String = (0..50).map(|i| format!("line number {}\n", i)).collect();
+        let results = vec![
+            FsReadResult::After {
+                content: content.clone(),
+                total_lines: 50,
+                lines_returned: 50,
+                found: true,
+                delimiter_line: None,
+                truncated: false,
+                omitted_count: 0,
+            },
+            FsReadResult::Section {
+                content: content.clone(),
+                start_line: 1,
+                lines_returned: 50,
+                total_sections: 1,
+                truncated: false,
+                omitted_count: 0,
+            },
+            FsReadResult::StripComments {
+                content: content.clone(),
+                total_lines: 50,
+                removed_comment_lines: 0,
+                truncated: false,
+                omitted_count: 0,
+            },
+            FsReadResult::Skeleton {
+                content: content.clone(),
+                total_lines: 50,
+                elided_lines: 0,
+                truncated: false,
+                omitted_count: 0,
+            },
+            FsReadResult::GitShow {
+                content: content.clone(),
+                total_lines: 50,
+                lines_returned: 50,
+                commit: "HEAD".to_string(),
+                truncated: false,
+                omitted_count: 0,
+            },
+        ];
+
+        for result in results {
+            let limited = limit_output(result, 300).unwrap();
+            assert!(serde_json::to_string(&limited).unwrap().len() <= 300);
+
+            let (content, omitted) = match &limited {
+                FsReadResult::After {
+                    content,
+                    lines_returned,
+                    omitted_count,
+                    ..
+                }
+                | FsReadResult::Section {
+                    content,
+                    lines_returned,
+                    omitted_count,
+                    ..
+                }
+                | FsReadResult::GitShow {
+                    content,
+                    lines_returned,
+                    omitted_count,
+                    ..
+                } => {
+                    assert_eq!(lines_returned + omitted_count, 50);
+                    (content, *omitted_count)
+                }
+                FsReadResult::StripComments {
+                    content,
+                    omitted_count,
+                    ..
+                }
+                | FsReadResult::Skeleton {
+                    content,
+                    omitted_count,
+                    ..
+                } => (content, *omitted_count),
+                _ => panic!("Expected a text result"),
+            };
+            assert!(omitted > 0);
+            assert!(content.starts_with("line number 0\n"));
+            assert!(!content.ends_with('\n'));
+            assert_eq!(content.lines().count() + omitted, 50);
+        }
+    }
+
+    #[test]
+    fn test_limit_output_lists() {
+        let names: Vec<String> = (0..50).map(|i| format!("src/file{}.rs", i)).collect();
+        let pairs: Vec<(String, String)> =
+            names.iter().map(|n| (n.clone(), "0".repeat(16))).collect();
+        let results = vec![
+            FsReadResult::FileSet {
+                files: names.clone(),
+                truncated: false,
+                omitted_count: 0,
+            },
+            FsReadResult::HashManifest {
+                entries: pairs.clone(),
+                skipped: Vec::new(),
+                truncated: false,
+                omitted_count: 0,
+            },
+            FsReadResult::KeyValues {
+                pairs,
+                malformed_lines: Vec::new(),
+                truncated: false,
+                omitted_count: 0,
+            },
+            FsReadResult::Lines {
+                lines: names
+                    .iter()
+                    .enumerate()
+                    .map(|(i, n)| NumberedLine {
+                        line_number: i + 1,
+                        content: n.clone(),
+                    })
+                    .collect(),
+                out_of_range: Vec::new(),
+                truncated: false,
+                omitted_count: 0,
+            },
+        ];
+
+        for result in results {
+            let limited = limit_output(result, 300).unwrap();
+            assert!(serde_json::to_string(&limited).unwrap().len() <= 300);
+
+            let (kept, truncated, omitted_count) = match limited {
+                FsReadResult::FileSet {
+                    files,
+                    truncated,
+                    omitted_count,
+                } => (files.len(), truncated, omitted_count),
+                FsReadResult::HashManifest {
+                    entries,
+                    truncated,
+                    omitted_count,
+                    ..
+                } => (entries.len(), truncated, omitted_count),
+                FsReadResult::KeyValues {
+                    pairs,
+                    truncated,
+                    omitted_count,
+                    ..
+                } => (pairs.len(), truncated, omitted_count),
+                FsReadResult::Lines {
+                    lines,
+                    truncated,
+                    omitted_count,
+                    ..
+                } => (lines.len(), truncated, omitted_count),
+                _ => panic!("Expected a list result"),
+            };
+            assert!(truncated);
+            assert!(kept > 0);
+            assert_eq!(kept + omitted_count, 50);
+        }
+    }
+
+    #[test]
+    fn test_limit_output_base64() {
+        let result = FsReadResult::Base64 {
+            content_b64: "QUJD".repeat(100),
+            bytes_read: 300,
+            truncated: false,
+        };
+        let limited = limit_output(result, 100).unwrap();
+        assert!(serde_json::to_string(&limited).unwrap().len() <= 100);
+
+        if let FsReadResult::Base64 {
+            content_b64,
+            bytes_read,
+            truncated,
+        } = limited
+        {
+            assert!(truncated);
+            assert!(!content_b64.is_empty());
+            assert_eq!(content_b64.len() % 4, 0);
+            assert_eq!(bytes_read as usize, content_b64.len() / 4 * 3);
+        } else {
+            panic!("Expected Base64 result");
+        }
+    }
+
+    #[test]
+    fn test_limit_output_multi_file() {
+        let files: Vec<FileContent> = (0..10)
+            .map(|i| FileContent {
+                path: format!("file{}.txt", i),
+                content: format!("contents of file {}", i),
+                total_lines: 1,
+                error: None,
+            })
+            .collect();
+        let combined = files
+            .iter()
+            .map(|f| f.content.as_str())
+            .collect::<Vec<_>>()
+            .join("\n---\n");
+        let result = FsReadResult::MultiFile {
+            files,
+            combined: Some(combined),
+            warnings: Vec::new(),
+            truncated: false,
+            omitted_count: 0,
+        };
+        let limited = limit_output(result, 400).unwrap();
+        assert!(serde_json::to_string(&limited).unwrap().len() <= 400);
+
+        if let FsReadResult::MultiFile {
+            files,
+            combined,
+            truncated,
+            omitted_count,
+            ..
+        } = limited
+        {
+            assert!(truncated);
+            assert!(!files.is_empty());
+            assert_eq!(files.len() + omitted_count, 10);
+            let expected = files
+                .iter()
+                .map(|f| f.content.as_str())
+                .collect::<Vec<_>>()
+                .join("\n---\n");
+            assert_eq!(combined.unwrap(), expected);
+        } else {
+            panic!("Expected MultiFile result");
+        }
+    }
+
+    #[test]
+    fn test_limit_output_too_large() {
+        let result = FsReadResult::Diff {
+            diff: "-old\n+new\n".repeat(100),
+            lines_added: 100,
+            lines_removed: 100,
+        };
+        match limit_output(result, 100) {
+            Err(FsReadError::OutputTooLarge(size, 100)) => assert!(size > 100),
+            other => panic!("Expected OutputTooLarge, got {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,279 @@
+use crate::retry;
+use crate::types::FsReadParams;
+use pyo3::prelude::*;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Why a submitted operation produced no result.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum JobError {
+    /// The operation JSON didn't parse as `FsReadParams`
+    InvalidParams(String),
+    /// The read itself failed, or its worker panicked
+    Failed(String),
+}
+
+type Outcome = Result<String, JobError>;
+
+/// Where a worker leaves a job's serialized result for whoever waits on it.
+#[derive(Default)]
+pub(crate) struct Slot {
+    outcome: Mutex<Option<Outcome>>,
+    ready: Condvar,
+}
+
+impl Slot {
+    fn set(&self, outcome: Outcome) {
+        *self.outcome.lock().unwrap() = Some(outcome);
+        self.ready.notify_all();
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.outcome.lock().unwrap().is_some()
+    }
+
+    /// Blocks until the job finishes, or `timeout` passes (`None`). A
+    /// deadline too far off to represent waits forever.
+    pub fn wait(&self, timeout: Option<Duration>) -> Option<Outcome> {
+        let deadline = timeout.and_then(|t| Instant::now().checked_add(t));
+        let mut outcome = self.outcome.lock().unwrap();
+
+        while outcome.is_none() {
+            outcome = match deadline {
+                None => self.ready.wait(outcome).unwrap(),
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        return None;
+                    }
+                    self.ready.wait_timeout(outcome, left).unwrap().0
+                }
+            };
+        }
+        outcome.clone()
+    }
+}
+
+struct Job {
+    op_json: String,
+    slot: Arc<Slot>,
+}
+
+/// Long-lived workers running `fs_read` operations as they're submitted.
+/// Jobs go through one shared queue, so a slow read only holds up its own
+/// worker.
+pub(crate) struct WorkerPool {
+    sender: Mutex<Option<Sender<Job>>>,
+    workers: Mutex<Vec<JoinHandle<()>>>,
+}
+
+impl WorkerPool {
+    /// `max_workers` defaults to the available parallelism.
+    pub fn new(max_workers: Option<usize>, max_retries: u32) -> Self {
+        let workers = max_workers
+            .unwrap_or_else(|| thread::available_parallelism().map_or(1, NonZeroUsize::get))
+            .max(1);
+        let (sender, receiver) = mpsc::channel();
+        let receiver = Arc::new(Mutex::new(receiver));
+
+        let workers = (0..workers)
+            .map(|i| {
+                let receiver = Arc::clone(&receiver);
+                thread::Builder::new()
+                    .name(format!("fs-read-pool-{}", i))
+                    .spawn(move || work(&receiver, max_retries))
+                    .expect("failed to spawn fs_read pool worker")
+            })
+            .collect();
+
+        Self {
+            sender: Mutex::new(Some(sender)),
+            workers: Mutex::new(workers),
+        }
+    }
+
+    /// Queues one operation; `None` once the pool has been shut down.
+    pub fn submit(&self, op_json: String) -> Option<Arc<Slot>> {
+        let slot = Arc::new(Slot::default());
+        let sender = self.sender.lock().unwrap();
+        let job = Job {
+            op_json,
+            slot: Arc::clone(&slot),
+        };
+        sender.as_ref()?.send(job).ok()?;
+        Some(slot)
+    }
+
+    /// Stops taking new jobs. Queued jobs still run; with `wait` this
+    /// returns only after they have.
+    pub fn shutdown(&self, wait: bool) {
+        self.sender.lock().unwrap().take();
+        if wait {
+            for worker in self.workers.lock().unwrap().drain(..) {
+                let _ = worker.join();
+            }
+        }
+    }
+}
+
+impl Drop for WorkerPool {
+    fn drop(&mut self) {
+        self.shutdown(false);
+    }
+}
+
+fn work(receiver: &Mutex<Receiver<Job>>, max_retries: u32) {
+    loop {
+        // The lock is only held while waiting for the next job
+        let job = receiver.lock().unwrap().recv();
+        let Ok(job) = job else {
+            return;
+        };
+
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| run(&job.op_json, max_retries)))
+            .unwrap_or_else(|_| Err(JobError::Failed("fs_read worker panicked".to_string())));
+        job.slot.set(outcome);
+    }
+}
+
+fn run(op_json: &str, max_retries: u32) -> Outcome {
+    let params: FsReadParams =
+        serde_json::from_str(op_json).map_err(|e| JobError::InvalidParams(e.to_string()))?;
+    let result = retry::with_default_backoff(max_retries, || crate::fs_read(params.clone()))
+        .map_err(|e| JobError::Failed(e.to_string()))?;
+    serde_json::to_string(&result).map_err(|e| JobError::Failed(e.to_string()))
+}
+
+/// Python handle for a `WorkerPool`: `submit(op_json)` returns an
+/// `FsReadFuture`, `shutdown(wait=True)` stops it. Futures block in
+/// `result()` with the GIL released, so asyncio code can await them through
+/// `loop.run_in_executor(None, future.result)`.
+#[pyclass(name = "FsReadPool")]
+pub struct PyFsReadPool {
+    pool: WorkerPool,
+}
+
+#[pymethods]
+impl PyFsReadPool {
+    #[new]
+    #[pyo3(signature = (max_workers=None, max_retries=retry::DEFAULT_MAX_RETRIES))]
+    fn new(max_workers: Option<usize>, max_retries: u32) -> Self {
+        Self {
+            pool: WorkerPool::new(max_workers, max_retries),
+        }
+    }
+
+    fn submit(&self, op_json: String) -> PyResult<FsReadFuture> {
+        match self.pool.submit(op_json) {
+            Some(slot) => Ok(FsReadFuture { slot }),
+            None => Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(
+                "FsReadPool has been shut down",
+            )),
+        }
+    }
+
+    #[pyo3(signature = (wait=true))]
+    fn shutdown(&self, py: Python<'_>, wait: bool) {
+        py.allow_threads(|| self.pool.shutdown(wait));
+    }
+}
+
+/// A Python timeout in seconds as a `Duration`; negative waits not at all,
+/// and `None` (wait forever) for infinity or anything else too large.
+fn timeout_duration(seconds: f64) -> Option<Duration> {
+    Duration::try_from_secs_f64(seconds.max(0.0)).ok()
+}
+
+/// Pending result of one `FsReadPool.submit` call.
+#[pyclass]
+pub struct FsReadFuture {
+    slot: Arc<Slot>,
+}
+
+#[pymethods]
+impl FsReadFuture {
+    fn done(&self) -> bool {
+        self.slot.is_done()
+    }
+
+    /// The result JSON, waiting up to `timeout` seconds (forever by default).
+    /// Raises `ValueError` for a malformed operation, `RuntimeError` for a
+    /// failed read and `TimeoutError` if the timeout passes first.
+    #[pyo3(signature = (timeout=None))]
+    fn result(&self, py: Python<'_>, timeout: Option<f64>) -> PyResult<String> {
+        match py.allow_threads(|| self.slot.wait(timeout.and_then(timeout_duration))) {
+            Some(Ok(json)) => Ok(json),
+            Some(Err(JobError::InvalidParams(e))) => {
+                Err(PyErr::new::<pyo3::exceptions::PyValueError, _>(e))
+            }
+            Some(Err(JobError::Failed(e))) => {
+                Err(PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e))
+            }
+            None => Err(PyErr::new::<pyo3::exceptions::PyTimeoutError, _>(
+                "FsReadFuture result not ready",
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_worker_pool() {
+        let temp = TempDir::new().unwrap();
+        let pool = WorkerPool::new(Some(2), 0);
+        let op = |name: &str| {
+            let path = temp.path().join(name);
+            format!(r#"{{"path": {:?}, "mode": "LineStats"}}"#, path)
+        };
+
+        let slots: Vec<_> = (0..8)
+            .map(|i| {
+                let name = format!("{}.txt", i);
+                fs::write(temp.path().join(&name), "x\n".repeat(i + 1)).unwrap();
+                pool.submit(op(&name)).unwrap()
+            })
+            .collect();
+        for (i, slot) in slots.iter().enumerate() {
+            let json = slot.wait(None).unwrap().unwrap();
+            assert!(json.contains(&format!(r#""total_lines":{}"#, i + 1)), "{}", json);
+            assert!(slot.is_done());
+        }
+
+        let missing = pool.submit(op("missing.txt")).unwrap();
+        assert!(matches!(missing.wait(None), Some(Err(JobError::Failed(_)))));
+        let invalid = pool.submit("{".to_string()).unwrap();
+        assert!(matches!(invalid.wait(None), Some(Err(JobError::InvalidParams(_)))));
+
+        pool.shutdown(true);
+        assert!(pool.submit(op("0.txt")).is_none());
+    }
+
+    #[test]
+    fn test_timeout_duration() {
+        assert_eq!(timeout_duration(1.5), Some(Duration::from_millis(1500)));
+        assert_eq!(timeout_duration(-2.0), Some(Duration::ZERO));
+        assert_eq!(timeout_duration(f64::INFINITY), None);
+        assert_eq!(timeout_duration(1e30), None);
+
+        let slot = Slot::default();
+        slot.set(Ok("done".to_string()));
+        assert_eq!(slot.wait(Some(Duration::MAX)), Some(Ok("done".to_string())));
+    }
+
+    #[test]
+    fn test_slot_timeout() {
+        let slot = Slot::default();
+        assert_eq!(slot.wait(Some(Duration::from_millis(5))), None);
+        slot.set(Ok("{}".to_string()));
+        assert_eq!(slot.wait(Some(Duration::ZERO)), Some(Ok("{}".to_string())));
+    }
+}
@@ -0,0 +1,207 @@
+use crate::types::{FsReadError, FsReadResult};
+use serde_json::Value;
+use std::path::Path;
+
+pub const REDACTED_ROOT: &str = "<root>";
+
+/// Fields holding paths, or messages that quote them. Only strings under
+/// these are redacted, so file content and map keys are left as read.
+const PATH_FIELDS: &[&str] = &[
+    "path",
+    "file_path",
+    "root",
+    "files",
+    "entries",
+    "capped_files",
+    "top_files",
+    "added",
+    "modified",
+    "deleted",
+    "renamed",
+    "message",
+    "error",
+];
+
+/// Fields holding data from the files themselves, whose keys may happen to
+/// match `PATH_FIELDS`
+const OPAQUE_FIELDS: &[&str] = &["shape", "groups"];
+
+/// Replaces `prefix` with `<root>` in the result's path fields (see
+/// `PATH_FIELDS`), so absolute paths in entries, matches, warnings and
+/// messages don't reveal where the files live. The prefix is matched as
+/// given, with `/` separators, and canonicalized, and only where it ends at
+/// a path boundary: `/home/al` leaves `/home/alice` alone.
+pub fn redact_paths(result: FsReadResult, prefix: &Path) -> Result<FsReadResult, FsReadError> {
+    let prefixes = prefix_forms(prefix);
+    if prefixes.is_empty() {
+        return Ok(result);
+    }
+
+    let mut value = serde_json::to_value(&result).map_err(redaction_failed)?;
+    redact_value(&mut value, &prefixes, false);
+    // Failing is safer than handing back the unredacted result
+    serde_json::from_value(value).map_err(redaction_failed)
+}
+
+fn redaction_failed(e: serde_json::Error) -> FsReadError {
+    FsReadError::Redaction(e.to_string())
+}
+
+/// Spellings of `prefix` to look for, longest first so a canonical path
+/// that extends the given one is replaced whole.
+fn prefix_forms(prefix: &Path) -> Vec<String> {
+    let given = prefix.to_string_lossy();
+    let given = given.trim_end_matches(['/', '\\']);
+    let mut forms = vec![given.to_string(), given.replace('\\', "/")];
+    if let Ok(canonical) = prefix.canonicalize() {
+        forms.push(canonical.to_string_lossy().to_string());
+    }
+
+    forms.retain(|form| !form.is_empty());
+    forms.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+    forms.dedup();
+    forms
+}
+
+/// Redacts strings under a path field; objects inside one are checked
+/// field by field again.
+fn redact_value(value: &mut Value, prefixes: &[String], in_path_field: bool) {
+    match value {
+        Value::String(s) if in_path_field => redact_str(s, prefixes),
+        Value::Array(items) => {
+            for item in items {
+                redact_value(item, prefixes, in_path_field);
+            }
+        }
+        Value::Object(fields) => {
+            for (key, field) in fields {
+                if !OPAQUE_FIELDS.contains(&key.as_str()) {
+                    redact_value(field, prefixes, PATH_FIELDS.contains(&key.as_str()));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn redact_str(s: &mut String, prefixes: &[String]) {
+    for prefix in prefixes {
+        if !s.contains(prefix.as_str()) {
+            continue;
+        }
+
+        let mut redacted = String::with_capacity(s.len());
+        let mut rest = s.as_str();
+        while let Some(at) = rest.find(prefix.as_str()) {
+            let after = &rest[at + prefix.len()..];
+            redacted.push_str(&rest[..at]);
+            if ends_path_component(after) {
+                redacted.push_str(REDACTED_ROOT);
+            } else {
+                redacted.push_str(prefix);
+            }
+            rest = after;
+        }
+        redacted.push_str(rest);
+        *s = redacted;
+    }
+}
+
+/// Whether a match followed by `after` stops where a path component does.
+fn ends_path_component(after: &str) -> bool {
+    after
+        .chars()
+        .next()
+        .is_none_or(|c| !(c.is_alphanumeric() || matches!(c, '.' | '_' | '-')))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use crate::types::{FileContent, FsReadParams};
+    use std::fs;
+
+    #[test]
+    fn test_redact_str() {
+        let prefixes = vec!["/home/al".to_string()];
+        let mut s = "/home/al/src/a.rs and /home/alice/b.rs, not /home/al".to_string();
+        redact_str(&mut s, &prefixes);
+        assert_eq!(s, "<root>/src/a.rs and /home/alice/b.rs, not <root>");
+    }
+
+    #[test]
+    fn test_redact_search_result() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.txt"), "needle\n").unwrap();
+
+        let params: FsReadParams = serde_json::from_value(serde_json::json!({
+            "path": temp.path(),
+            "mode": "Search",
+            "pattern": "needle",
+            "normalize_separators": false,
+        }))
+        .unwrap();
+        let params = FsReadParams {
+            redact_paths: Some(temp.path().to_path_buf()),
+            ..params
+        };
+
+        match crate::fs_read(params).unwrap() {
+            FsReadResult::Search { matches, .. } => {
+                let expected = Path::new(REDACTED_ROOT).join("a.txt");
+                assert_eq!(matches[0].file_path, expected.to_string_lossy());
+            }
+            _ => panic!("Expected Search result"),
+        }
+    }
+
+    #[test]
+    fn test_redact_skips_content() {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().to_string_lossy().to_string();
+
+        let line = FsReadResult::Line {
+            content: format!("see {}/a.txt", root),
+            total_lines: 1,
+            lines_returned: 1,
+            truncated_lines: 0,
+            byte_size: 0,
+            is_empty: false,
+            detected_encoding: "utf-8".to_string(),
+            interpreter: None,
+            lines_collapsed: 0,
+            content_hash: None,
+            line_numbers: Vec::new(),
+            original_line_ending: None,
+            line_endings_normalized: false,
+            tabs_expanded: 0,
+            truncated: false,
+            omitted_count: 0,
+        };
+        match redact_paths(line, temp.path()).unwrap() {
+            FsReadResult::Line { content, .. } => assert!(content.contains(&root)),
+            _ => panic!("Expected Line result"),
+        }
+
+        let files = FsReadResult::MultiFile {
+            files: vec![FileContent {
+                path: format!("{}/a.txt", root),
+                content: format!("{}/b.txt", root),
+                total_lines: 1,
+                error: None,
+            }],
+            combined: None,
+            warnings: Vec::new(),
+            truncated: false,
+            omitted_count: 0,
+        };
+        match redact_paths(files, temp.path()).unwrap() {
+            FsReadResult::MultiFile { files, .. } => {
+                assert_eq!(files[0].path, format!("{}/a.txt", REDACTED_ROOT));
+                assert_eq!(files[0].content, format!("{}/b.txt", root));
+            }
+            _ => panic!("Expected MultiFile result"),
+        }
+    }
+}
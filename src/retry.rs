@@ -0,0 +1,86 @@
+use crate::types::FsReadError;
+use std::io::ErrorKind;
+use std::thread;
+use std::time::Duration;
+
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+
+const BASE_DELAY: Duration = Duration::from_millis(10);
+
+/// Transient conditions seen on networked filesystems that tend to clear on
+/// a second attempt. Everything else, missing paths included, fails fast.
+pub(crate) fn is_retryable(error: &FsReadError) -> bool {
+    match error {
+        FsReadError::IoError(e) => matches!(
+            e.kind(),
+            ErrorKind::Interrupted | ErrorKind::WouldBlock | ErrorKind::TimedOut
+        ),
+        _ => false,
+    }
+}
+
+/// Runs `op` up to `max_retries + 1` times, doubling the delay after each
+/// retryable failure, and returns the last error once retries run out.
+pub(crate) fn with_retry<T>(
+    max_retries: u32,
+    base_delay: Duration,
+    mut op: impl FnMut() -> Result<T, FsReadError>,
+) -> Result<T, FsReadError> {
+    let mut delay = base_delay;
+    let mut attempt = 0;
+
+    loop {
+        match op() {
+            Err(e) if attempt < max_retries && is_retryable(&e) => {
+                thread::sleep(delay);
+                delay = delay.saturating_mul(2);
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+pub(crate) fn with_default_backoff<T>(
+    max_retries: u32,
+    op: impl FnMut() -> Result<T, FsReadError>,
+) -> Result<T, FsReadError> {
+    with_retry(max_retries, BASE_DELAY, op)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_with_retry() {
+        let mut calls = 0;
+        let result = with_retry(3, Duration::ZERO, || {
+            calls += 1;
+            if calls < 3 {
+                Err(FsReadError::IoError(io::Error::from(ErrorKind::TimedOut)))
+            } else {
+                Ok(calls)
+            }
+        });
+        assert_eq!(result.unwrap(), 3);
+
+        let mut calls = 0;
+        let result: Result<(), _> = with_retry(3, Duration::ZERO, || {
+            calls += 1;
+            Err(FsReadError::IoError(io::Error::from(ErrorKind::Interrupted)))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 4);
+
+        let mut calls = 0;
+        let result: Result<(), _> = with_retry(3, Duration::ZERO, || {
+            calls += 1;
+            Err(FsReadError::PathNotFound(PathBuf::from("missing")))
+        });
+        assert!(result.is_err());
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,524 @@
+use crate::language::{detect_language, Language};
+use crate::line::with_file_content;
+use crate::types::{kind_check, FsReadError, FsReadResult, PathKind, ScopeFrame};
+use regex::Regex;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::LazyLock;
+
+static RUST_FN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^\s*(?:pub(?:\([^)]*\))?\s+)?",
+        r#"(?:(?:const|async|unsafe|extern(?:\s+"[^"]*")?)\s+)*fn\s+\w+"#,
+    ))
+    .unwrap()
+});
+static GO_FN: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*func\b").unwrap());
+static JS_FN: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"\bfunction\b|=>\s*\{\s*$|",
+        r"^\s*(?:(?:public|private|protected|static|async|get|set|override)\s+)*",
+        r"(?:[\w$]+)\s*\([^;]*\)\s*(?::[^=;{]+)?\{\s*$",
+    ))
+    .unwrap()
+});
+static PYTHON_DEF: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:async\s+)?def\s").unwrap());
+
+// Named scope headers for `enclosing_scopes`; each captures `kind` and `name`
+static RUST_SCOPE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^\s*(?:pub(?:\([^)]*\))?\s+)?",
+        r#"(?:(?:const|async|unsafe|default|extern(?:\s+"[^"]*")?)\s+)*"#,
+        r"(?:(?P<kind>fn|mod|trait|struct|enum|union)\s+(?P<name>\w+)",
+        r"|(?P<impl>impl)\b(?:\s*<[^>]*>)?\s+(?P<target>[^{]+?)\s*(?:where\b.*)?\{?\s*$)",
+    ))
+    .unwrap()
+});
+static GO_SCOPE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^\s*(?:(?P<kind>func)\s+(?:\([^)]*\)\s*)?(?P<name>\w+)",
+        r"|type\s+(?P<type>\w+)\s+(?P<type_kind>struct|interface)\b)",
+    ))
+    .unwrap()
+});
+static JS_SCOPE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"^\s*(?:export\s+)?(?:default\s+)?(?:abstract\s+)?",
+        r"(?:(?P<kind>class|interface)\s+(?P<name>[\w$]+)",
+        r"|(?:async\s+)?function\s*\*?\s*(?P<function>[\w$]+)",
+        r"|(?:const|let|var)\s+(?P<arrow>[\w$]+)\s*=\s*(?:async\s+)?",
+        r"(?:\([^)]*\)|[\w$]+)\s*(?::[^=]+)?=>",
+        r"|(?:(?:public|private|protected|static|async|get|set|override)\s+)*",
+        r"(?P<method>[\w$]+)\s*\([^;]*\)\s*(?::[^=;{]+)?\{\s*$)",
+    ))
+    .unwrap()
+});
+static PYTHON_SCOPE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*(?:(?:async\s+)?(?P<def>def)|(?P<class>class))\s+(?P<name>\w+)").unwrap()
+});
+
+// Lines that look like a method header but open a control-flow block
+static JS_CONTROL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(?:\}\s*)?(?:if|for|while|switch|catch|with)\b").unwrap());
+
+/// Line range of the innermost function or method containing `line_idx`,
+/// found with per-language header patterns plus brace or indentation
+/// matching rather than a full parse. Unsupported languages yield `None`.
+pub(crate) fn enclosing_function(
+    lines: &[Cow<str>],
+    line_idx: usize,
+    language: Language,
+) -> Option<Range<usize>> {
+    match language {
+        Language::Python => python_function(lines, line_idx),
+        Language::Rust => braced_function(lines, line_idx, |l| RUST_FN.is_match(l)),
+        Language::Go => braced_function(lines, line_idx, |l| GO_FN.is_match(l)),
+        Language::JavaScript | Language::TypeScript => braced_function(lines, line_idx, |l| {
+            JS_FN.is_match(l) && !JS_CONTROL.is_match(l)
+        }),
+        _ => None,
+    }
+}
+
+/// Every named scope containing the 1-based `line`, innermost first, using
+/// the same header-and-block heuristics as `enclosing_function`. Languages
+/// without header patterns yield an empty list.
+pub fn enclosing_scope(path: &Path, line: u32) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+    let language = detect_language(path);
+
+    with_file_content(path, |content| {
+        let lines: Vec<Cow<str>> = content.lines().map(Cow::Borrowed).collect();
+        if line == 0 || line as usize > lines.len() {
+            return Err(FsReadError::InvalidLineRange(line as i32, line as i32));
+        }
+
+        let scopes = match language {
+            Some(language) => enclosing_scopes(&lines, line as usize - 1, language),
+            None => Vec::new(),
+        };
+        Ok(FsReadResult::EnclosingScope { scopes })
+    })
+}
+
+pub(crate) fn enclosing_scopes(
+    lines: &[Cow<str>],
+    line_idx: usize,
+    language: Language,
+) -> Vec<ScopeFrame> {
+    let header: fn(&str) -> Option<(&'static str, String)> = match language {
+        Language::Python => return python_scopes(lines, line_idx),
+        Language::Rust => rust_header,
+        Language::Go => go_header,
+        Language::JavaScript | Language::TypeScript => js_header,
+        _ => return Vec::new(),
+    };
+
+    // Scanning up from the line, each header whose block still reaches it
+    // encloses everything found so far
+    (0..=line_idx)
+        .rev()
+        .filter_map(|i| {
+            let (kind, name) = header(&lines[i])?;
+            let end = braced_body_end(lines, i)?;
+            (end > line_idx).then(|| ScopeFrame {
+                kind: kind.to_string(),
+                name,
+                start_line: i + 1,
+                end_line: end,
+            })
+        })
+        .collect()
+}
+
+/// The file with every multi-line function body collapsed: braced bodies
+/// to `{ ... }` on the line that opens them, Python bodies to an indented
+/// `...`. Everything else, including class, impl and trait blocks around
+/// the functions, is kept. Other languages come back unchanged.
+pub fn skeleton(path: &Path) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+    let language = detect_language(path);
+
+    with_file_content(path, |content| {
+        let lines: Vec<Cow<str>> = content.lines().map(Cow::Borrowed).collect();
+        let (kept, elided_lines) = match language {
+            Some(Language::Python) => python_skeleton(&lines),
+            Some(Language::Rust) => braced_skeleton(&lines, |l| RUST_FN.is_match(l)),
+            Some(Language::Go) => braced_skeleton(&lines, |l| GO_FN.is_match(l)),
+            Some(Language::JavaScript | Language::TypeScript) => {
+                braced_skeleton(&lines, |l| JS_FN.is_match(l) && !JS_CONTROL.is_match(l))
+            }
+            _ => (lines, 0),
+        };
+
+        Ok(FsReadResult::Skeleton {
+            content: kept.join("\n"),
+            total_lines: kept.len(),
+            elided_lines,
+            truncated: false,
+            omitted_count: 0,
+        })
+    })
+}
+
+fn braced_skeleton<'a>(
+    lines: &[Cow<'a, str>],
+    is_header: impl Fn(&str) -> bool,
+) -> (Vec<Cow<'a, str>>, usize) {
+    let mut kept = Vec::new();
+    let mut elided = 0;
+    let mut i = 0;
+
+    while i < lines.len() {
+        let body = is_header(&lines[i])
+            .then(|| braced_body_end(lines, i))
+            .flatten()
+            .and_then(|end| {
+                let open = (i..end).find(|&j| lines[j].contains('{'))?;
+                (end - open > 1).then_some((open, end))
+            });
+        let Some((open, end)) = body else {
+            kept.push(lines[i].clone());
+            i += 1;
+            continue;
+        };
+
+        kept.extend(lines[i..open].iter().cloned());
+        let opening = &lines[open][..=lines[open].find('{').unwrap()];
+        // Keep whatever follows the closing brace, like the `);` of a callback
+        let closing = lines[end - 1].trim();
+        let after = closing.strip_prefix('}').unwrap_or("");
+        kept.push(Cow::Owned(format!("{} ... }}{}", opening, after)));
+        elided += end - open - 1;
+        i = end;
+    }
+
+    (kept, elided)
+}
+
+fn python_skeleton<'a>(lines: &[Cow<'a, str>]) -> (Vec<Cow<'a, str>>, usize) {
+    let mut kept = Vec::new();
+    let mut elided = 0;
+    let mut i = 0;
+
+    while i < lines.len() {
+        kept.push(lines[i].clone());
+        if !PYTHON_DEF.is_match(&lines[i]) {
+            i += 1;
+            continue;
+        }
+
+        // Parameters may wrap; the signature ends where its brackets close,
+        // and only has a block under it if that line ends in `:`
+        let mut depth = 0;
+        let colon = (i..lines.len())
+            .find(|&j| {
+                let code = lines[j].split('#').next().unwrap_or("");
+                depth += code.matches(['(', '[']).count() as isize;
+                depth -= code.matches([')', ']']).count() as isize;
+                depth <= 0
+            })
+            .filter(|&j| lines[j].split('#').next().unwrap_or("").trim_end().ends_with(':'));
+        let Some(colon) = colon else {
+            i += 1;
+            continue;
+        };
+        let end = python_block_end(lines, colon, indent_of(&lines[i]));
+        kept.extend(lines[i + 1..=colon].iter().cloned());
+
+        if let Some(first) = (colon + 1..end).find(|&j| !is_blank(&lines[j])) {
+            let body = &lines[first];
+            kept.push(Cow::Owned(format!("{}...", &body[..indent_of(body)])));
+            elided += end - colon - 1;
+        }
+        i = end;
+    }
+
+    (kept, elided)
+}
+
+fn rust_header(line: &str) -> Option<(&'static str, String)> {
+    let caps = RUST_SCOPE.captures(line)?;
+    if let Some(target) = caps.name("target") {
+        return Some(("impl", target.as_str().to_string()));
+    }
+    let kind = match caps.name("kind")?.as_str() {
+        "fn" => "function",
+        "mod" => "module",
+        "trait" => "trait",
+        "struct" => "struct",
+        "enum" => "enum",
+        _ => "union",
+    };
+    Some((kind, caps["name"].to_string()))
+}
+
+fn go_header(line: &str) -> Option<(&'static str, String)> {
+    let caps = GO_SCOPE.captures(line)?;
+    match caps.name("name") {
+        Some(name) => Some(("function", name.as_str().to_string())),
+        None => {
+            let kind = if &caps["type_kind"] == "struct" { "struct" } else { "interface" };
+            Some((kind, caps["type"].to_string()))
+        }
+    }
+}
+
+fn js_header(line: &str) -> Option<(&'static str, String)> {
+    if JS_CONTROL.is_match(line) {
+        return None;
+    }
+    let caps = JS_SCOPE.captures(line)?;
+    let frame = |kind, name: regex::Match| Some((kind, name.as_str().to_string()));
+    if let Some(name) = caps.name("name") {
+        let kind = if &caps["kind"] == "class" { "class" } else { "interface" };
+        frame(kind, name)
+    } else if let Some(name) = caps.name("function").or(caps.name("arrow")) {
+        frame("function", name)
+    } else {
+        frame("method", caps.name("method")?)
+    }
+}
+
+fn python_scopes(lines: &[Cow<str>], line_idx: usize) -> Vec<ScopeFrame> {
+    let mut scopes = Vec::new();
+
+    // Same upward walk as `python_function`, keeping every def and class
+    let mut limit = if is_blank(&lines[line_idx]) {
+        usize::MAX
+    } else {
+        indent_of(&lines[line_idx]) + 1
+    };
+
+    for header in (0..=line_idx).rev() {
+        let line = &lines[header];
+        if is_blank(line) {
+            continue;
+        }
+        let indent = indent_of(line);
+        if indent >= limit {
+            continue;
+        }
+        limit = indent;
+
+        if let Some(caps) = PYTHON_SCOPE.captures(line) {
+            let end = python_block_end(lines, header, indent);
+            if end > line_idx {
+                scopes.push(ScopeFrame {
+                    kind: if caps.name("class").is_some() { "class" } else { "function" }
+                        .to_string(),
+                    name: caps["name"].to_string(),
+                    start_line: header + 1,
+                    end_line: end,
+                });
+            }
+        }
+    }
+
+    scopes
+}
+
+fn braced_function(
+    lines: &[Cow<str>],
+    line_idx: usize,
+    is_header: impl Fn(&str) -> bool,
+) -> Option<Range<usize>> {
+    // The nearest header whose body reaches the match is the innermost one
+    (0..=line_idx)
+        .rev()
+        .filter(|&i| is_header(&lines[i]))
+        .find_map(|header| {
+            let end = braced_body_end(lines, header)?;
+            (end > line_idx).then_some(header..end)
+        })
+}
+
+/// Exclusive end line of the brace block opened on or after `header`, or
+/// `None` if a `;` ends the item first (a declaration without a body).
+fn braced_body_end(lines: &[Cow<str>], header: usize) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut opened = false;
+
+    for (i, line) in lines.iter().enumerate().skip(header) {
+        let mut in_string = false;
+        let mut escaped = false;
+
+        for c in line.chars() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '{' => {
+                    depth += 1;
+                    opened = true;
+                }
+                '}' if depth > 0 => {
+                    depth -= 1;
+                    if depth == 0 && opened {
+                        return Some(i + 1);
+                    }
+                }
+                ';' if !opened => return None,
+                _ => {}
+            }
+        }
+    }
+
+    None
+}
+
+fn python_function(lines: &[Cow<str>], line_idx: usize) -> Option<Range<usize>> {
+    // A header must be indented less than everything between it and the match
+    let mut limit = if is_blank(&lines[line_idx]) {
+        usize::MAX
+    } else {
+        indent_of(&lines[line_idx]) + 1
+    };
+
+    for header in (0..=line_idx).rev() {
+        let line = &lines[header];
+        if is_blank(line) {
+            continue;
+        }
+        let indent = indent_of(line);
+        if indent >= limit && header != line_idx {
+            continue;
+        }
+        limit = indent;
+
+        if PYTHON_DEF.is_match(line) {
+            let end = python_block_end(lines, header, indent);
+            return (end > line_idx).then_some(header..end);
+        }
+    }
+
+    None
+}
+
+/// Exclusive end of the block under `header`: the last non-blank line before
+/// the next one indented no deeper than the header. Trailing blank lines
+/// belong to whatever follows.
+fn python_block_end(lines: &[Cow<str>], header: usize, indent: usize) -> usize {
+    let end = (header + 1..lines.len())
+        .find(|&i| !is_blank(&lines[i]) && indent_of(&lines[i]) <= indent)
+        .unwrap_or(lines.len());
+    (header + 1..end)
+        .rev()
+        .find(|&i| !is_blank(&lines[i]))
+        .map_or(end, |i| i + 1)
+}
+
+fn indent_of(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(source: &str) -> Vec<Cow<'_, str>> {
+        source.lines().map(Cow::Borrowed).collect()
+    }
+
+    #[test]
+    fn test_enclosing_rust_function() {
+        let source = lines(
+            "fn outer() {\n    let s = \"}\";\n    fn inner() {\n        target();\n    }\n    after();\n}\ntrait T {\n    fn decl();\n}\n",
+        );
+
+        assert_eq!(enclosing_function(&source, 3, Language::Rust), Some(2..5));
+        assert_eq!(enclosing_function(&source, 5, Language::Rust), Some(0..7));
+        assert_eq!(enclosing_function(&source, 8, Language::Rust), None);
+    }
+
+    #[test]
+    fn test_enclosing_python_function() {
+        let source = lines(
+            "class A:\n    def method(self):\n        if x:\n            target()\n\n        return 1\n\n    def other(self):\n        pass\n",
+        );
+
+        assert_eq!(enclosing_function(&source, 3, Language::Python), Some(1..6));
+        assert_eq!(enclosing_function(&source, 0, Language::Python), None);
+    }
+
+    #[test]
+    fn test_enclosing_scopes() {
+        let rust = lines(
+            "mod net {\n    impl<T: Read> Reader for Conn<T> {\n        fn read(&mut self) {\n            target();\n        }\n    }\n}\n",
+        );
+        let frames: Vec<(String, String, usize, usize)> = enclosing_scopes(&rust, 3, Language::Rust)
+            .into_iter()
+            .map(|f| (f.kind, f.name, f.start_line, f.end_line))
+            .collect();
+        assert_eq!(
+            frames,
+            vec![
+                ("function".to_string(), "read".to_string(), 3, 5),
+                ("impl".to_string(), "Reader for Conn<T>".to_string(), 2, 6),
+                ("module".to_string(), "net".to_string(), 1, 7),
+            ]
+        );
+
+        let python = lines(
+            "class A:\n    def method(self):\n        if x:\n            target()\n\n        return 1\n\ndef f():\n    pass\n",
+        );
+        let names: Vec<(String, String)> = enclosing_scopes(&python, 3, Language::Python)
+            .into_iter()
+            .map(|f| (f.kind, f.name))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("function".to_string(), "method".to_string()),
+                ("class".to_string(), "A".to_string()),
+            ]
+        );
+        assert_eq!(enclosing_scopes(&python, 8, Language::Python)[0].name, "f");
+
+        let js = lines(
+            "export class Store {\n  async load(id) {\n    if (id) {\n      return 1;\n    }\n  }\n}\n",
+        );
+        let names: Vec<String> =
+            enclosing_scopes(&js, 3, Language::JavaScript).into_iter().map(|f| f.name).collect();
+        assert_eq!(names, vec!["load", "Store"]);
+
+        assert!(enclosing_scopes(&js, 3, Language::Markdown).is_empty());
+    }
+
+    #[test]
+    fn test_skeleton() {
+        let rust = "impl Foo {\n    pub fn new() -> Self {\n        let x = 1;\n        Self { x }\n    }\n\n    fn short(&self) -> u32 { self.x }\n    fn decl(&self);\n}\n";
+        let (kept, elided) = braced_skeleton(&lines(rust), |l| RUST_FN.is_match(l));
+        assert_eq!(
+            kept.join("\n"),
+            "impl Foo {\n    pub fn new() -> Self { ... }\n\n    fn short(&self) -> u32 { self.x }\n    fn decl(&self);\n}"
+        );
+        assert_eq!(elided, 3);
+
+        let js = "items.forEach(function (item) {\n  use(item);\n});\nclass A {\n  run() {\n    go();\n  }\n}\n";
+        let (kept, elided) = braced_skeleton(&lines(js), |l| JS_FN.is_match(l));
+        assert_eq!(kept.join("\n"), "items.forEach(function (item) { ... });\nclass A {\n  run() { ... }\n}");
+        assert_eq!(elided, 4);
+
+        let python = "class A:\n    @property\n    def name(\n        self,\n    ) -> str:\n        # doc\n        return 'a'\n\n    def one(self): return 1\n\nclass B:\n    pass\n";
+        let (kept, elided) = python_skeleton(&lines(python));
+        assert_eq!(
+            kept.join("\n"),
+            "class A:\n    @property\n    def name(\n        self,\n    ) -> str:\n        ...\n\n    def one(self): return 1\n\nclass B:\n    pass"
+        );
+        assert_eq!(elided, 2);
+    }
+}
@@ -1,102 +1,1155 @@
-use crate::types::{FsReadError, FsReadResult, SearchMatch};
-use regex::Regex;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use std::path::Path;
+use crate::accents::strip_accents;
+use crate::archive;
+use crate::comments::mask_outside;
+use crate::generated;
+use crate::gitignore::Gitignore;
+use crate::glob::normalize_separators;
+use crate::hash::ContentHasher;
+use crate::language::{detect_language, Language};
+use crate::line::{resolve_line_range, truncate_line, with_file_content};
+use crate::scope::enclosing_function;
+use crate::types::{
+    kind_check, ContextLine, ContextLines, ContextMode, ExpandScope, ExtractRow, FileMatches,
+    FileRatio, FsReadError, FsReadResult, FsWarning, HashAlgorithm, HistogramBucket, LineMatch,
+    MatchScope, Occurrence, PathKind, Requirement, ScoreFactors, SearchMatch, SearchSummary,
+    SkipReason, SkippedEntry, SyntaxScope, WarningKind,
+};
+use memchr::memmem;
+use regex::{Regex, RegexBuilder};
+use std::borrow::Cow;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
 use walkdir::WalkDir;
 
+#[derive(Debug, Clone, Default)]
+pub struct SearchOptions {
+    pub context_lines: u32,
+    pub start_line: Option<i32>,
+    pub end_line: Option<i32>,
+    pub include_summary: bool,
+    pub literal: bool,
+    pub max_line_bytes: Option<usize>,
+    pub group_by_file: bool,
+    /// Same meaning as Directory mode's depth; `None` walks everything
+    pub depth: Option<u32>,
+    pub anchor_start: bool,
+    pub anchor_end: bool,
+    pub numbered_context: bool,
+    /// Replaces line-based context when the enclosing scope can be found
+    pub expand_to: Option<ExpandScope>,
+    /// Gitignore-syntax file applied to directory searches
+    pub ignore_file: Option<PathBuf>,
+    /// Most occurrences per line first; stays within each file when grouped
+    pub sort_by_match_count: bool,
+    /// Emit each source line at most once per file; implies numbered context
+    pub dedupe_context_lines: bool,
+    /// Stop collecting from a file after this many matches and move on
+    pub max_matches_per_file: Option<usize>,
+    /// Search the text members of `.zip`/`.tar.gz` files instead of skipping them
+    pub search_archives: bool,
+    /// Files modified most recently first, then by path; applied before
+    /// `sort_by_match_count`
+    pub recent_first: bool,
+    /// Leave out generated and vendored files, listing them as skipped
+    pub skip_generated: bool,
+    /// Rewrite match and capped file paths relative to this base
+    pub relative_to: Option<PathBuf>,
+    /// Clip the matched line around the match, and context lines from their
+    /// start, to this many bytes
+    pub max_context_width: Option<usize>,
+    /// Add each match's context as one ready-to-show `block`
+    pub render_block: bool,
+    /// Display-only: `/` separators in reported paths on every platform.
+    /// Search mode defaults this to true
+    pub normalize_separators: bool,
+    /// Further patterns that must all match alongside the main one
+    pub all_of: Vec<String>,
+    /// Where `all_of` patterns must co-occur
+    pub scope: MatchScope,
+    /// Only match within comments, strings or code
+    pub within: Option<SyntaxScope>,
+    /// Replaces `context_lines` when set
+    pub context_mode: Option<ContextMode>,
+    /// Reject matches touching a word character on either side
+    pub whole_word: bool,
+    pub case_insensitive: bool,
+    /// Add per-file hunks; implies `group_by_file`
+    pub hunk_format: bool,
+    /// Strip accents from pattern and lines and ignore case
+    pub normalize_unicode: bool,
+    /// Score matches and sort by score; overrides `sort_by_match_count`.
+    /// Grouped results order files by their best match
+    pub rank: bool,
+}
+
 pub fn search_pattern(
     path: &Path,
     pattern: &str,
-    context_lines: u32,
+    options: &SearchOptions,
 ) -> Result<FsReadResult, FsReadError> {
-    if !path.exists() {
-        return Err(FsReadError::PathNotFound(path.to_path_buf()));
+    // A line window only makes sense for a single file
+    let expected = if options.start_line.is_some() || options.end_line.is_some() {
+        Some(PathKind::File)
+    } else {
+        None
+    };
+    let kind = kind_check(path, expected)?;
+
+    let matcher = Matcher::for_query(pattern, options)?;
+
+    let mut warnings = Vec::new();
+    let found = match kind {
+        PathKind::File if options.search_archives && archive::is_archive(path) => {
+            search_archive(path, &matcher, options)?
+        }
+        PathKind::File => search_file(path, &matcher, options)?,
+        PathKind::Directory => search_directory(path, &matcher, options, &mut warnings)?,
+        PathKind::Other => return Err(FsReadError::ExpectedFile(path.to_path_buf(), kind)),
+    };
+    let Found {
+        mut matches,
+        truncated_lines,
+        mut capped_files,
+        mut skipped,
+        unscoped_files,
+        warnings: archive_warnings,
+        hunks,
+    } = found;
+    warnings.extend(archive_warnings);
+    warnings.extend(unscoped_files.into_iter().map(|path| FsWarning {
+        message: format!("no syntax rules for {}; searched whole lines", path),
+        path,
+        kind: WarningKind::UnsupportedLanguage,
+    }));
+
+    // While paths are still relative to the filesystem, for metadata and depth
+    if options.rank {
+        score_matches(&mut matches, path, SystemTime::now());
     }
 
-    let regex = Regex::new(pattern).map_err(|e| FsReadError::InvalidPattern(e.to_string()))?;
+    if let Some(base) = &options.relative_to {
+        let paths = matches.iter_mut().map(|m| &mut m.file_path);
+        for file_path in paths.chain(capped_files.iter_mut()) {
+            relativize(file_path, base);
+        }
+    }
+    if options.normalize_separators {
+        let paths = matches.iter_mut().map(|m| &mut m.file_path);
+        let paths = paths.chain(capped_files.iter_mut());
+        for file_path in paths.chain(skipped.iter_mut().map(|s| &mut s.path)) {
+            normalize_separators(file_path);
+        }
+    }
+    if options.dedupe_context_lines {
+        dedupe_context_lines(&mut matches);
+    }
 
-    let mut matches = Vec::new();
+    let summary = options.include_summary.then(|| summarize(&matches));
 
-    if path.is_file() {
-        matches.extend(search_file(path, &regex, context_lines)?);
-    } else {
-        matches.extend(search_directory(path, &regex, context_lines)?);
+    if options.group_by_file || options.hunk_format {
+        let mut files = group_by_file(matches);
+        if options.hunk_format {
+            let mut hunks: HashMap<String, Vec<String>> = hunks.into_iter().collect();
+            for file in &mut files {
+                file.hunks = hunks.remove(&file.file_path).unwrap_or_default();
+            }
+        }
+        if options.rank {
+            for file in &mut files {
+                file.matches.sort_by(|a, b| highest_score_first(a.score, b.score));
+            }
+            // Each file's best match now comes first
+            let best = |file: &FileMatches| file.matches.first().and_then(|m| m.score);
+            files.sort_by(|a, b| highest_score_first(best(a), best(b)));
+        } else if options.sort_by_match_count {
+            for file in &mut files {
+                file.matches.sort_by_key(|m| Reverse(m.match_count));
+            }
+        }
+        return Ok(FsReadResult::SearchGrouped {
+            total_matches: files.iter().map(|f| f.matches.len()).sum(),
+            files,
+            truncated_lines,
+            summary,
+            truncated: false,
+            omitted_count: 0,
+            warnings,
+            capped_files,
+            skipped,
+        });
+    }
+
+    // Stable, so file position breaks ties
+    if options.rank {
+        matches.sort_by(|a, b| highest_score_first(a.score, b.score));
+    } else if options.sort_by_match_count {
+        matches.sort_by_key(|m| Reverse(m.match_count));
     }
 
     Ok(FsReadResult::Search {
         total_matches: matches.len(),
         matches,
+        truncated_lines,
+        summary,
+        truncated: false,
+        omitted_count: 0,
+        warnings,
+        capped_files,
+        skipped,
     })
 }
 
+/// Weights of `ScoreFactors::density`, `recency` and `shallowness`
+const SCORE_WEIGHTS: [f32; 3] = [0.5, 0.3, 0.2];
+const RECENCY_HALF_LIFE_DAYS: f32 = 30.0;
+
+/// Sets each match's `score` to `0.5 * density + 0.3 * recency + 0.2 *
+/// shallowness` (see `ScoreFactors`), so a score is between 0 and 1. Lines
+/// with more occurrences, recently modified files and files nearer `root`
+/// score higher. Files are stat'ed once each.
+fn score_matches(matches: &mut [SearchMatch], root: &Path, now: SystemTime) {
+    let mut per_file: HashMap<String, (f32, f32)> = HashMap::new();
+
+    for m in matches {
+        let (recency, shallowness) = *per_file.entry(m.file_path.clone()).or_insert_with(|| {
+            let file = Path::new(&m.file_path);
+            let recency = fs::metadata(file)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .map_or(0.0, |modified| {
+                    let age = now.duration_since(modified).unwrap_or_default();
+                    let days = age.as_secs_f32() / 86_400.0;
+                    1.0 / (1.0 + days / RECENCY_HALF_LIFE_DAYS)
+                });
+            let depth = file
+                .strip_prefix(root)
+                .map_or(0, |relative| relative.components().count().saturating_sub(1));
+            (recency, 1.0 / (1.0 + depth as f32))
+        });
+
+        let density = m.match_count as f32 / (m.match_count as f32 + 1.0);
+        let [w_density, w_recency, w_shallowness] = SCORE_WEIGHTS;
+        m.score = Some(w_density * density + w_recency * recency + w_shallowness * shallowness);
+        m.score_factors = Some(ScoreFactors {
+            density,
+            recency,
+            shallowness,
+        });
+    }
+}
+
+fn highest_score_first(a: Option<f32>, b: Option<f32>) -> std::cmp::Ordering {
+    b.unwrap_or(0.0).total_cmp(&a.unwrap_or(0.0))
+}
+
+/// FNV-1a (64-bit) over the reported file path, the decimal line number
+/// and the full line content (before `max_line_bytes` or
+/// `max_context_width` clip it), each followed by a NUL byte, as 16 hex
+/// digits.
+/// Identical matches get identical IDs across runs; changing this format
+/// breaks clients diffing stored results.
+pub(crate) fn match_id(file_path: &str, line_number: usize, line_content: &str) -> String {
+    let mut hasher = ContentHasher::new(HashAlgorithm::Fnv1a);
+    for part in [file_path, &line_number.to_string(), line_content] {
+        hasher.update(part.as_bytes());
+        hasher.update(&[0]);
+    }
+    hasher.finish()
+}
+
+/// `file_path` as it will be reported, once `relative_to` and
+/// `normalize_separators` are applied.
+fn reported_path(file_path: &str, options: &SearchOptions) -> String {
+    let mut reported = file_path.to_string();
+    if let Some(base) = &options.relative_to {
+        relativize(&mut reported, base);
+    }
+    if options.normalize_separators {
+        normalize_separators(&mut reported);
+    }
+    reported
+}
+
+fn relativize(file_path: &mut String, base: &Path) {
+    if let Ok(relative) = Path::new(file_path.as_str()).strip_prefix(base) {
+        *file_path = relative.to_string_lossy().into_owned();
+    }
+}
+
+/// Plain literals skip the regex engine and use a memmem finder instead.
+enum Matcher {
+    Regex(Regex),
+    Literal(Box<memmem::Finder<'static>>),
+    /// `all_of` with line scope: every part on the same line
+    All(Vec<Matcher>),
+    /// `all_of` with file scope: lines matching any part, in files where
+    /// every part matches somewhere (see `covers`)
+    Any(Vec<Matcher>),
+}
+
+impl Matcher {
+    /// `pattern` alone, or together with `all_of` combined per `scope`.
+    fn for_query(pattern: &str, options: &SearchOptions) -> Result<Self, FsReadError> {
+        if options.all_of.is_empty() {
+            return Self::new(pattern, options);
+        }
+
+        let parts = std::iter::once(pattern)
+            .chain(options.all_of.iter().map(String::as_str))
+            .map(|p| Self::new(p, options))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(match options.scope {
+            MatchScope::Line => Self::All(parts),
+            MatchScope::File => Self::Any(parts),
+        })
+    }
+
+    /// Flags apply in a fixed order: `literal` escapes the pattern first, then
+    /// `whole_word` wraps it in `\b{start-half}(?:...)\b{end-half}`, then
+    /// `anchor_start`/`anchor_end` wrap the result in `^(?:...)` / `(?:...)$`,
+    /// so anchors stay regex anchors even for literal patterns. Word
+    /// boundaries and case folding both follow Unicode, so accented and CJK
+    /// letters count as word characters. `normalize_unicode` strips the
+    /// pattern's accents before anything else and implies `case_insensitive`.
+    fn new(pattern: &str, options: &SearchOptions) -> Result<Self, FsReadError> {
+        let case_insensitive = options.case_insensitive || options.normalize_unicode;
+        let needs_regex =
+            options.anchor_start || options.anchor_end || options.whole_word || case_insensitive;
+        let (pattern, _) = if options.normalize_unicode {
+            strip_accents(pattern)
+        } else {
+            (Cow::Borrowed(pattern), None)
+        };
+        let pattern = pattern.as_ref();
+
+        if !needs_regex && (options.literal || is_plain_literal(pattern)) {
+            let finder = memmem::Finder::new(pattern.as_bytes()).into_owned();
+            return Ok(Self::Literal(Box::new(finder)));
+        }
+
+        let mut source = if options.literal {
+            regex::escape(pattern)
+        } else {
+            pattern.to_string()
+        };
+        // Half boundaries, unlike `\b`, also work for patterns that start or
+        // end with a non-word character such as `-v` or `foo(`
+        if options.whole_word {
+            source = format!(r"\b{{start-half}}(?:{})\b{{end-half}}", source);
+        }
+        if options.anchor_start {
+            source = format!("^(?:{})", source);
+        }
+        if options.anchor_end {
+            source = format!("(?:{})$", source);
+        }
+
+        let regex = RegexBuilder::new(&source)
+            .case_insensitive(case_insensitive)
+            .build()
+            .map_err(|e| FsReadError::InvalidPattern(e.to_string()))?;
+        Ok(Self::Regex(regex))
+    }
+
+    fn is_match(&self, line: &str) -> bool {
+        match self {
+            Self::Regex(regex) => regex.is_match(line),
+            Self::Literal(finder) => finder.find(line.as_bytes()).is_some(),
+            Self::All(parts) => parts.iter().all(|p| p.is_match(line)),
+            Self::Any(parts) => parts.iter().any(|p| p.is_match(line)),
+        }
+    }
+
+    /// Whether a file with these lines can match at all: for file scope,
+    /// every part must match at least one of them.
+    fn covers<S: AsRef<str>>(&self, lines: &[S]) -> bool {
+        match self {
+            Self::Any(parts) => parts
+                .iter()
+                .all(|p| lines.iter().any(|line| p.is_match(line.as_ref()))),
+            _ => true,
+        }
+    }
+
+    /// Byte range of the first occurrence in `line`.
+    fn find(&self, line: &str) -> Option<Range<usize>> {
+        match self {
+            Self::Regex(regex) => regex.find(line).map(|m| m.range()),
+            Self::Literal(finder) => {
+                let start = finder.find(line.as_bytes())?;
+                Some(start..start + finder.needle().len())
+            }
+            Self::All(parts) | Self::Any(parts) => {
+                parts.iter().filter_map(|p| p.find(line)).min_by_key(|r| r.start)
+            }
+        }
+    }
+
+    fn count(&self, line: &str) -> usize {
+        match self {
+            Self::Regex(regex) => regex.find_iter(line).count(),
+            // An empty needle matches at every byte; the regex engine only
+            // matches at char boundaries
+            Self::Literal(finder) if finder.needle().is_empty() => line.chars().count() + 1,
+            Self::Literal(finder) => finder.find_iter(line.as_bytes()).count(),
+            Self::All(parts) | Self::Any(parts) => parts.iter().map(|p| p.count(line)).sum(),
+        }
+    }
+}
+
+fn is_plain_literal(pattern: &str) -> bool {
+    regex::escape(pattern) == pattern
+}
+
+fn group_by_file(matches: Vec<SearchMatch>) -> Vec<FileMatches> {
+    // Matches arrive grouped by file, so consecutive runs form the groups
+    let mut files: Vec<FileMatches> = Vec::new();
+
+    for m in matches {
+        let line_match = LineMatch {
+            line_number: m.line_number,
+            line_content: m.line_content,
+            context_before: m.context_before,
+            context_after: m.context_after,
+            match_count: m.match_count,
+            column: m.column,
+            match_id: m.match_id,
+            block: m.block,
+            score: m.score,
+            score_factors: m.score_factors,
+        };
+        match files.last_mut() {
+            Some(group) if group.file_path == m.file_path => group.matches.push(line_match),
+            _ => files.push(FileMatches {
+                file_path: m.file_path,
+                matches: vec![line_match],
+                hunks: Vec::new(),
+            }),
+        }
+    }
+
+    files
+}
+
+/// The lines covered by `ranges` (0-based, in any order) as unified-diff
+/// hunks, overlapping or adjacent ranges merged. Every line is context, so
+/// each hunk is a valid diff that changes nothing.
+fn file_hunks(lines: &[&str], mut ranges: Vec<Range<usize>>) -> Vec<String> {
+    ranges.sort_by_key(|range| range.start);
+    let mut merged: Vec<Range<usize>> = Vec::new();
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+
+    merged
+        .into_iter()
+        .map(|range| {
+            let (start, len) = (range.start + 1, range.len());
+            let body: Vec<String> = lines[range].iter().map(|line| format!(" {}", line)).collect();
+            format!("@@ -{start},{len} +{start},{len} @@\n{}", body.join("\n"))
+        })
+        .collect()
+}
+
+const SUMMARY_TOP_FILES: usize = 10;
+
+fn summarize(matches: &[SearchMatch]) -> SearchSummary {
+    // Matches arrive grouped by file, so counting runs is enough
+    let mut per_file: Vec<(String, usize)> = Vec::new();
+    for m in matches {
+        match per_file.last_mut() {
+            Some((path, count)) if *path == m.file_path => *count += 1,
+            _ => per_file.push((m.file_path.clone(), 1)),
+        }
+    }
+
+    // Power-of-two buckets: 1, 2-3, 4-7, ...
+    let mut histogram: Vec<HistogramBucket> = Vec::new();
+    for (_, count) in &per_file {
+        let bucket = count.ilog2() as usize;
+        while histogram.len() <= bucket {
+            let min = 1 << histogram.len();
+            histogram.push(HistogramBucket { min, max: min * 2 - 1, files: 0 });
+        }
+        histogram[bucket].files += 1;
+    }
+
+    let files_with_matches = per_file.len();
+    per_file.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    per_file.truncate(SUMMARY_TOP_FILES);
+
+    SearchSummary {
+        files_with_matches,
+        top_files: per_file,
+        matches_per_file_histogram: histogram,
+    }
+}
+
+/// Matches gathered from one or more files.
+#[derive(Default)]
+struct Found {
+    matches: Vec<SearchMatch>,
+    /// Scanned lines cut by `max_line_bytes`
+    truncated_lines: usize,
+    /// Files that had more matches than `max_matches_per_file`
+    capped_files: Vec<String>,
+    /// Files left out by `skip_generated`
+    skipped: Vec<SkippedEntry>,
+    /// Files searched whole because `within` doesn't know their language
+    unscoped_files: Vec<String>,
+    /// Archive members too large to inflate
+    warnings: Vec<FsWarning>,
+    /// With `hunk_format`, each file's hunks under its reported path, built
+    /// from its lines before any clipping
+    hunks: Vec<(String, Vec<String>)>,
+}
+
+impl Found {
+    fn extend(&mut self, other: Found) {
+        self.matches.extend(other.matches);
+        self.truncated_lines += other.truncated_lines;
+        self.capped_files.extend(other.capped_files);
+        self.skipped.extend(other.skipped);
+        self.unscoped_files.extend(other.unscoped_files);
+        self.warnings.extend(other.warnings);
+        self.hunks.extend(other.hunks);
+    }
+}
+
 fn search_file(
     path: &Path,
-    regex: &Regex,
-    context_lines: u32,
-) -> Result<Vec<SearchMatch>, FsReadError> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    let lines: Vec<String> = reader
-        .lines()
-        .collect::<Result<_, _>>()
-        .map_err(FsReadError::IoError)?;
+    matcher: &Matcher,
+    options: &SearchOptions,
+) -> Result<Found, FsReadError> {
+    let language = needs_language(options).then(|| detect_language(path)).flatten();
+    with_file_content(path, |content| {
+        search_content(&path.to_string_lossy(), content, language, matcher, options)
+    })
+}
+
+/// Searches each text member of an archive, reporting matches as
+/// `archive.zip!inner/path.txt`. The line window applies to every member.
+fn search_archive(
+    path: &Path,
+    matcher: &Matcher,
+    options: &SearchOptions,
+) -> Result<Found, FsReadError> {
+    let mut all = Found::default();
+    let mut first_error = None;
 
+    all.warnings = archive::for_each_text_member(path, |name, content| {
+        let file_path = format!("{}!{}", path.to_string_lossy(), name);
+        let language = needs_language(options)
+            .then(|| detect_language(Path::new(name)))
+            .flatten();
+        match search_content(&file_path, content, language, matcher, options) {
+            Ok(found) => all.extend(found),
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    })?;
+
+    match first_error {
+        Some(e) => Err(e),
+        None => Ok(all),
+    }
+}
+
+fn needs_language(options: &SearchOptions) -> bool {
+    options.expand_to.is_some() || options.within.is_some()
+}
+
+fn search_content(
+    file_path: &str,
+    content: &str,
+    language: Option<Language>,
+    matcher: &Matcher,
+    options: &SearchOptions,
+) -> Result<Found, FsReadError> {
+    let context_lines = match options.context_mode {
+        Some(ContextMode::Lines(n)) => n,
+        _ => options.context_lines,
+    };
+    let raw_lines: Vec<&str> = content.lines().collect();
+    let lines: Vec<Cow<str>> = raw_lines
+        .iter()
+        .map(|line| truncate_line(line, options.max_line_bytes))
+        .collect();
+    // Out-of-scope text blanked to spaces, so byte offsets still line up
+    let masked = options.within.and_then(|within| mask_outside(content, language, within));
+    let masked_lines: Option<Vec<Cow<str>>> = masked.as_deref().map(|masked| {
+        masked
+            .lines()
+            .map(|line| truncate_line(line, options.max_line_bytes))
+            .collect()
+    });
+    let searched = masked_lines.as_deref().unwrap_or(&lines);
+    // Accents stripped, with where each byte came from in the searched line
+    let (stripped_lines, stripped_offsets): (Vec<Cow<str>>, Vec<Option<Vec<usize>>>) =
+        if options.normalize_unicode {
+            searched.iter().map(|line| strip_accents(line)).unzip()
+        } else {
+            (Vec::new(), Vec::new())
+        };
+    let searched = if options.normalize_unicode { &stripped_lines } else { searched };
+    let unscoped_files = match (options.within, &masked) {
+        (Some(_), None) => vec![file_path.to_string()],
+        _ => Vec::new(),
+    };
+
+    // Only matches are restricted to the window; context may extend past it
+    let (start_idx, end_idx) =
+        resolve_line_range(options.start_line, options.end_line, lines.len())?;
     let mut matches = Vec::new();
+    let mut capped_files = Vec::new();
+    let truncated_lines = lines[start_idx..end_idx]
+        .iter()
+        .filter(|line| matches!(line, Cow::Owned(_)))
+        .count();
+
+    if !matcher.covers(&searched[start_idx..end_idx]) {
+        return Ok(Found {
+            truncated_lines,
+            unscoped_files,
+            ..Default::default()
+        });
+    }
 
-    for (line_num, line) in lines.iter().enumerate() {
-        if regex.is_match(line) {
-            let context_before = get_context_before(&lines, line_num, context_lines);
-            let context_after = get_context_after(&lines, line_num, context_lines);
+    let reported = reported_path(file_path, options);
+    // Lines around each match, for `hunk_format`
+    let mut shown = Vec::new();
+    for (line_num, line) in lines.iter().enumerate().take(end_idx).skip(start_idx) {
+        let target = &searched[line_num];
+        if matcher.is_match(target) {
+            if options.max_matches_per_file.is_some_and(|max| matches.len() >= max) {
+                capped_files.push(file_path.to_string());
+                break;
+            }
+            let scope = match (options.expand_to, language) {
+                (Some(ExpandScope::Function), Some(language)) => {
+                    enclosing_function(&lines, line_num, language)
+                }
+                _ => None,
+            };
+            let scope = scope.or_else(|| {
+                (options.context_mode == Some(ContextMode::Paragraph))
+                    .then(|| paragraph_around(&lines, line_num))
+            });
+            let (before_start, after_end) = match scope {
+                Some(range) => (range.start, range.end),
+                None => (
+                    line_num.saturating_sub(context_lines as usize),
+                    (line_num + 1 + context_lines as usize).min(lines.len()),
+                ),
+            };
+            let numbered = options.numbered_context;
+            let width = options.max_context_width;
+            let context_before = collect_context(&lines, before_start..line_num, numbered, width);
+            let context_after = collect_context(&lines, line_num + 1..after_end, numbered, width);
+            let span = matcher.find(target).map(|span| {
+                match stripped_offsets.get(line_num).and_then(Option::as_ref) {
+                    Some(offsets) => offsets[span.start]..offsets[span.end],
+                    None => span,
+                }
+            });
+            let line_content = match (width, &span) {
+                (Some(width), Some(span)) => clip_around(line, span.clone(), width).into_owned(),
+                _ => line.to_string(),
+            };
+            let block = options.render_block.then(|| {
+                render_block(&lines, before_start..after_end, line_num, &line_content, width)
+            });
+            if options.hunk_format {
+                shown.push(before_start..after_end);
+            }
 
             matches.push(SearchMatch {
-                file_path: path.to_string_lossy().to_string(),
+                file_path: file_path.to_string(),
                 line_number: line_num + 1,
-                line_content: line.clone(),
+                line_content,
                 context_before,
                 context_after,
+                match_count: matcher.count(target),
+                column: span.map(|span| span.start + 1),
+                match_id: match_id(&reported, line_num + 1, raw_lines[line_num]),
+                block,
+                score: None,
+                score_factors: None,
             });
         }
     }
 
-    Ok(matches)
+    let hunks = if shown.is_empty() {
+        Vec::new()
+    } else {
+        vec![(reported, file_hunks(&raw_lines, shown))]
+    };
+
+    Ok(Found {
+        matches,
+        truncated_lines,
+        capped_files,
+        skipped: Vec::new(),
+        unscoped_files,
+        warnings: Vec::new(),
+        hunks,
+    })
+}
+
+/// The lines around `line_idx` up to, not including, the nearest blank line
+/// on each side.
+fn paragraph_around(lines: &[Cow<str>], line_idx: usize) -> Range<usize> {
+    let is_blank = |i: &usize| lines[*i].trim().is_empty();
+    let start = (0..line_idx).rev().find(is_blank).map_or(0, |i| i + 1);
+    let end = (line_idx + 1..lines.len()).find(is_blank).unwrap_or(lines.len());
+    start..end
 }
 
 fn search_directory(
     path: &Path,
-    regex: &Regex,
-    context_lines: u32,
-) -> Result<Vec<SearchMatch>, FsReadError> {
-    let mut all_matches = Vec::new();
+    matcher: &Matcher,
+    options: &SearchOptions,
+    warnings: &mut Vec<FsWarning>,
+) -> Result<Found, FsReadError> {
+    let mut all = Found::default();
+    let mut by_mtime = Vec::new();
+    let ignore = options.ignore_file.as_deref().map(Gitignore::from_file).transpose()?;
 
-    for entry in WalkDir::new(path)
-        .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_file())
-    {
-        if let Ok(matches) = search_file(entry.path(), regex, context_lines) {
-            all_matches.extend(matches);
+    let walk = WalkFiles {
+        depth: options.depth,
+        ignore: ignore.as_ref(),
+        skip_generated: options.skip_generated,
+    };
+    all.skipped = for_each_file(path, &walk, |file| {
+        // Unreadable files (binaries included) are skipped but reported
+        let found = if options.search_archives && archive::is_archive(file) {
+            search_archive(file, matcher, options)
+        } else {
+            search_file(file, matcher, options)
+        };
+        match found {
+            Ok(found) if options.recent_first => {
+                if found.matches.is_empty() {
+                    all.extend(found);
+                } else {
+                    let modified = fs::metadata(file).and_then(|m| m.modified()).ok();
+                    by_mtime.push((modified, file.to_path_buf(), found));
+                }
+            }
+            Ok(found) => all.extend(found),
+            Err(e) => warnings.push(FsWarning::from_error(file.to_string_lossy(), &e)),
+        }
+    });
+
+    // Files without an mtime sort last
+    by_mtime.sort_by(|(a_time, a_path, _), (b_time, b_path, _)| {
+        b_time.cmp(a_time).then_with(|| a_path.cmp(b_path))
+    });
+    for (_, _, found) in by_mtime {
+        all.extend(found);
+    }
+
+    Ok(all)
+}
+
+/// Which files `for_each_file` visits; `depth` has the same meaning as in
+/// `SearchOptions`.
+#[derive(Default)]
+struct WalkFiles<'a> {
+    depth: Option<u32>,
+    ignore: Option<&'a Gitignore>,
+    skip_generated: bool,
+}
+
+/// Calls `visit` for every regular file under `path` not excluded by `walk`,
+/// returning the generated files and directories it left out.
+fn for_each_file(path: &Path, walk: &WalkFiles, mut visit: impl FnMut(&Path)) -> Vec<SkippedEntry> {
+    let WalkFiles {
+        depth,
+        ignore,
+        skip_generated,
+    } = *walk;
+    let max_depth = match depth {
+        None => usize::MAX,
+        Some(0) => 1,
+        Some(depth) => depth as usize,
+    };
+
+    let is_included = |entry: &walkdir::DirEntry| match ignore {
+        Some(ignore) if entry.depth() > 0 => {
+            let relative = entry.path().strip_prefix(path).unwrap_or(entry.path());
+            !ignore.is_ignored(relative, entry.file_type().is_dir())
+        }
+        _ => true,
+    };
+
+    let mut skipped = Vec::new();
+    let walker = WalkDir::new(path).max_depth(max_depth).into_iter().filter_entry(|e| {
+        if !is_included(e) {
+            return false;
+        }
+        let is_generated = skip_generated
+            && e.depth() > 0
+            && generated::is_generated(e.path(), e.file_type().is_dir());
+        if is_generated {
+            skipped.push(SkippedEntry {
+                path: e.path().to_string_lossy().into_owned(),
+                reason: SkipReason::Generated,
+            });
+        }
+        !is_generated
+    });
+
+    for entry in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+        visit(entry.path());
+    }
+
+    skipped
+}
+
+/// Everything after the first or last line matching `pattern`.
+pub fn after_pattern(
+    path: &Path,
+    pattern: &str,
+    occurrence: Occurrence,
+) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+    let matcher = Matcher::new(pattern, &SearchOptions::default())?;
+
+    with_file_content(path, |content| {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut matching = lines.iter().enumerate().filter(|(_, l)| matcher.is_match(l));
+        let delimiter = match occurrence {
+            Occurrence::First => matching.next(),
+            Occurrence::Last => matching.next_back(),
+        }
+        .map(|(i, _)| i);
+
+        let rest = delimiter.map_or(&[][..], |i| &lines[i + 1..]);
+
+        Ok(FsReadResult::After {
+            content: rest.join("\n"),
+            total_lines: lines.len(),
+            lines_returned: rest.len(),
+            found: delimiter.is_some(),
+            delimiter_line: delimiter.map(|i| i + 1),
+            truncated: false,
+            omitted_count: 0,
+        })
+    })
+}
+
+/// Per-file share of lines matching `pattern`, counted in one pass over each
+/// file. Unreadable files are skipped as in a directory search.
+pub fn match_ratio(path: &Path, pattern: &str) -> Result<FsReadResult, FsReadError> {
+    let kind = kind_check(path, None)?;
+    let matcher = Matcher::new(pattern, &SearchOptions::default())?;
+
+    let per_file = match kind {
+        PathKind::File => vec![file_ratio(path, &matcher)?],
+        PathKind::Directory => {
+            let mut per_file = Vec::new();
+            for_each_file(path, &WalkFiles::default(), |file| {
+                if let Ok(ratio) = file_ratio(file, &matcher) {
+                    per_file.push(ratio);
+                }
+            });
+            per_file
+        }
+        PathKind::Other => return Err(FsReadError::ExpectedFile(path.to_path_buf(), kind)),
+    };
+
+    Ok(FsReadResult::MatchRatio {
+        per_file,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+/// Files containing every pattern (or any of them), each read once and
+/// only until the requirement is settled. Patterns are regexes, or
+/// literals when they have no special characters, as in a search.
+/// Unreadable files are skipped as in a directory search.
+pub fn files_containing(
+    path: &Path,
+    patterns: &[String],
+    require: Requirement,
+) -> Result<FsReadResult, FsReadError> {
+    let kind = kind_check(path, None)?;
+    if patterns.is_empty() {
+        return Err(FsReadError::InvalidPattern("no patterns given".to_string()));
+    }
+    let matchers = patterns
+        .iter()
+        .map(|p| Matcher::new(p, &SearchOptions::default()))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut files = Vec::new();
+    match kind {
+        PathKind::File => {
+            if file_contains(path, &matchers, require)? {
+                files.push(path.to_string_lossy().to_string());
+            }
+        }
+        PathKind::Directory => {
+            for_each_file(path, &WalkFiles::default(), |file| {
+                if let Ok(true) = file_contains(file, &matchers, require) {
+                    files.push(file.to_string_lossy().to_string());
+                }
+            });
+            files.sort();
+        }
+        PathKind::Other => return Err(FsReadError::ExpectedFile(path.to_path_buf(), kind)),
+    }
+
+    Ok(FsReadResult::FileSet {
+        files,
+        truncated: false,
+        omitted_count: 0,
+    })
+}
+
+fn file_contains(
+    path: &Path,
+    matchers: &[Matcher],
+    require: Requirement,
+) -> Result<bool, FsReadError> {
+    with_file_content(path, |content| {
+        // Patterns already found aren't tested against later lines
+        let mut missing: Vec<&Matcher> = matchers.iter().collect();
+
+        for line in content.lines() {
+            let before = missing.len();
+            missing.retain(|m| !m.is_match(line));
+            let settled = match require {
+                Requirement::All => missing.is_empty(),
+                Requirement::Any => missing.len() < before,
+            };
+            if settled {
+                return Ok(true);
+            }
         }
+        Ok(false)
+    })
+}
+
+/// Capture groups of every line matching `pattern`, for pulling fields out
+/// of logs and similar line-oriented text. Unreadable files are skipped as
+/// in a directory search.
+pub fn extract(path: &Path, pattern: &str) -> Result<FsReadResult, FsReadError> {
+    let kind = kind_check(path, None)?;
+    let regex = Regex::new(pattern).map_err(|e| FsReadError::InvalidPattern(e.to_string()))?;
+
+    let mut rows = Vec::new();
+    match kind {
+        PathKind::File => extract_file(path, &regex, &mut rows)?,
+        PathKind::Directory => {
+            for_each_file(path, &WalkFiles::default(), |file| {
+                let _ = extract_file(file, &regex, &mut rows);
+            });
+        }
+        PathKind::Other => return Err(FsReadError::ExpectedFile(path.to_path_buf(), kind)),
     }
 
-    Ok(all_matches)
+    Ok(FsReadResult::Extract {
+        rows,
+        truncated: false,
+        omitted_count: 0,
+    })
 }
 
-fn get_context_before(lines: &[String], index: usize, count: u32) -> Vec<String> {
-    let start = index.saturating_sub(count as usize);
-    lines[start..index].to_vec()
+fn extract_file(path: &Path, regex: &Regex, rows: &mut Vec<ExtractRow>) -> Result<(), FsReadError> {
+    let names: Vec<Option<&str>> = regex.capture_names().collect();
+
+    with_file_content(path, |content| {
+        for (i, line) in content.lines().enumerate() {
+            let Some(captures) = regex.captures(line) else {
+                continue;
+            };
+            let mut groups = BTreeMap::new();
+            // Group 0 is the whole match, not a field
+            for (index, capture) in captures.iter().enumerate().skip(1) {
+                let Some(capture) = capture else {
+                    continue;
+                };
+                if let Some(name) = names[index] {
+                    groups.insert(name.to_string(), capture.as_str().to_string());
+                }
+                groups.insert(index.to_string(), capture.as_str().to_string());
+            }
+            rows.push(ExtractRow {
+                file_path: path.to_string_lossy().to_string(),
+                line_number: i + 1,
+                groups,
+            });
+        }
+        Ok(())
+    })
 }
 
-fn get_context_after(lines: &[String], index: usize, count: u32) -> Vec<String> {
-    let end = (index + 1 + count as usize).min(lines.len());
-    lines[index + 1..end].to_vec()
+fn file_ratio(path: &Path, matcher: &Matcher) -> Result<FileRatio, FsReadError> {
+    with_file_content(path, |content| {
+        let mut total_lines = 0;
+        let mut matching_lines = 0;
+        for line in content.lines() {
+            total_lines += 1;
+            if matcher.is_match(line) {
+                matching_lines += 1;
+            }
+        }
+
+        Ok(FileRatio {
+            file_path: path.to_string_lossy().to_string(),
+            matching_lines,
+            total_lines,
+            ratio: if total_lines == 0 {
+                0.0
+            } else {
+                matching_lines as f64 / total_lines as f64
+            },
+        })
+    })
+}
+
+/// Blanks context lines already shown earlier in the same file, either as
+/// context or as a matched line, leaving a `shown_with` reference to the
+/// match that first showed them. Matched lines themselves are kept.
+fn dedupe_context_lines(matches: &mut [SearchMatch]) {
+    // Source line number to the line number of the match that first showed it
+    let mut shown: HashMap<usize, usize> = HashMap::new();
+    let mut current_file: Option<String> = None;
+
+    for m in matches.iter_mut() {
+        if current_file.as_ref() != Some(&m.file_path) {
+            shown.clear();
+            current_file = Some(m.file_path.clone());
+        }
+        let line_number = m.line_number;
+        shown.entry(line_number).or_insert(line_number);
+
+        // Context is contiguous on either side of the match
+        let before_start = line_number - m.context_before.len();
+        for (context, first) in [
+            (&mut m.context_before, before_start),
+            (&mut m.context_after, line_number + 1),
+        ] {
+            let numbered = match std::mem::take(context) {
+                ContextLines::Numbered(lines) => lines,
+                ContextLines::Plain(lines) => lines
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, content)| ContextLine {
+                        line_number: first + i,
+                        content,
+                        shown_with: None,
+                    })
+                    .collect(),
+            };
+            *context = ContextLines::Numbered(
+                numbered
+                    .into_iter()
+                    .map(|mut line| {
+                        match shown.get(&line.line_number) {
+                            Some(&with) => {
+                                line.content.clear();
+                                line.shown_with = Some(with);
+                            }
+                            None => {
+                                shown.insert(line.line_number, line_number);
+                            }
+                        }
+                        line
+                    })
+                    .collect(),
+            );
+        }
+    }
+}
+
+fn collect_context(
+    lines: &[Cow<str>],
+    range: Range<usize>,
+    numbered: bool,
+    width: Option<usize>,
+) -> ContextLines {
+    let content = |line: &str| match width {
+        Some(width) => clip_around(line, 0..0, width).into_owned(),
+        None => line.to_string(),
+    };
+    if numbered {
+        ContextLines::Numbered(
+            range
+                .map(|i| ContextLine {
+                    line_number: i + 1,
+                    content: content(&lines[i]),
+                    shown_with: None,
+                })
+                .collect(),
+        )
+    } else {
+        ContextLines::Plain(lines[range].iter().map(|l| content(l)).collect())
+    }
+}
+
+/// The lines of `range` as `> 12 | text` for the matched line and
+/// `  11 | text` around it, numbers right-aligned, joined with `\n`.
+fn render_block(
+    lines: &[Cow<str>],
+    range: Range<usize>,
+    line_num: usize,
+    line_content: &str,
+    width: Option<usize>,
+) -> String {
+    let number_width = range.end.to_string().len();
+    range
+        .map(|i| {
+            let (marker, content) = match (i == line_num, width) {
+                (true, _) => (">", Cow::Borrowed(line_content)),
+                (false, Some(width)) => (" ", clip_around(&lines[i], 0..0, width)),
+                (false, None) => (" ", Cow::Borrowed(lines[i].as_ref())),
+            };
+            format!("{} {:>w$} | {}", marker, i + 1, content, w = number_width)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// At most `width` bytes of `line` (on char boundaries) centred on `span`,
+/// with `…` marking each cut end. A span wider than `width` keeps its start.
+fn clip_around(line: &str, span: Range<usize>, width: usize) -> Cow<'_, str> {
+    if line.len() <= width {
+        return Cow::Borrowed(line);
+    }
+
+    let slack = width.saturating_sub(span.len());
+    let mut start = span.start.saturating_sub(slack / 2).min(line.len() - width);
+    let mut end = start + width;
+    // `span.start` is a char boundary, so `start` never moves past it
+    while !line.is_char_boundary(start) {
+        start += 1;
+    }
+    while !line.is_char_boundary(end) {
+        end -= 1;
+    }
+
+    let prefix = if start > 0 { "…" } else { "" };
+    let suffix = if end < line.len() { "…" } else { "" };
+    Cow::Owned(format!("{}{}{}", prefix, &line[start..end], suffix))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use std::fs;
-    use tempfile::TempDir;
+    use crate::test_util::TempDir;
 
     #[test]
     fn test_search_file() {
@@ -104,9 +1157,13 @@ mod tests {
         let file_path = temp.path().join("test.txt");
         fs::write(&file_path, "line 1\nTODO: fix this\nline 3\nTODO: another\nline 5").unwrap();
 
-        let result = search_pattern(&file_path, "TODO", 1).unwrap();
+        let options = SearchOptions {
+            context_lines: 1,
+            ..Default::default()
+        };
+        let result = search_pattern(&file_path, "TODO", &options).unwrap();
 
-        if let FsReadResult::Search { matches, total_matches } = result {
+        if let FsReadResult::Search { matches, total_matches, .. } = result {
             assert_eq!(total_matches, 2);
             assert_eq!(matches[0].line_number, 2);
             assert_eq!(matches[1].line_number, 4);
@@ -114,4 +1171,895 @@ mod tests {
             panic!("Expected Search result");
         }
     }
+
+    #[test]
+    fn test_search_file_line_window() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("test.txt");
+        fs::write(&file_path, "TODO 1\nline 2\nTODO 3\nline 4\nTODO 5").unwrap();
+
+        let options = SearchOptions {
+            context_lines: 1,
+            start_line: Some(2),
+            end_line: Some(4),
+            ..Default::default()
+        };
+        let result = search_pattern(&file_path, "TODO", &options).unwrap();
+
+        if let FsReadResult::Search { matches, total_matches, .. } = result {
+            assert_eq!(total_matches, 1);
+            assert_eq!(matches[0].line_number, 3);
+            assert_eq!(matches[0].context_before, ContextLines::Plain(vec!["line 2".into()]));
+            assert_eq!(matches[0].context_after, ContextLines::Plain(vec!["line 4".into()]));
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_search_numbered_context() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("test.txt");
+        fs::write(&file_path, "a\nb\nTODO\nc").unwrap();
+
+        let options = SearchOptions {
+            context_lines: 2,
+            numbered_context: true,
+            ..Default::default()
+        };
+        let result = search_pattern(&file_path, "TODO", &options).unwrap();
+
+        if let FsReadResult::Search { matches, .. } = result {
+            let line = |line_number: usize, content: &str| ContextLine {
+                line_number,
+                content: content.to_string(),
+                shown_with: None,
+            };
+            assert_eq!(
+                matches[0].context_before,
+                ContextLines::Numbered(vec![line(1, "a"), line(2, "b")])
+            );
+            assert_eq!(matches[0].context_after, ContextLines::Numbered(vec![line(4, "c")]));
+            assert_eq!(
+                serde_json::to_value(&matches[0].context_after).unwrap(),
+                serde_json::json!([{"line_number": 4, "content": "c"}])
+            );
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_search_line_window_rejects_directory() {
+        let temp = TempDir::new().unwrap();
+
+        let options = SearchOptions {
+            start_line: Some(1),
+            ..Default::default()
+        };
+        let err = search_pattern(temp.path(), "TODO", &options).unwrap_err();
+
+        assert!(matches!(err, FsReadError::ExpectedFile(_, PathKind::Directory)));
+    }
+
+    #[test]
+    fn test_search_summary() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.txt"), "TODO\nTODO\nTODO").unwrap();
+        fs::write(temp.path().join("b.txt"), "TODO").unwrap();
+        fs::write(temp.path().join("c.txt"), "nothing").unwrap();
+
+        let options = SearchOptions {
+            include_summary: true,
+            ..Default::default()
+        };
+        let result = search_pattern(temp.path(), "TODO", &options).unwrap();
+
+        if let FsReadResult::Search { summary: Some(summary), .. } = result {
+            assert_eq!(summary.files_with_matches, 2);
+            assert!(summary.top_files[0].0.ends_with("a.txt"));
+            assert_eq!(summary.top_files[0].1, 3);
+            let buckets: Vec<(usize, usize, usize)> = summary
+                .matches_per_file_histogram
+                .iter()
+                .map(|b| (b.min, b.max, b.files))
+                .collect();
+            assert_eq!(buckets, vec![(1, 1, 1), (2, 3, 1)]);
+        } else {
+            panic!("Expected Search result with summary");
+        }
+    }
+
+    #[test]
+    fn test_search_group_by_file() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.txt"), "TODO one\nTODO two").unwrap();
+        fs::write(temp.path().join("b.txt"), "x\nTODO three").unwrap();
+
+        let options = SearchOptions {
+            group_by_file: true,
+            ..Default::default()
+        };
+        let result = search_pattern(temp.path(), "TODO", &options).unwrap();
+
+        if let FsReadResult::SearchGrouped { mut files, total_matches, .. } = result {
+            files.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+            assert_eq!(total_matches, 3);
+            assert_eq!(files.len(), 2);
+            assert!(files[0].file_path.ends_with("a.txt"));
+            assert_eq!(files[0].matches.len(), 2);
+            assert_eq!(files[1].matches[0].line_number, 2);
+        } else {
+            panic!("Expected SearchGrouped result");
+        }
+    }
+
+    #[test]
+    fn test_search_depth() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("a/b")).unwrap();
+        fs::write(temp.path().join("top.txt"), "TODO").unwrap();
+        fs::write(temp.path().join("a/mid.txt"), "TODO").unwrap();
+        fs::write(temp.path().join("a/b/deep.txt"), "TODO").unwrap();
+
+        let total = |depth: Option<u32>| {
+            let options = SearchOptions {
+                depth,
+                ..Default::default()
+            };
+            match search_pattern(temp.path(), "TODO", &options).unwrap() {
+                FsReadResult::Search { total_matches, .. } => total_matches,
+                _ => panic!("Expected Search result"),
+            }
+        };
+
+        assert_eq!(total(Some(0)), 1);
+        assert_eq!(total(Some(2)), 2);
+        assert_eq!(total(None), 3);
+    }
+
+    #[test]
+    fn test_anchors_compose_with_literal() {
+        let options = SearchOptions {
+            literal: true,
+            anchor_start: true,
+            anchor_end: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new("}", &options).unwrap();
+        assert!(matcher.is_match("}"));
+        assert!(!matcher.is_match("  }"));
+        assert!(!matcher.is_match("};"));
+
+        let options = SearchOptions {
+            literal: true,
+            anchor_start: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new("a.b", &options).unwrap();
+        assert!(matcher.is_match("a.b = 1"));
+        assert!(!matcher.is_match("axb = 1"));
+
+        let options = SearchOptions {
+            anchor_end: true,
+            ..Default::default()
+        };
+        let matcher = Matcher::new("foo|bar", &options).unwrap();
+        assert!(matcher.is_match("x = bar"));
+        assert!(!matcher.is_match("foo = 1"));
+    }
+
+    #[test]
+    fn test_literal_matches_regex_path() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("test.txt");
+        fs::write(
+            &file_path,
+            "fn main() {\n    let x = a.b;\n    ab\n\n    a.b.c\n    ÄÖ a.b ü\n}",
+        )
+        .unwrap();
+        let options = SearchOptions {
+            context_lines: 1,
+            ..Default::default()
+        };
+
+        for pattern in ["a.b", "fn", "", "ü", "}", "x = "] {
+            let literal_options = SearchOptions {
+                literal: true,
+                ..Default::default()
+            };
+            let literal = Matcher::new(pattern, &literal_options).unwrap();
+            let regex = Matcher::Regex(Regex::new(&regex::escape(pattern)).unwrap());
+            assert!(matches!(literal, Matcher::Literal(_)));
+
+            let literal_matches = search_file(&file_path, &literal, &options).unwrap().matches;
+            let regex_matches = search_file(&file_path, &regex, &options).unwrap().matches;
+            assert_eq!(
+                serde_json::to_string(&literal_matches).unwrap(),
+                serde_json::to_string(&regex_matches).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn test_search_directory_warnings() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.txt"), "TODO").unwrap();
+        fs::write(temp.path().join("b.bin"), [0xff, 0xfe, 0x00]).unwrap();
+
+        let result = search_pattern(temp.path(), "TODO", &SearchOptions::default()).unwrap();
+
+        if let FsReadResult::Search { total_matches, warnings, .. } = result {
+            assert_eq!(total_matches, 1);
+            assert_eq!(warnings.len(), 1);
+            assert!(warnings[0].path.ends_with("b.bin"));
+            assert_eq!(warnings[0].kind, crate::types::WarningKind::InvalidData);
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_search_dedupe_context_lines() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("test.txt");
+        fs::write(&file_path, "a\nTODO 1\nb\nTODO 2\nc").unwrap();
+
+        let options = SearchOptions {
+            context_lines: 2,
+            dedupe_context_lines: true,
+            ..Default::default()
+        };
+        let result = search_pattern(&file_path, "TODO", &options).unwrap();
+
+        if let FsReadResult::Search { matches, .. } = result {
+            let summary = |context: &ContextLines| -> Vec<(usize, String, Option<usize>)> {
+                match context {
+                    ContextLines::Numbered(lines) => lines
+                        .iter()
+                        .map(|l| (l.line_number, l.content.clone(), l.shown_with))
+                        .collect(),
+                    ContextLines::Plain(_) => panic!("Expected numbered context"),
+                }
+            };
+
+            assert_eq!(matches[1].line_content, "TODO 2");
+            assert_eq!(
+                summary(&matches[1].context_before),
+                vec![(2, String::new(), Some(2)), (3, String::new(), Some(2))]
+            );
+            assert_eq!(summary(&matches[1].context_after), vec![(5, "c".to_string(), None)]);
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_search_max_matches_per_file() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("big.txt"), "TODO\n".repeat(10)).unwrap();
+        fs::write(temp.path().join("small.txt"), "TODO\nTODO").unwrap();
+
+        let options = SearchOptions {
+            max_matches_per_file: Some(2),
+            ..Default::default()
+        };
+        let result = search_pattern(temp.path(), "TODO", &options).unwrap();
+
+        if let FsReadResult::Search {
+            total_matches,
+            capped_files,
+            ..
+        } = result
+        {
+            assert_eq!(total_matches, 4);
+            assert_eq!(capped_files.len(), 1);
+            assert!(capped_files[0].ends_with("big.txt"));
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_search_recent_first() {
+        let temp = TempDir::new().unwrap();
+        let now = std::time::SystemTime::now();
+        for (name, age) in [("a_old.txt", 3600), ("b_new.txt", 0), ("c_mid.txt", 60)] {
+            let path = temp.path().join(name);
+            fs::write(&path, "hit\nmiss\nhit").unwrap();
+            let modified = now - std::time::Duration::from_secs(age);
+            fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        }
+
+        let options = SearchOptions {
+            recent_first: true,
+            ..Default::default()
+        };
+        let result = search_pattern(temp.path(), "hit", &options).unwrap();
+
+        if let FsReadResult::Search { matches, .. } = result {
+            let order: Vec<(String, usize)> = matches
+                .iter()
+                .map(|m| {
+                    let name = Path::new(&m.file_path).file_name().unwrap();
+                    (name.to_string_lossy().to_string(), m.line_number)
+                })
+                .collect();
+            let expected = [("b_new.txt", 1), ("b_new.txt", 3), ("c_mid.txt", 1), ("c_mid.txt", 3)];
+            assert_eq!(order[..4], expected.map(|(n, l)| (n.to_string(), l)));
+            assert_eq!(order[4].0, "a_old.txt");
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_search_skip_generated() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("third_party")).unwrap();
+        fs::write(temp.path().join("third_party/dep.py"), "TODO").unwrap();
+        fs::write(temp.path().join("api_pb2.py"), "TODO").unwrap();
+        fs::write(temp.path().join("main.py"), "TODO").unwrap();
+
+        let options = SearchOptions {
+            skip_generated: true,
+            ..Default::default()
+        };
+        let result = search_pattern(temp.path(), "TODO", &options).unwrap();
+
+        if let FsReadResult::Search {
+            matches, skipped, ..
+        } = result
+        {
+            assert_eq!(matches.len(), 1);
+            assert!(matches[0].file_path.ends_with("main.py"));
+            assert_eq!(skipped.len(), 2);
+            assert!(skipped.iter().all(|s| s.reason == SkipReason::Generated));
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_search_relative_to() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("src/main.rs");
+        fs::create_dir_all(file.parent().unwrap()).unwrap();
+        fs::write(&file, "TODO").unwrap();
+
+        let options = SearchOptions {
+            relative_to: Some(temp.path().to_path_buf()),
+            ..Default::default()
+        };
+        let expected = Path::new("src").join("main.rs");
+        for path in [temp.path(), file.as_path()] {
+            let result = search_pattern(path, "TODO", &options).unwrap();
+            if let FsReadResult::Search { matches, .. } = result {
+                assert_eq!(matches[0].file_path, expected.to_string_lossy());
+            } else {
+                panic!("Expected Search result");
+            }
+        }
+
+        // Outside the base the path is left as is
+        let options = SearchOptions {
+            relative_to: Some(PathBuf::from("/elsewhere")),
+            ..Default::default()
+        };
+        let result = search_pattern(&file, "TODO", &options).unwrap();
+        if let FsReadResult::Search { matches, .. } = result {
+            assert_eq!(matches[0].file_path, file.to_string_lossy());
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_match_id() {
+        // Pinned: IDs must not change between versions
+        assert_eq!(match_id("src/main.rs", 3, "let x = 1;"), "12afd9996cfd1497");
+
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.txt"), "hit\nhit\n").unwrap();
+        let options = SearchOptions {
+            relative_to: Some(temp.path().to_path_buf()),
+            ..Default::default()
+        };
+        let ids = || match search_pattern(temp.path(), "hit", &options).unwrap() {
+            FsReadResult::Search { matches, .. } => {
+                matches.into_iter().map(|m| m.match_id).collect::<Vec<_>>()
+            }
+            _ => panic!("Expected Search result"),
+        };
+
+        let first = ids();
+        assert_eq!(first, ids());
+        assert_eq!(first[0], match_id("a.txt", 1, "hit"));
+        assert_ne!(first[0], first[1]);
+
+        // Clipping changes what's shown, not the ID
+        let long = format!("{}hit{}", "a".repeat(100), "b".repeat(100));
+        fs::write(temp.path().join("a.txt"), &long).unwrap();
+        let options = SearchOptions {
+            relative_to: Some(temp.path().to_path_buf()),
+            max_context_width: Some(20),
+            max_line_bytes: Some(150),
+            ..Default::default()
+        };
+        match search_pattern(temp.path(), "hit", &options).unwrap() {
+            FsReadResult::Search { matches, .. } => {
+                assert_ne!(matches[0].line_content, long);
+                assert_eq!(matches[0].match_id, match_id("a.txt", 1, &long));
+            }
+            _ => panic!("Expected Search result"),
+        }
+    }
+
+    #[test]
+    fn test_search_hunk_format() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("notes.txt");
+        let content: Vec<String> = (1..=12)
+            .map(|i| if [3, 5, 10].contains(&i) { format!("hit {}", i) } else { format!("l{}", i) })
+            .collect();
+        fs::write(&file_path, content.join("\n")).unwrap();
+
+        let hunks = |dedupe_context_lines, clip: bool| {
+            let options = SearchOptions {
+                context_lines: 1,
+                hunk_format: true,
+                dedupe_context_lines,
+                max_context_width: clip.then_some(2),
+                max_line_bytes: clip.then_some(4),
+                ..Default::default()
+            };
+            match search_pattern(&file_path, "hit", &options).unwrap() {
+                FsReadResult::SearchGrouped { files, .. } => files[0].hunks.clone(),
+                _ => panic!("Expected SearchGrouped result"),
+            }
+        };
+
+        let expected = vec![
+            "@@ -2,5 +2,5 @@\n l2\n hit 3\n l4\n hit 5\n l6".to_string(),
+            "@@ -9,3 +9,3 @@\n l9\n hit 10\n l11".to_string(),
+        ];
+        assert_eq!(hunks(false, false), expected);
+        assert_eq!(hunks(true, false), expected);
+        // Clipping applies to matches and context, not to hunks
+        assert_eq!(hunks(false, true), expected);
+    }
+
+    #[test]
+    fn test_search_normalize_unicode() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("menu.txt");
+        fs::write(&file_path, "Café au lait\nnai\u{308}ve\ncafe\n").unwrap();
+
+        let search = |pattern| {
+            let options = SearchOptions {
+                normalize_unicode: true,
+                ..Default::default()
+            };
+            match search_pattern(&file_path, pattern, &options).unwrap() {
+                FsReadResult::Search { matches, .. } => matches
+                    .into_iter()
+                    .map(|m| (m.line_number, m.line_content, m.column))
+                    .collect::<Vec<_>>(),
+                _ => panic!("Expected Search result"),
+            }
+        };
+
+        assert_eq!(
+            search("CAFÉ"),
+            vec![(1, "Café au lait".to_string(), Some(1)), (3, "cafe".to_string(), Some(1))]
+        );
+        // Columns are byte positions in the original line
+        assert_eq!(search("lait"), vec![(1, "Café au lait".to_string(), Some(10))]);
+        assert_eq!(search("naive"), vec![(2, "nai\u{308}ve".to_string(), Some(1))]);
+    }
+
+    #[test]
+    fn test_search_rank() {
+        let temp = TempDir::new().unwrap();
+        fs::create_dir_all(temp.path().join("a/b")).unwrap();
+        let week_ago = SystemTime::now() - std::time::Duration::from_secs(7 * 86_400);
+        for (name, content) in [("top.txt", "x\n"), ("a/b/deep.txt", "x x x\n")] {
+            let path = temp.path().join(name);
+            fs::write(&path, content).unwrap();
+            fs::File::options()
+                .write(true)
+                .open(&path)
+                .unwrap()
+                .set_modified(week_ago)
+                .unwrap();
+        }
+
+        let options = SearchOptions {
+            rank: true,
+            ..Default::default()
+        };
+        let matches = match search_pattern(temp.path(), "x", &options).unwrap() {
+            FsReadResult::Search { matches, .. } => matches,
+            _ => panic!("Expected Search result"),
+        };
+
+        let factors: Vec<_> = matches.iter().map(|m| m.score_factors.unwrap()).collect();
+        assert!(matches[0].file_path.ends_with("top.txt"));
+        assert_eq!((factors[0].density, factors[0].shallowness), (0.5, 1.0));
+        assert_eq!((factors[1].density, factors[1].shallowness), (0.75, 1.0 / 3.0));
+        assert!((factors[0].recency - 30.0 / 37.0).abs() < 0.01);
+        let score = 0.5 * 0.5 + 0.3 * factors[0].recency + 0.2;
+        assert!((matches[0].score.unwrap() - score).abs() < 1e-6);
+        assert!(matches[0].score > matches[1].score);
+
+        let options = SearchOptions {
+            rank: true,
+            group_by_file: true,
+            ..Default::default()
+        };
+        match search_pattern(temp.path(), "x", &options).unwrap() {
+            FsReadResult::SearchGrouped { files, .. } => {
+                assert!(files[0].file_path.ends_with("top.txt"));
+                assert!(files[1].file_path.ends_with("deep.txt"));
+            }
+            _ => panic!("Expected SearchGrouped result"),
+        }
+    }
+
+    #[test]
+    fn test_search_all_of() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.rs"), "use tokio;\nasync fn run() {}\nfn tokio_run() {}\n")
+            .unwrap();
+        fs::write(temp.path().join("b.rs"), "use tokio;\n").unwrap();
+
+        let search = |scope| {
+            let options = SearchOptions {
+                all_of: vec!["fn".to_string()],
+                scope,
+                ..Default::default()
+            };
+            match search_pattern(temp.path(), "tokio", &options).unwrap() {
+                FsReadResult::Search { matches, .. } => matches
+                    .into_iter()
+                    .map(|m| {
+                        let name = Path::new(&m.file_path).file_name().unwrap();
+                        (name.to_string_lossy().to_string(), m.line_number)
+                    })
+                    .collect::<Vec<_>>(),
+                _ => panic!("Expected Search result"),
+            }
+        };
+
+        assert_eq!(search(MatchScope::Line), vec![("a.rs".to_string(), 3)]);
+        // b.rs never mentions fn, so none of its lines are returned
+        let mut file_scope = search(MatchScope::File);
+        file_scope.sort();
+        let expected: Vec<_> = [1, 2, 3].map(|n| ("a.rs".to_string(), n)).into();
+        assert_eq!(file_scope, expected);
+    }
+
+    #[test]
+    fn test_search_max_context_width() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("bundle.min.js");
+        let wide = format!("{}NEEDLE{}", "a".repeat(100), "b".repeat(100));
+        fs::write(&file, format!("{}\n{}\nshort\n", "c".repeat(50), wide)).unwrap();
+
+        let options = SearchOptions {
+            context_lines: 1,
+            max_context_width: Some(16),
+            ..Default::default()
+        };
+        let result = search_pattern(&file, "NEEDLE", &options).unwrap();
+
+        if let FsReadResult::Search { matches, .. } = result {
+            assert_eq!(matches[0].line_content, "…aaaaaNEEDLEbbbbb…");
+            assert_eq!(matches[0].column, Some(101));
+            assert_eq!(
+                matches[0].context_before,
+                ContextLines::Plain(vec![format!("{}…", "c".repeat(16))])
+            );
+            assert_eq!(matches[0].context_after, ContextLines::Plain(vec!["short".to_string()]));
+        } else {
+            panic!("Expected Search result");
+        }
+
+        // Char boundaries are respected and a wide match keeps its start
+        assert_eq!(clip_around("ééééé", 4..6, 5), "…éé…");
+        assert_eq!(clip_around("xxLONGMATCHxx", 2..11, 4), "…LONG…");
+        assert_eq!(clip_around("matchxxxxxx", 0..5, 7), "matchxx…");
+    }
+
+    #[test]
+    fn test_search_render_block() {
+        let temp = TempDir::new().unwrap();
+        let file = temp.path().join("main.rs");
+        let content: Vec<String> = (1..=12).map(|i| format!("line {}", i)).collect();
+        fs::write(&file, content.join("\n")).unwrap();
+
+        let options = SearchOptions {
+            context_lines: 1,
+            render_block: true,
+            ..Default::default()
+        };
+        let result = search_pattern(&file, "line (1|9)$", &options).unwrap();
+
+        if let FsReadResult::Search { matches, .. } = result {
+            assert_eq!(matches[0].block.as_deref(), Some("> 1 | line 1\n  2 | line 2"));
+            assert_eq!(
+                matches[1].block.as_deref(),
+                Some("   8 | line 8\n>  9 | line 9\n  10 | line 10")
+            );
+            assert_eq!(matches[1].line_content, "line 9");
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_search_sort_by_match_count() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("test.txt");
+        fs::write(&file_path, "x\nx x x\nx x\nx x x").unwrap();
+
+        let options = SearchOptions {
+            sort_by_match_count: true,
+            ..Default::default()
+        };
+        let result = search_pattern(&file_path, "x", &options).unwrap();
+
+        if let FsReadResult::Search { matches, .. } = result {
+            let order: Vec<(usize, usize)> =
+                matches.iter().map(|m| (m.line_number, m.match_count)).collect();
+            assert_eq!(order, vec![(2, 3), (4, 3), (3, 2), (1, 1)]);
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_after_pattern() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("app.log");
+        fs::write(&file_path, "=== START ===\na\n=== START ===\nb\nc").unwrap();
+
+        let after = |pattern: &str, occurrence| match after_pattern(&file_path, pattern, occurrence)
+        {
+            Ok(FsReadResult::After { content, found, delimiter_line, .. }) => {
+                (content, found, delimiter_line)
+            }
+            _ => panic!("Expected After result"),
+        };
+
+        assert_eq!(
+            after("=== START ===", Occurrence::First),
+            ("a\n=== START ===\nb\nc".to_string(), true, Some(1))
+        );
+        assert_eq!(after("=== START ===", Occurrence::Last), ("b\nc".to_string(), true, Some(3)));
+        assert_eq!(after("=== END ===", Occurrence::Last), (String::new(), false, None));
+    }
+
+    #[test]
+    fn test_extract() {
+        let temp = TempDir::new().unwrap();
+        let log = temp.path().join("app.log");
+        fs::write(
+            &log,
+            "12:00 INFO started\nnoise\n12:01 WARN disk (sda)\n12:02 ERROR crashed\n",
+        )
+        .unwrap();
+
+        let pattern = r"^(?P<time>\S+) (?P<level>INFO|WARN) \w+(?: \((\w+)\))?$";
+        let result = extract(&log, pattern).unwrap();
+
+        if let FsReadResult::Extract { rows, .. } = result {
+            let lines: Vec<usize> = rows.iter().map(|r| r.line_number).collect();
+            assert_eq!(lines, vec![1, 3]);
+
+            let groups = |row: &ExtractRow| {
+                row.groups.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>()
+            };
+            // The unmatched optional group is absent rather than empty
+            assert_eq!(groups(&rows[0]), ["1=12:00", "2=INFO", "level=INFO", "time=12:00"]);
+            assert_eq!(
+                groups(&rows[1]),
+                ["1=12:01", "2=WARN", "3=sda", "level=WARN", "time=12:01"]
+            );
+        } else {
+            panic!("Expected Extract result");
+        }
+
+        assert!(matches!(extract(&log, "("), Err(FsReadError::InvalidPattern(_))));
+    }
+
+    #[test]
+    fn test_match_ratio() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.rs"), "println!(1);\nlet x = 1;\nprintln!(2);\nx").unwrap();
+        fs::write(temp.path().join("empty.rs"), "").unwrap();
+
+        let result = match_ratio(temp.path(), "println!").unwrap();
+
+        if let FsReadResult::MatchRatio { mut per_file, .. } = result {
+            per_file.sort_by(|a, b| a.file_path.cmp(&b.file_path));
+            assert_eq!(per_file.len(), 2);
+            assert_eq!((per_file[0].matching_lines, per_file[0].total_lines), (2, 4));
+            assert_eq!(per_file[0].ratio, 0.5);
+            assert_eq!(per_file[1].ratio, 0.0);
+        } else {
+            panic!("Expected MatchRatio result");
+        }
+    }
+
+    #[test]
+    fn test_files_containing() {
+        let temp = TempDir::new().unwrap();
+        fs::write(temp.path().join("a.js"), "import React from 'react';\nimport _ from 'lodash';\n")
+            .unwrap();
+        fs::write(temp.path().join("b.js"), "import React from 'react';\n").unwrap();
+        fs::write(temp.path().join("c.js"), "const x = 1;\n").unwrap();
+
+        let files = |require| {
+            let patterns = ["'react'".to_string(), "'lodash'".to_string()];
+            match files_containing(temp.path(), &patterns, require).unwrap() {
+                FsReadResult::FileSet { files, .. } => files
+                    .iter()
+                    .map(|f| Path::new(f).file_name().unwrap().to_string_lossy().to_string())
+                    .collect::<Vec<_>>(),
+                _ => panic!("Expected FileSet result"),
+            }
+        };
+
+        assert_eq!(files(Requirement::All), vec!["a.js"]);
+        assert_eq!(files(Requirement::Any), vec!["a.js", "b.js"]);
+        assert!(files_containing(temp.path(), &[], Requirement::Any).is_err());
+    }
+
+    #[test]
+    fn test_search_expand_to_function() {
+        let temp = TempDir::new().unwrap();
+        let file_path = temp.path().join("lib.rs");
+        fs::write(&file_path, "use x;
+
+fn f() {
+    a();
+    TODO();
+    b();
+}
+
+TODO
+").unwrap();
+
+        let options = SearchOptions {
+            context_lines: 0,
+            expand_to: Some(ExpandScope::Function),
+            ..Default::default()
+        };
+        let result = search_pattern(&file_path, "TODO", &options).unwrap();
+
+        if let FsReadResult::Search { matches, .. } = result {
+            assert_eq!(
+                matches[0].context_before,
+                ContextLines::Plain(vec!["fn f() {".into(), "    a();".into()])
+            );
+            assert_eq!(
+                matches[0].context_after,
+                ContextLines::Plain(vec!["    b();".into(), "}".into()])
+            );
+            // Outside any function the line-based context applies
+            assert_eq!(matches[1].context_before, ContextLines::Plain(vec![]));
+        } else {
+            panic!("Expected Search result");
+        }
+    }
+
+    #[test]
+    fn test_search_within() {
+        let temp = TempDir::new().unwrap();
+        let source = "// FIXME: later\nlet FIXME = \"FIXME\";\n/* FIXME */\n";
+        fs::write(temp.path().join("a.rs"), source).unwrap();
+        fs::write(temp.path().join("notes.md"), "FIXME\n").unwrap();
+
+        let search = |within| {
+            let options = SearchOptions {
+                within: Some(within),
+                ..Default::default()
+            };
+            match search_pattern(temp.path(), "FIXME", &options).unwrap() {
+                FsReadResult::Search {
+                    matches, warnings, ..
+                } => {
+                    let mut found: Vec<_> = matches
+                        .into_iter()
+                        .map(|m| {
+                            let name = Path::new(&m.file_path).file_name().unwrap();
+                            (name.to_string_lossy().to_string(), m.line_number, m.column)
+                        })
+                        .collect();
+                    found.sort();
+                    (found, warnings)
+                }
+                _ => panic!("Expected Search result"),
+            }
+        };
+        let hit = |file: &str, line, column| (file.to_string(), line, Some(column));
+
+        let (comments, warnings) = search(SyntaxScope::Comments);
+        assert_eq!(comments, vec![hit("a.rs", 1, 4), hit("a.rs", 3, 4), hit("notes.md", 1, 1)]);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].path.ends_with("notes.md"));
+        assert_eq!(warnings[0].kind, WarningKind::UnsupportedLanguage);
+
+        let (strings, _) = search(SyntaxScope::Strings);
+        assert_eq!(strings, vec![hit("a.rs", 2, 14), hit("notes.md", 1, 1)]);
+        let (code, _) = search(SyntaxScope::Code);
+        assert_eq!(code, vec![hit("a.rs", 2, 5), hit("notes.md", 1, 1)]);
+    }
+
+    #[test]
+    fn test_search_paragraph_context() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("README.md");
+        let text = "# Title\n\nFirst line\nhas the needle\nand more.\n\nOther text.\n";
+        fs::write(&path, text).unwrap();
+
+        let context = |context_mode| {
+            let options = SearchOptions {
+                context_lines: 5,
+                context_mode: Some(context_mode),
+                ..Default::default()
+            };
+            match search_pattern(&path, "needle", &options).unwrap() {
+                FsReadResult::Search { matches, .. } => {
+                    (matches[0].context_before.clone(), matches[0].context_after.clone())
+                }
+                _ => panic!("Expected Search result"),
+            }
+        };
+
+        let (before, after) = context(ContextMode::Paragraph);
+        assert_eq!(before, ContextLines::Plain(vec!["First line".to_string()]));
+        assert_eq!(after, ContextLines::Plain(vec!["and more.".to_string()]));
+
+        let (before, _) = context(ContextMode::Lines(2));
+        assert_eq!(before, ContextLines::Plain(vec![String::new(), "First line".to_string()]));
+
+        let mode: ContextMode = serde_json::from_str(r#"{"lines": 3}"#).unwrap();
+        assert_eq!(mode, ContextMode::Lines(3));
+    }
+
+    #[test]
+    fn test_matcher_whole_word_unicode() {
+        let matcher = |pattern: &str, whole_word, case_insensitive| {
+            let options = SearchOptions {
+                whole_word,
+                case_insensitive,
+                ..Default::default()
+            };
+            Matcher::new(pattern, &options).unwrap()
+        };
+
+        let word = matcher("café", true, false);
+        assert!(word.is_match("let café = 1;"));
+        assert!(!word.is_match("let caféine = 1;"));
+        // `é` is a word character, so `caf` isn't a whole word in `café`
+        assert!(!matcher("caf", true, false).is_match("café"));
+        assert_eq!(matcher("naïve", true, false).find("x naïve_y naïve"), Some(11..17));
+
+        // CJK text has no spaces, so only punctuation delimits words
+        let cjk = matcher("世界", true, false);
+        assert!(cjk.is_match("你好，世界。"));
+        assert!(!cjk.is_match("你好世界"));
+
+        // Patterns with non-word edges still match next to spaces
+        let flag = matcher("-v", true, false);
+        assert!(flag.is_match("run -v now"));
+        assert!(!flag.is_match("run x-v"));
+
+        let folded = matcher("ÉCOLE", true, true);
+        assert!(folded.is_match("une école"));
+        assert!(!folded.is_match("écoles"));
+        assert!(matcher("STRASSE", false, true).is_match("strasse"));
+    }
 }
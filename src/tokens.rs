@@ -0,0 +1,108 @@
+use crate::line::with_file_content;
+use crate::types::{kind_check, FsReadError, FsReadResult, PathKind, TokenizerKind};
+use regex::Regex;
+use std::path::Path;
+use std::sync::LazyLock;
+
+// The cl100k/o200k pre-tokenizer split, minus the `\s+(?!\S)` lookahead
+// alternative the regex crate can't express
+static PIECE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(concat!(
+        r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}|",
+        r" ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+",
+    ))
+    .unwrap()
+});
+
+/// Approximate token count for `path` under the given encoding. The text is
+/// split the way the real tokenizer pre-splits it, and each piece is costed
+/// by its shape instead of running the BPE merges, so counts are estimates
+/// meant for budgeting rather than exact limits.
+pub fn token_estimate(path: &Path, model: TokenizerKind) -> Result<FsReadResult, FsReadError> {
+    kind_check(path, Some(PathKind::File))?;
+
+    with_file_content(path, |content| {
+        Ok(FsReadResult::TokenEstimate {
+            token_count: estimate_tokens(content, model),
+            approximate: true,
+            char_count: content.chars().count(),
+            bytes: content.len() as u64,
+        })
+    })
+}
+
+fn estimate_tokens(text: &str, model: TokenizerKind) -> usize {
+    // Average ASCII letters per token inside a word, and non-ASCII letters
+    // (mostly CJK) per token; o200k's larger vocabulary merges more of both
+    let (ascii_per_token, other_per_token) = match model {
+        TokenizerKind::Cl100k => (5, 1),
+        TokenizerKind::O200k => (6, 2),
+    };
+
+    PIECE
+        .find_iter(text)
+        .map(|piece| {
+            let piece = piece.as_str();
+            let (letters, other): (usize, usize) = piece
+                .chars()
+                .filter(|c| c.is_alphabetic())
+                .fold((0, 0), |(a, o), c| if c.is_ascii() { (a + 1, o) } else { (a, o + 1) });
+            if letters + other > 0 {
+                let cost = letters.div_ceil(ascii_per_token) + other.div_ceil(other_per_token);
+                cost.max(1)
+            } else if piece.trim().is_empty() {
+                1
+            } else {
+                // Numbers come in groups of up to 3 digits; punctuation runs
+                // merge in pairs
+                piece.trim().chars().count().div_ceil(3).max(1)
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::TempDir;
+    use std::fs;
+
+    #[test]
+    fn test_token_estimate() {
+        // Strings whose cl100k counts the estimate matches exactly
+        for (text, expected) in [
+            ("hello world", 2),
+            ("Hello, world!", 4),
+            ("The quick brown fox jumps over the lazy dog.", 10),
+            ("1234567", 3),
+        ] {
+            assert_eq!(estimate_tokens(text, TokenizerKind::Cl100k), expected, "{}", text);
+        }
+        assert_eq!(estimate_tokens("hello world", TokenizerKind::O200k), 2);
+        assert_eq!(estimate_tokens("", TokenizerKind::Cl100k), 0);
+
+        let cjk = "你好世界";
+        assert!(
+            estimate_tokens(cjk, TokenizerKind::O200k) < estimate_tokens(cjk, TokenizerKind::Cl100k)
+        );
+
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("main.rs");
+        fs::write(&path, "fn main() {\n    println!(\"héllo\");\n}\n").unwrap();
+
+        if let FsReadResult::TokenEstimate {
+            token_count,
+            approximate,
+            char_count,
+            bytes,
+        } = token_estimate(&path, TokenizerKind::Cl100k).unwrap()
+        {
+            assert_eq!(token_count, 12);
+            assert!(approximate);
+            assert_eq!(char_count, 37);
+            assert_eq!(bytes, 38);
+        } else {
+            panic!("Expected TokenEstimate result");
+        }
+    }
+}
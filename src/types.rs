@@ -1,5 +1,7 @@
+use crate::language::Language;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "mode", rename_all = "PascalCase")]
@@ -9,27 +11,499 @@ pub enum FsReadMode {
         start_line: Option<i32>,
         #[serde(skip_serializing_if = "Option::is_none")]
         end_line: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_line_bytes: Option<usize>,
+        #[serde(default)]
+        detect_shebang: bool,
+        #[serde(default)]
+        collapse_blank_lines: bool,
+        #[serde(default)]
+        trim_trailing_whitespace: bool,
+        /// Hash of the whole file, whatever range is returned
+        #[serde(skip_serializing_if = "Option::is_none")]
+        include_hash: Option<HashAlgorithm>,
+        /// Keep only the lines in range that pass this test
+        #[serde(skip_serializing_if = "Option::is_none")]
+        filter: Option<LineFilter>,
+        /// Rewrite every line terminator in the returned content to this
+        #[serde(skip_serializing_if = "Option::is_none")]
+        normalize_line_endings: Option<LineEnding>,
+        /// Expand leading tabs to spaces with tab stops this many columns apart
+        #[serde(skip_serializing_if = "Option::is_none")]
+        expand_tabs: Option<u8>,
+        /// With `expand_tabs`, expand every tab rather than only leading ones
+        #[serde(default)]
+        expand_all_tabs: bool,
     },
     Directory {
         #[serde(default)]
         depth: u32,
+        #[serde(default = "default_true")]
+        resolve_root: bool,
+        #[serde(default)]
+        git_status: bool,
+        #[serde(default)]
+        include_hidden: bool,
+        /// Entry names to skip, matched exactly against each path component
+        /// like the default ignores
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        extra_ignores: Vec<String>,
+        #[serde(default)]
+        prune_empty_dirs: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ignore_file: Option<PathBuf>,
+        /// Gitignore-syntax patterns, `!pattern` included, applied after
+        /// `ignore_file`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        exclude_globs: Vec<String>,
+        /// Apply `.gitignore` files from the root and its parents
+        #[serde(default)]
+        parent_gitignores: bool,
+        /// Where the parent search stops instead of the repository root
+        #[serde(skip_serializing_if = "Option::is_none")]
+        gitignore_boundary: Option<PathBuf>,
+        /// Leave out generated and vendored files, listing them as skipped
+        #[serde(default)]
+        skip_generated: bool,
+        #[serde(default)]
+        entries_kind: EntryKind,
+        /// Report paths with `/` separators on every platform
+        #[serde(default = "default_true")]
+        normalize_separators: bool,
+        /// Leave out binary files; opens every file to check
+        #[serde(default)]
+        text_only: bool,
+        /// Stop listing after this many entries and mark the result
+        /// truncated. The cap counts only entries that end up listed
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_entries: Option<usize>,
+    },
+    Base64 {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_bytes: Option<u64>,
+    },
+    LineBytes {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_line: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        end_line: Option<i32>,
+    },
+    Lines {
+        numbers: Vec<u32>,
+        /// Number the returned lines from 1, counting from the lowest line
+        /// returned, instead of by file position. Gaps between requested
+        /// lines keep their distance.
+        #[serde(default)]
+        relative_numbering: bool,
+    },
+    NestedTree {
+        #[serde(default)]
+        depth: u32,
+    },
+    LineStats,
+    IndentStyle,
+    WhitespaceLint,
+    Section {
+        /// A line equal to this (ignoring trailing whitespace) separates sections
+        separator: String,
+        /// 0-based
+        index: usize,
+        /// Treat `separator` as a regex any separator line must match
+        #[serde(default)]
+        regex: bool,
+    },
+    DotEnv,
+    TreeHash {
+        algorithm: HashAlgorithm,
+    },
+    HashManifest {
+        algorithm: HashAlgorithm,
+        /// Gitignore-syntax rules applied on top of the default ignores
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ignore_file: Option<PathBuf>,
+    },
+    VerifyHash {
+        algorithm: HashAlgorithm,
+        expected: String,
+    },
+    JsonShape {
+        max_depth: u32,
+    },
+    LargestFiles {
+        limit: usize,
+        /// Gitignore-syntax rules applied on top of the default ignores
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ignore_file: Option<PathBuf>,
+    },
+    /// Estimated, not exact: the text is pre-split as the tokenizer does
+    /// but pieces are costed by shape instead of running the BPE merges, so
+    /// results come back with `approximate` set
+    TokenEstimate {
+        model: TokenizerKind,
+    },
+    EnclosingScope {
+        line: u32,
+    },
+    RepoRoot,
+    EditPreview {
+        edits: Vec<LineEdit>,
+    },
+    DiffAgainst {
+        content: String,
+        #[serde(default = "default_diff_context")]
+        context_lines: u32,
+    },
+    Validate {
+        #[serde(default)]
+        repair: bool,
+    },
+    HeadTail {
+        head: u32,
+        tail: u32,
+    },
+    After {
+        pattern: String,
+        #[serde(default)]
+        occurrence: Occurrence,
+    },
+    GlobFind {
+        glob: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        depth: Option<u32>,
+    },
+    NewerThan {
+        /// File whose modification time is the cutoff
+        reference: PathBuf,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        depth: Option<u32>,
+    },
+    GroupBy {
+        key: GroupKey,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        depth: Option<u32>,
+    },
+    LanguageBreakdown {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        depth: Option<u32>,
+        #[serde(default)]
+        skip_generated: bool,
+    },
+    DirTreemap {
+        /// Directory levels below the root to report; totals always cover
+        /// the whole tree
+        #[serde(skip_serializing_if = "Option::is_none")]
+        depth: Option<u32>,
+    },
+    Imports,
+    StripComments,
+    CommentRatio,
+    Skeleton,
+    MatchRatio {
+        pattern: String,
+    },
+    Extract {
+        pattern: String,
+    },
+    FilesContaining {
+        patterns: Vec<String>,
+        #[serde(default)]
+        require: Requirement,
+    },
+    FuzzyFind {
+        query: String,
+        #[serde(default = "default_max_results")]
+        max_results: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        min_score: Option<i64>,
+    },
+    MultiFile {
+        paths: Vec<PathBuf>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        separator: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_threads: Option<usize>,
+    },
+    ChangedSince {
+        since: u64,
+        #[serde(default)]
+        depth: u32,
+    },
+    ChangedBetween {
+        base: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        head: Option<String>,
+    },
+    GitShow {
+        revision: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_line: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        end_line: Option<i32>,
     },
     Search {
         pattern: String,
         #[serde(default = "default_context_lines")]
         context_lines: u32,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        start_line: Option<i32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        end_line: Option<i32>,
+        #[serde(default)]
+        include_summary: bool,
+        #[serde(default)]
+        literal: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_line_bytes: Option<usize>,
+        #[serde(default)]
+        group_by_file: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        depth: Option<u32>,
+        #[serde(default)]
+        anchor_start: bool,
+        #[serde(default)]
+        anchor_end: bool,
+        #[serde(default)]
+        numbered_context: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        expand_to: Option<ExpandScope>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ignore_file: Option<PathBuf>,
+        #[serde(default)]
+        sort_by_match_count: bool,
+        #[serde(default)]
+        dedupe_context_lines: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_matches_per_file: Option<usize>,
+        #[serde(default)]
+        search_archives: bool,
+        #[serde(default)]
+        recent_first: bool,
+        #[serde(default)]
+        skip_generated: bool,
+        /// Report file paths relative to this base; paths outside it are
+        /// left unchanged
+        #[serde(skip_serializing_if = "Option::is_none")]
+        relative_to: Option<PathBuf>,
+        /// Clip matched and context lines to this many bytes, keeping the
+        /// match in view
+        #[serde(skip_serializing_if = "Option::is_none")]
+        max_context_width: Option<usize>,
+        #[serde(default)]
+        render_block: bool,
+        /// Report paths with `/` separators on every platform
+        #[serde(default = "default_true")]
+        normalize_separators: bool,
+        /// Patterns that must all match as well as `pattern`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        all_of: Vec<String>,
+        #[serde(default)]
+        scope: MatchScope,
+        /// Only match inside this kind of syntax; files in languages without
+        /// syntax rules are searched whole, with a warning
+        #[serde(skip_serializing_if = "Option::is_none")]
+        within: Option<SyntaxScope>,
+        /// Overrides `context_lines` when set
+        #[serde(skip_serializing_if = "Option::is_none")]
+        context_mode: Option<ContextMode>,
+        /// Only match where no Unicode word character is directly before or
+        /// after the match
+        #[serde(default)]
+        whole_word: bool,
+        /// Unicode-aware case folding, so `É` matches `é`
+        #[serde(default)]
+        case_insensitive: bool,
+        /// Group by file and add each file's matches and context as
+        /// diff-style hunks; implies `group_by_file`
+        #[serde(default)]
+        hunk_format: bool,
+        /// Match ignoring accents and case, so `cafe` finds `Café`;
+        /// reported lines keep their accents. Accents come off Latin letters
+        /// (U+00C0..=U+024F and U+1E00..=U+1EFF) and combining marks
+        /// (U+0300..=U+036F) are ignored; other scripts match as written
+        #[serde(default)]
+        normalize_unicode: bool,
+        /// Score each match and order by score, highest first; see
+        /// `search::score_matches`. Grouped results order files by their
+        /// best match and each file's matches by score
+        #[serde(default)]
+        rank: bool,
     },
 }
 
+/// Replaces lines `start_line..=end_line` (1-based) with `replacement`;
+/// `end_line = start_line - 1` inserts before `start_line` instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineEdit {
+    pub start_line: u32,
+    pub end_line: u32,
+    pub replacement: String,
+}
+
+/// Which entries a Directory listing returns.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EntryKind {
+    #[default]
+    All,
+    FilesOnly,
+    /// Files are never stat'ed, which keeps folder maps of large trees cheap
+    DirsOnly,
+}
+
+/// Where a search's `all_of` patterns must all appear.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchScope {
+    /// Only lines matching every pattern
+    #[default]
+    Line,
+    /// Lines matching any pattern, from files where every pattern matches
+    File,
+}
+
+/// Which of the patterns a file must contain for `FilesContaining`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Requirement {
+    #[default]
+    All,
+    Any,
+}
+
+/// Kind of source text a search can be limited to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyntaxScope {
+    Comments,
+    /// String and char literals, quotes included
+    Strings,
+    /// Everything that is neither
+    Code,
+}
+
+/// Which of several matching lines to use.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Occurrence {
+    #[default]
+    First,
+    Last,
+}
+
+/// Digest used for content hashes, rendered as lowercase hex.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+    /// 64-bit FNV-1a; fast, for cache keys rather than integrity
+    Fnv1a,
+}
+
+/// BPE encoding whose token counts TokenEstimate approximates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenizerKind {
+    /// GPT-4 / GPT-3.5
+    Cl100k,
+    /// GPT-4o
+    O200k,
+}
+
+/// What GroupBy mode clusters files by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupKey {
+    /// Extension without the dot; files without one share the empty key
+    Extension,
+    /// Root-relative parent directory, `.` for the root itself
+    Directory,
+    /// File name up to the last dot
+    FilenameStem,
+}
+
+/// Syntactic unit a search match's context is widened to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpandScope {
+    Function,
+}
+
+/// Test a line must pass to be returned by Line mode.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LineFilter {
+    /// Lines with something other than whitespace
+    NonBlank,
+    /// Lines at least this many characters long
+    MinLength(usize),
+    /// Lines the regex matches somewhere in
+    Regex(String),
+}
+
+/// A line terminator style.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+    Cr,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+            LineEnding::Cr => "\r",
+        }
+    }
+}
+
+/// How much of the file around a search match is returned as context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContextMode {
+    /// This many lines either side, like `context_lines`
+    Lines(u32),
+    /// The run of non-blank lines the match is in
+    Paragraph,
+}
+
 fn default_context_lines() -> u32 {
     2
 }
 
+fn default_diff_context() -> u32 {
+    3
+}
+
+fn default_max_results() -> usize {
+    50
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FsReadParams {
     pub path: PathBuf,
     #[serde(flatten)]
     pub mode: FsReadMode,
+    /// Caps the serialized result size; see `output_limit`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_output_bytes: Option<usize>,
+    /// Replaces this prefix with `<root>` in the result's paths; see `redact`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redact_paths: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -39,14 +513,375 @@ pub enum FsReadResult {
         content: String,
         total_lines: usize,
         lines_returned: usize,
+        truncated_lines: usize,
+        byte_size: u64,
+        /// The file is 0 bytes, as opposed to holding only blank lines
+        #[serde(default)]
+        is_empty: bool,
+        /// UTF-8, UTF-8-BOM, UTF-16LE/BE or Latin-1; `content` is always
+        /// decoded to UTF-8 without the BOM
+        detected_encoding: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        interpreter: Option<String>,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        lines_collapsed: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        content_hash: Option<String>,
+        /// 1-based file line number of each returned line, when filtered
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        line_numbers: Vec<usize>,
+        /// With `normalize_line_endings`, the most common terminator among
+        /// the returned lines (`None` when they have none) and whether they
+        /// used any other than the requested one. Counted before
+        /// `max_output_bytes` drops any lines
+        #[serde(skip_serializing_if = "Option::is_none")]
+        original_line_ending: Option<LineEnding>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        line_endings_normalized: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        tabs_expanded: usize,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
     },
     Directory {
         entries: Vec<FileEntry>,
         total_count: usize,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+        /// Entries that couldn't be read during the walk, left out of `entries`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        skipped: Vec<SkippedEntry>,
     },
     Search {
         matches: Vec<SearchMatch>,
         total_matches: usize,
+        truncated_lines: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        summary: Option<SearchSummary>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<FsWarning>,
+        /// Files that had more matches than `max_matches_per_file`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        capped_files: Vec<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        skipped: Vec<SkippedEntry>,
+    },
+    SearchGrouped {
+        files: Vec<FileMatches>,
+        total_matches: usize,
+        truncated_lines: usize,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        summary: Option<SearchSummary>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<FsWarning>,
+        /// Files that had more matches than `max_matches_per_file`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        capped_files: Vec<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        skipped: Vec<SkippedEntry>,
+    },
+    Base64 {
+        content_b64: String,
+        bytes_read: u64,
+        truncated: bool,
+    },
+    /// Raw bytes of a line range with their original terminators, at
+    /// `byte_start..byte_end` in the file
+    LineBytes {
+        content_b64: String,
+        byte_start: u64,
+        byte_end: u64,
+    },
+    Imports {
+        imports: Vec<ImportStatement>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    NestedTree {
+        root: TreeNode,
+    },
+    Groups {
+        groups: Vec<FileGroup>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    /// Most bytes first; `primary` is the leading language that isn't
+    /// documentation or data (Markdown, JSON, YAML, TOML)
+    LanguageBreakdown {
+        languages: Vec<LanguageStat>,
+        primary: Option<Language>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    DirTreemap {
+        nodes: Vec<DirNode>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    /// Content following the delimiter line; empty when it wasn't `found`
+    After {
+        content: String,
+        total_lines: usize,
+        lines_returned: usize,
+        found: bool,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        delimiter_line: Option<usize>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    RepoRoot {
+        /// Nearest ancestor containing `.git`, `None` outside any repository
+        root: Option<String>,
+        is_worktree: bool,
+    },
+    EditPreview {
+        content: String,
+        /// Unified diff against the file on disk; empty when nothing changed
+        diff: String,
+    },
+    Diff {
+        /// Unified diff; empty when nothing changed
+        diff: String,
+        lines_added: usize,
+        lines_removed: usize,
+    },
+    Validate {
+        is_valid_utf8: bool,
+        /// Byte offset of the first invalid sequence
+        #[serde(skip_serializing_if = "Option::is_none")]
+        first_invalid_offset: Option<usize>,
+        /// Lossy copy, only when `repair` was requested
+        #[serde(skip_serializing_if = "Option::is_none")]
+        repaired: Option<String>,
+        replacements: usize,
+    },
+    HeadTail {
+        head_content: String,
+        tail_content: String,
+        total_lines: usize,
+        /// Lines between the head and the tail
+        omitted: usize,
+    },
+    /// Lengths are in bytes; `longest_line_number` is 0 for an empty file
+    LineStats {
+        /// The file is 0 bytes
+        #[serde(default)]
+        is_empty: bool,
+        total_lines: usize,
+        max_line_length: usize,
+        avg_line_length: f64,
+        longest_line_number: usize,
+    },
+    /// Innermost scope first
+    EnclosingScope {
+        scopes: Vec<ScopeFrame>,
+    },
+    /// `space_width` is `None` when no step between space indents was seen;
+    /// `confidence` is the share of indented lines using the reported style
+    IndentStyle {
+        uses_tabs: bool,
+        space_width: Option<u8>,
+        mixed: bool,
+        confidence: f32,
+    },
+    Section {
+        content: String,
+        /// 1-based line the section's content starts on
+        start_line: usize,
+        lines_returned: usize,
+        total_sections: usize,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    /// 1-based line numbers of each problem; an empty file has none
+    WhitespaceLint {
+        trailing_ws_lines: Vec<usize>,
+        mixed_indent: bool,
+        missing_final_newline: bool,
+        tabs_in_indent_lines: Vec<usize>,
+    },
+    /// Digest over every file's relative path and content under the root
+    TreeHash {
+        digest: String,
+        file_count: usize,
+    },
+    /// `(relative path, digest)` for every file, `/`-separated and by path
+    HashManifest {
+        entries: Vec<(String, String)>,
+        /// Files that couldn't be hashed, left out of `entries`
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        skipped: Vec<SkippedEntry>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    VerifyHash {
+        matches: bool,
+        actual: String,
+    },
+    JsonShape {
+        shape: serde_json::Value,
+    },
+    /// Largest first; ties in path order
+    LargestFiles {
+        files: Vec<FileEntry>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    NewerThan {
+        entries: Vec<FileEntry>,
+        /// The reference didn't exist, so every file was counted as newer
+        #[serde(default)]
+        reference_missing: bool,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    /// `token_count` is an estimate, always flagged `approximate`; `bytes`
+    /// is the UTF-8 content length
+    TokenEstimate {
+        token_count: usize,
+        approximate: bool,
+        char_count: usize,
+        bytes: u64,
+    },
+    /// Pairs in file order; values are raw, without interpolation.
+    /// `malformed_lines` are 1-based.
+    KeyValues {
+        pairs: Vec<(String, String)>,
+        malformed_lines: Vec<usize>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    Lines {
+        lines: Vec<NumberedLine>,
+        out_of_range: Vec<u32>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    MatchRatio {
+        per_file: Vec<FileRatio>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    Extract {
+        rows: Vec<ExtractRow>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    FileSet {
+        files: Vec<String>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    StripComments {
+        content: String,
+        total_lines: usize,
+        removed_comment_lines: usize,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    CommentRatio {
+        comment_lines: usize,
+        code_lines: usize,
+        blank_lines: usize,
+        /// `comment_lines / code_lines`; absent when there is no code
+        #[serde(skip_serializing_if = "Option::is_none")]
+        ratio: Option<f64>,
+        /// Lines were classified by comment prefix, not by lexing the language
+        #[serde(default)]
+        heuristic: bool,
+    },
+    Skeleton {
+        content: String,
+        total_lines: usize,
+        /// Source lines folded into `{ ... }` or `...` markers
+        elided_lines: usize,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    FuzzyFind {
+        matches: Vec<FuzzyMatch>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    ChangedFiles {
+        added: Vec<String>,
+        modified: Vec<String>,
+        deleted: Vec<String>,
+        /// `(from, to)` pairs
+        renamed: Vec<(String, String)>,
+    },
+    GitShow {
+        content: String,
+        total_lines: usize,
+        lines_returned: usize,
+        /// Full hash of the commit `revision` resolved to
+        commit: String,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    MultiFile {
+        files: Vec<FileContent>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        combined: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        warnings: Vec<FsWarning>,
+        #[serde(default, skip_serializing_if = "is_false")]
+        truncated: bool,
+        #[serde(default, skip_serializing_if = "is_zero")]
+        omitted_count: usize,
+    },
+    /// An operation that failed outright, reported in place of its result
+    /// when the caller asked for soft failures
+    Error {
+        warnings: Vec<FsWarning>,
     },
 }
 
@@ -57,6 +892,95 @@ pub struct FileEntry {
     pub size: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub modified: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub change: Option<ChangeKind>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub git_status: Option<GitStatus>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TreeNode {
+    pub name: String,
+    pub is_dir: bool,
+    pub size: u64,
+    /// Directories first, then by name
+    pub children: Vec<TreeNode>,
+}
+
+/// An entry a walk saw but couldn't stat or descend into.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedEntry {
+    pub path: String,
+    pub reason: SkipReason,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SkipReason {
+    /// Removed between being listed and being read, as on a live tree
+    Vanished,
+    /// Any other per-entry IO error, such as a permission error
+    Unreadable,
+    /// Left out by `skip_generated`
+    Generated,
+}
+
+/// A named scope such as a function, class or impl block. Lines are 1-based
+/// and inclusive.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScopeFrame {
+    pub kind: String,
+    pub name: String,
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Files sharing a GroupBy key, sorted by path.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileGroup {
+    pub key: String,
+    pub files: Vec<String>,
+    pub total_bytes: u64,
+}
+
+/// File totals for one directory in a DirTreemap; `path` is relative to
+/// the root, which is `.`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirNode {
+    pub path: String,
+    /// Files directly inside the directory
+    pub direct_files: usize,
+    /// Files anywhere beneath it
+    pub recursive_files: usize,
+    pub recursive_bytes: u64,
+}
+
+/// Files of one detected language in a LanguageBreakdown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LanguageStat {
+    pub language: Language,
+    pub files: usize,
+    pub bytes: u64,
+    /// Share of the bytes of all files with a detected language
+    pub percentage: f64,
+}
+
+/// Directories take the most significant status among their descendants.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GitStatus {
+    Clean,
+    Untracked,
+    Staged,
+    Modified,
+}
+
+/// Best-effort classification from a stat walk; deletions cannot be observed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Modified,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -64,8 +988,221 @@ pub struct SearchMatch {
     pub file_path: String,
     pub line_number: usize,
     pub line_content: String,
-    pub context_before: Vec<String>,
-    pub context_after: Vec<String>,
+    pub context_before: ContextLines,
+    pub context_after: ContextLines,
+    /// Non-overlapping occurrences of the pattern on this line
+    #[serde(default)]
+    pub match_count: usize,
+    /// 1-based byte column where the first occurrence starts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    /// Stable across runs and versions: see `search::match_id`
+    #[serde(default)]
+    pub match_id: String,
+    /// Context and matched line as numbered text, with `render_block`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<String>,
+    /// With `rank`, the weighted sum of `score_factors`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_factors: Option<ScoreFactors>,
+}
+
+/// Inputs to a ranked search's score, each between 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScoreFactors {
+    /// `match_count / (match_count + 1)`
+    pub density: f32,
+    /// `1 / (1 + days since the file was modified / 30)`; 0 without an mtime
+    pub recency: f32,
+    /// `1 / (1 + directories between the search root and the file)`
+    pub shallowness: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileRatio {
+    pub file_path: String,
+    pub matching_lines: usize,
+    pub total_lines: usize,
+    /// `matching_lines / total_lines`, 0 for an empty file
+    pub ratio: f64,
+}
+
+/// Capture groups of one line matched in Extract mode. Groups are keyed by
+/// index (`"1"`, `"2"`, ...) and, when named, by name as well; groups that
+/// didn't participate in the match are left out.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExtractRow {
+    pub file_path: String,
+    pub line_number: usize,
+    pub groups: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImportStatement {
+    pub raw: String,
+    pub module: String,
+    pub line_number: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FuzzyMatch {
+    pub path: String,
+    pub score: i64,
+    /// Char indices into `path` of the matched query characters
+    pub match_indices: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileContent {
+    pub path: String,
+    pub content: String,
+    pub total_lines: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Context around a match: bare strings by default, or numbered lines when
+/// `numbered_context` is requested.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ContextLines {
+    Plain(Vec<String>),
+    Numbered(Vec<ContextLine>),
+}
+
+impl Default for ContextLines {
+    fn default() -> Self {
+        Self::Plain(Vec::new())
+    }
+}
+
+impl ContextLines {
+    pub fn len(&self) -> usize {
+        match self {
+            Self::Plain(lines) => lines.len(),
+            Self::Numbered(lines) => lines.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ContextLine {
+    /// Absolute 1-based line number, consistent with `SearchMatch::line_number`
+    pub line_number: usize,
+    pub content: String,
+    /// With `dedupe_context_lines`, a line already shown for an earlier match
+    /// has empty content and names that match's line number here
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub shown_with: Option<usize>,
+}
+
+/// A line picked out by number in `Lines` mode.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NumberedLine {
+    pub line_number: usize,
+    pub content: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileMatches {
+    pub file_path: String,
+    pub matches: Vec<LineMatch>,
+    /// With `hunk_format`, overlapping or adjacent context merged into
+    /// `@@ -start,len +start,len @@` hunks, one string each. Lines are
+    /// unclipped and all start with a space, so each hunk is a valid
+    /// unified diff; `matches` gives the matched line numbers
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hunks: Vec<String>,
+}
+
+/// A `SearchMatch` without the file path, used inside `FileMatches`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineMatch {
+    pub line_number: usize,
+    pub line_content: String,
+    pub context_before: ContextLines,
+    pub context_after: ContextLines,
+    /// Non-overlapping occurrences of the pattern on this line
+    #[serde(default)]
+    pub match_count: usize,
+    /// 1-based byte column where the first occurrence starts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub column: Option<usize>,
+    /// Stable across runs and versions: see `search::match_id`
+    #[serde(default)]
+    pub match_id: String,
+    /// Context and matched line as numbered text, with `render_block`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub block: Option<String>,
+    /// With `rank`, the weighted sum of `score_factors`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_factors: Option<ScoreFactors>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchSummary {
+    pub files_with_matches: usize,
+    pub top_files: Vec<(String, usize)>,
+    pub matches_per_file_histogram: Vec<HistogramBucket>,
+}
+
+/// Number of files whose match count falls within `min..=max`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    pub min: usize,
+    pub max: usize,
+    pub files: usize,
+}
+
+/// A per-item failure that didn't abort the operation it occurred in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsWarning {
+    pub path: String,
+    pub message: String,
+    pub kind: WarningKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarningKind {
+    NotFound,
+    PermissionDenied,
+    /// Content that couldn't be decoded, typically a binary file
+    InvalidData,
+    Io,
+    /// The file's language isn't supported by the requested feature
+    UnsupportedLanguage,
+    Other,
+}
+
+impl FsWarning {
+    pub fn from_error(path: impl Into<String>, error: &FsReadError) -> Self {
+        let kind = match error {
+            FsReadError::PathNotFound(_) => WarningKind::NotFound,
+            FsReadError::PermissionDenied(_) => WarningKind::PermissionDenied,
+            FsReadError::IoError(e) => match e.kind() {
+                std::io::ErrorKind::NotFound => WarningKind::NotFound,
+                std::io::ErrorKind::PermissionDenied => WarningKind::PermissionDenied,
+                std::io::ErrorKind::InvalidData => WarningKind::InvalidData,
+                _ => WarningKind::Io,
+            },
+            _ => WarningKind::Other,
+        };
+
+        Self {
+            path: path.into(),
+            message: error.to_string(),
+            kind,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -76,6 +1213,18 @@ pub enum FsReadError {
     IoError(std::io::Error),
     InvalidLineRange(i32, i32),
     InvalidPattern(String),
+    ExpectedFile(PathBuf, PathKind),
+    ExpectedDirectory(PathBuf, PathKind),
+    UnsupportedFormat(String),
+    Git(String),
+    InvalidEdit(String),
+    /// Requested section index and how many sections there are
+    SectionOutOfRange(usize, usize),
+    /// Serialized size of a result that couldn't be trimmed to
+    /// `max_output_bytes`, and that limit
+    OutputTooLarge(usize, usize),
+    /// A result that `redact_paths` couldn't rewrite
+    Redaction(String),
 }
 
 impl std::fmt::Display for FsReadError {
@@ -87,12 +1236,73 @@ impl std::fmt::Display for FsReadError {
             Self::IoError(e) => write!(f, "IO error: {}", e),
             Self::InvalidLineRange(s, e) => write!(f, "Invalid line range: {} to {}", s, e),
             Self::InvalidPattern(s) => write!(f, "Invalid pattern: {}", s),
+            Self::ExpectedFile(p, k) => {
+                write!(f, "Expected a file but {} is a {}", p.display(), k)
+            }
+            Self::ExpectedDirectory(p, k) => {
+                write!(f, "Expected a directory but {} is a {}", p.display(), k)
+            }
+            Self::UnsupportedFormat(s) => write!(f, "Unsupported output format: {}", s),
+            Self::Git(s) => write!(f, "Git error: {}", s),
+            Self::InvalidEdit(s) => write!(f, "Invalid edit: {}", s),
+            Self::SectionOutOfRange(index, total) => {
+                write!(f, "Section {} out of range: the file has {} sections", index, total)
+            }
+            Self::OutputTooLarge(size, max) => {
+                write!(f, "Result of {} bytes can't be trimmed to {} bytes", size, max)
+            }
+            Self::Redaction(s) => write!(f, "Could not redact paths: {}", s),
         }
     }
 }
 
 impl std::error::Error for FsReadError {}
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathKind {
+    File,
+    Directory,
+    Other,
+}
+
+impl std::fmt::Display for PathKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File => write!(f, "file"),
+            Self::Directory => write!(f, "directory"),
+            Self::Other => write!(f, "special file"),
+        }
+    }
+}
+
+/// Classifies `path` once (following symlinks) and, when `expected` is given,
+/// fails with `ExpectedFile`/`ExpectedDirectory` naming the actual kind.
+pub fn kind_check(path: &Path, expected: Option<PathKind>) -> Result<PathKind, FsReadError> {
+    let metadata = std::fs::metadata(path).map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => FsReadError::PathNotFound(path.to_path_buf()),
+        std::io::ErrorKind::PermissionDenied => FsReadError::PermissionDenied(path.to_path_buf()),
+        _ => FsReadError::IoError(e),
+    })?;
+
+    let actual = if metadata.is_file() {
+        PathKind::File
+    } else if metadata.is_dir() {
+        PathKind::Directory
+    } else {
+        PathKind::Other
+    };
+
+    match expected {
+        Some(PathKind::Directory) if actual != PathKind::Directory => {
+            Err(FsReadError::ExpectedDirectory(path.to_path_buf(), actual))
+        }
+        Some(kind) if kind != PathKind::Directory && actual != kind => {
+            Err(FsReadError::ExpectedFile(path.to_path_buf(), actual))
+        }
+        _ => Ok(actual),
+    }
+}
+
 impl From<std::io::Error> for FsReadError {
     fn from(err: std::io::Error) -> Self {
         Self::IoError(err)